@@ -0,0 +1,27 @@
+//! Compare the single-threaded and the multi-threaded minimax search on the 4 players board.
+//!
+//! `cargo run --release -p board --features parallel --example parallel_bench [depth] [nb_thread]`
+
+use std::time::Instant;
+
+use board::*;
+
+fn main()
+{
+    let mut args = std::env::args().skip(1);
+    let depth = args.next().and_then(|e| e.parse().ok()).unwrap_or(3);
+    let nb_thread = args.next().and_then(|e| e.parse().ok()).unwrap_or_else(|| std::thread::available_parallelism().map(|e| e.get()).unwrap_or(1));
+
+    let mut b = BoardGame::new_chess_custom(4, true, 0);
+    // a few actions to leave the opening
+    for _ in 0..8 { b.execute(b.minimax_custom(1).action_id.unwrap()); }
+
+    println!("4 players board, depth {}, {} thread(s)", depth, nb_thread);
+    for (name, nb_thread) in [("single-threaded", 1), ("multi-threaded", nb_thread)]
+    {
+        let start = Instant::now();
+        let r = b.minimax_parallel(SearchBudget::depth(depth), &CancellationToken::new(), nb_thread);
+        let elapsed_s = start.elapsed().as_secs_f64();
+        println!("{:>16} : {:>7.3}s, {:>9} actions evaluated ({:>9.0} actions/s), score {:e}, action {}", name, elapsed_s, r.stat_nb_action_evaluated, r.stat_nb_action_evaluated as f64 / elapsed_s, r.score, r.action_id.unwrap());
+    }
+}
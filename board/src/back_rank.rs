@@ -0,0 +1,227 @@
+//! Starting back ranks other than `CHESS_BACK_VALUE` : Chess960 (Fischer Random) and random armies.
+//!
+//! Both are built from a seed, shown by the front ends so a layout can be replayed with `seed = N` in a `GameConfig`.
+
+use super::*;
+
+/// The back rank of `GameLayout::Chess`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum BackRank
+{
+    /// `RNBQKBNR`
+    #[default]
+    Standard,
+    /// One of the 960 positions of Fischer Random, with the bishops on tiles of different colors and the king between the rooks
+    Chess960,
+    /// Random pieces on the whole width of the board between the void corners, with a king in the middle
+    RandomArmy,
+}
+impl BackRank
+{
+    pub const NAMES : [&'static str; 3] = ["standard", "chess960", "random_army"];
+
+    pub fn name(&self) -> &'static str { Self::NAMES[*self as usize] }
+    pub fn from_name(name : &str) -> Option<Self> { [BackRank::Standard, BackRank::Chess960, BackRank::RandomArmy].into_iter().find(|b| b.name() == name) }
+
+    /// The standard back rank don't use the seed
+    pub fn use_seed(&self) -> bool { *self != BackRank::Standard }
+
+    /// The pieces of the back rank from the left to the right, for a rank of `len` tiles.
+    /// The random army can use the `custom` abilities
+    pub fn pieces(&self, len : AtIntType, seed : u64, custom : &[PieceFlags]) -> Vec<PieceFlags>
+    {
+        match self
+        {
+            BackRank::Standard => BoardGame::CHESS_BACK_VALUE.to_vec(),
+            BackRank::Chess960 => BoardGame::chess960_back_rank((seed % 960) as usize).to_vec(),
+            BackRank::RandomArmy => BoardGame::random_army_back_rank(len.max(1) as usize, seed, custom),
+        }
+    }
+}
+
+impl BoardGame
+{
+    /// The Chess960 position number `id` (Scharnagl numbering, 518 is the standard position)
+    pub fn chess960_back_rank(id : usize) -> [PieceFlags; 8]
+    {
+        debug_assert!(id < 960);
+        // the 2 knights on the 5 tiles left after the bishops and the queen
+        const KNIGHTS : [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+        let mut rank = [PieceFlags::ZERO; 8];
+        let mut n = id % 960;
+        rank[2 * (n % 4) + 1] = PieceFlags::AB_CHESS_BISHOP;
+        n /= 4;
+        rank[2 * (n % 4)] = PieceFlags::AB_CHESS_BISHOP;
+        n /= 4;
+
+        let nth_empty = |rank : &[PieceFlags; 8], nth : usize| rank.iter().enumerate().filter(|(_, p)| p.is_empty_ability()).nth(nth).unwrap().0;
+        let queen = nth_empty(&rank, n % 6);
+        rank[queen] = PieceFlags::AB_CHESS_QUEEN;
+        n /= 6;
+
+        let (a, b) = KNIGHTS[n];
+        let (a, b) = (nth_empty(&rank, a), nth_empty(&rank, b));
+        rank[a] = PieceFlags::AB_CHESS_KNIGHT;
+        rank[b] = PieceFlags::AB_CHESS_KNIGHT;
+
+        // the king between the rooks on the 3 tiles left
+        for p in [PieceFlags::AB_CHESS_ROOK, PieceFlags::AB_CHESS_KING.with_flag(PieceFlags::CROWN), PieceFlags::AB_CHESS_ROOK]
+        {
+            let idx = nth_empty(&rank, 0);
+            rank[idx] = p;
+        }
+        rank
+    }
+
+    /// A random back rank of `len` pieces with a king in the middle.
+    /// The other pieces are knights, bishops, rooks, `custom` abilities and at most one queen for 8 tiles
+    pub fn random_army_back_rank(len : usize, seed : u64, custom : &[PieceFlags]) -> Vec<PieceFlags>
+    {
+        let mut rng = Random::new(seed);
+        // (piece, weight)
+        let mut pool = vec![(PieceFlags::AB_CHESS_QUEEN, 1), (PieceFlags::AB_CHESS_ROOK, 2), (PieceFlags::AB_CHESS_BISHOP, 2), (PieceFlags::AB_CHESS_KNIGHT, 3)];
+        pool.extend(custom.iter().map(|c| (*c, 2)));
+        let mut nb_queen_left = (len / 8).max(1);
+
+        let king = if len >= 2 { len / 2 - 1 + rng.gen_range(0..2) } else { 0 };
+        (0..len).map(|idx|
+        {
+            if idx == king { return PieceFlags::AB_CHESS_KING.with_flag(PieceFlags::CROWN); }
+            let candidates : Vec<(PieceFlags, usize)> = pool.iter().copied().filter(|(p, _)| *p != PieceFlags::AB_CHESS_QUEEN || nb_queen_left > 0).collect();
+            let mut pick = rng.gen_range(0..candidates.iter().map(|(_, w)| w).sum::<usize>());
+            let piece = candidates.iter().find(|(_, w)| if pick < *w { true } else { pick -= w; false }).unwrap().0;
+            if piece == PieceFlags::AB_CHESS_QUEEN { nb_queen_left -= 1; }
+            piece
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn back_rank_chess960()
+    {
+        assert_eq!(BoardGame::chess960_back_rank(518), BoardGame::CHESS_BACK_VALUE);
+        let mut all = vec![];
+        for id in 0..960
+        {
+            let rank = BoardGame::chess960_back_rank(id);
+            let find = |ability : PieceFlags| rank.iter().enumerate().filter(|(_, p)| p.ability() == ability).map(|(i, _)| i).collect::<Vec<_>>();
+            let (bishops, rooks, king) = (find(PieceFlags::AB_CHESS_BISHOP), find(PieceFlags::AB_CHESS_ROOK), find(PieceFlags::AB_CHESS_KING)[0]);
+            assert_eq!(bishops[0] % 2 + bishops[1] % 2, 1);
+            assert!(rooks[0] < king && king < rooks[1]);
+            assert_eq!((find(PieceFlags::AB_CHESS_QUEEN).len(), find(PieceFlags::AB_CHESS_KNIGHT).len()), (1, 2));
+            all.push(rank);
+        }
+        all.sort_by_key(|r| r.map(|p| p.ability().0));
+        all.dedup();
+        assert_eq!(all.len(), 960);
+    }
+
+    #[test]
+    fn back_rank_chess960_castling()
+    {
+        // BBQNNRKR : the king go from g1 to c1 and the rook from f1 to d1
+        let mut b = BoardGame::from_config(&GameConfig::chess().with_back_rank(BackRank::Chess960).with_seed(960)).unwrap();
+        assert_eq!(b.layout_seed, Some(960));
+        assert!(b[at(0, 0)].is_also_chess_bishop() && b[at(6, 0)].is_also_chess_king() && b[at(7, 7)].is_also_chess_rook());
+        for san in ["Nc3", "Nc6", "Nf3", "Nf6", "d3", "d6", "Qd2", "Qd7"] { b.execute(b.san_to_action_id(san).unwrap()); }
+        let id = b.san_to_action_id("O-O-O").unwrap();
+        assert_eq!(id, ActionID::Move(at(6, 0), at(2, 0)));
+        b.execute(id);
+        assert!(b[at(2, 0)].is_also_chess_king() && b[at(3, 0)].is_also_chess_rook() && b[at(5, 0)].is_empty_ability() && b[at(6, 0)].is_empty_ability());
+        assert_eq!(b.zobrist, b.zobrist_compute());
+
+        // the king move 1 tile : the action is the king going on its rook
+        let mut b = BoardGame::from_fen("4k3/8/8/8/8/8/8/RK5R w KQ - 0 1", Relics::ZERO).unwrap();
+        assert_eq!(b.san_to_action_id("O-O-O").unwrap(), ActionID::Move(at(1, 0), at(0, 0)));
+        assert_eq!(b.san_to_action_id("O-O").unwrap(), ActionID::Move(at(1, 0), at(6, 0)));
+        b.execute(ActionID::Move(at(1, 0), at(0, 0)));
+        assert!(b[at(2, 0)].is_also_chess_king() && b[at(3, 0)].is_also_chess_rook() && b[at(0, 0)].is_empty_ability());
+        assert_eq!(b.zobrist, b.zobrist_compute());
+
+        // the king stay on g1, only the rook move
+        let mut b = BoardGame::from_fen("4k3/8/8/8/8/8/8/6KR w K - 0 1", Relics::ZERO).unwrap();
+        let id = b.san_to_action_id("O-O").unwrap();
+        assert_eq!(id, ActionID::Move(at(6, 0), at(7, 0)));
+        assert_eq!(b.action_id_to_san(id).unwrap(), "O-O");
+        b.execute(id);
+        assert!(b[at(6, 0)].is_also_chess_king() && b[at(5, 0)].is_also_chess_rook() && b[at(7, 0)].is_empty_ability());
+        assert_eq!(b.zobrist, b.zobrist_compute());
+        assert_eq!(b.to_fen().unwrap(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+    }
+
+    #[test]
+    fn back_rank_castling_on_target()
+    {
+        // the rook is already on d1, only the king move
+        let mut b = BoardGame::from_fen("4k3/8/8/8/8/8/8/3RK3 w Q - 0 1", Relics::ZERO).unwrap();
+        let id = b.san_to_action_id("O-O-O").unwrap();
+        assert_eq!(id, ActionID::Move(at(4, 0), at(2, 0)));
+        b.execute(id);
+        assert!(b[at(2, 0)].is_also_chess_king() && b[at(3, 0)].is_also_chess_rook() && b[at(4, 0)].is_empty_ability());
+        assert_eq!(b.zobrist, b.zobrist_compute());
+
+        // the king is already on c1, the rook jump over it
+        let mut b = BoardGame::from_fen("4k3/8/8/8/8/8/8/1RK5 w Q - 0 1", Relics::ZERO).unwrap();
+        let id = b.san_to_action_id("O-O-O").unwrap();
+        assert_eq!(id, ActionID::Move(at(2, 0), at(1, 0)));
+        b.execute(id);
+        assert!(b[at(2, 0)].is_also_chess_king() && b[at(3, 0)].is_also_chess_rook() && b[at(1, 0)].is_empty_ability());
+        assert_eq!(b.zobrist, b.zobrist_compute());
+        assert_eq!(b.to_fen().unwrap(), "4k3/8/8/8/8/8/8/2KR4 b - - 1 1");
+    }
+
+    #[test]
+    fn back_rank_castling_wide_board()
+    {
+        // the back rank is centered on the side of each team
+        let c = GameConfig::chess_custom(2, true, Relics::ZERO).with_size(at(10, 8));
+        assert_eq!(c.build().unwrap().team_data[Team::White].back_rank, Some((1, 8)));
+        assert_eq!(c.with_back_rank(BackRank::RandomArmy).build().unwrap().team_data[Team::White].back_rank, Some((0, 10)));
+        assert_eq!(BoardGame::new_chess_custom(4, true, Relics::ZERO).team_data[Team::Yellow].back_rank, Some((3, 8)));
+
+        // without a layout, the back rank is the whole rank : the king go on the c or i file of a 10 tiles rank
+        let b = BoardGame::from_fen("r3k4r/10/10/10/10/10/10/R3K4R w KQkq - 0 1", Relics::ZERO).unwrap();
+        let mut king_side = b.clone();
+        king_side.execute(king_side.san_to_action_id("O-O").unwrap());
+        assert!(king_side[at(8, 0)].is_also_chess_king() && king_side[at(7, 0)].is_also_chess_rook() && king_side[at(9, 0)].is_empty_ability());
+        let mut queen_side = b.clone();
+        queen_side.execute(queen_side.san_to_action_id("O-O-O").unwrap());
+        assert!(queen_side[at(2, 0)].is_also_chess_king() && queen_side[at(3, 0)].is_also_chess_rook() && queen_side[at(0, 0)].is_empty_ability());
+
+        // a back rank of 8 tiles centered on the 10 tiles
+        let b = BoardGameFixedTime::from_fen_with_setup("1r3k2r1/10/10/10/10/10/10/1R3K2R1 w KQkq - 0 1", [Relics::ZERO; Team::LENGHT], |data|
+        {
+            for t in [Team::White, Team::Black] { data.team_data[t].back_rank = Some((1, 8)); }
+        }).unwrap();
+        assert_eq!(b.san_to_action_id("O-O").unwrap(), ActionID::Move(at(5, 0), at(7, 0)));
+        assert_eq!(b.san_to_action_id("O-O-O").unwrap(), ActionID::Move(at(5, 0), at(3, 0)));
+    }
+
+    #[test]
+    fn back_rank_random_army()
+    {
+        let a = BoardGame::random_army_back_rank(12, 42, &[]);
+        assert_eq!(a, BoardGame::random_army_back_rank(12, 42, &[]));
+        assert_eq!(a.len(), 12);
+        assert_eq!(a.iter().filter(|p| p.is_also_chess_king()).count(), 1);
+        assert!(a[5].is_also_chess_king() || a[6].is_also_chess_king());
+        assert!(a.iter().filter(|p| p.ability() == PieceFlags::AB_CHESS_QUEEN).count() <= 1);
+        assert!((0..16).any(|seed| BoardGame::random_army_back_rank(12, seed, &[]) != a));
+
+        // the same army for the 4 teams, on the width between the void corners
+        let b = BoardGame::from_config(&GameConfig::chess_custom(4, true, Relics::ZERO).with_back_rank(BackRank::RandomArmy).with_seed(7)).unwrap();
+        let army = BoardGame::random_army_back_rank(8, 7, &[]);
+        for i in 0..8
+        {
+            assert_eq!(b[at(3 + i, 0)].ability(), army[i as usize].ability());
+            assert_eq!(b[at(0, 3 + i)].ability(), army[i as usize].ability());
+        }
+        assert_eq!(b.layout_seed, Some(7));
+    }
+}
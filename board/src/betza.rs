@@ -0,0 +1,530 @@
+//! Movements of the pieces written in Betza notation.
+//!
+//! Each ability of `PieceFlags` moves with a `PieceMoves` : the standard abilities use `PieceMoves::STANDARD_BETZA`,
+//! the `PieceFlags::AB_CUSTOM` abilities are defined by each game in `BoardGameNotStarted::custom_abilities` (see `GameConfig`).
+//!
+//! A movement is a list of atoms, each one optionally preceded by modifiers and followed by a range :
+//!
+//! - Atoms : `W` (0,1), `F` (1,1), `D` (0,2), `N` (1,2), `A` (2,2), `H` (0,3), `C` (1,3), `Z` (2,3), `G` (3,3)
+//!   and the shortcuts `R` (`WW`), `B` (`FF`), `Q` (`WWFF`), `K` (`WF`). `O` is the castling with a rook.
+//! - Range : a doubled atom is a rider (`NN` the nightrider), a number is the maximal number of steps (`W2`). A leaper by default.
+//! - Modifiers : `m` move only, `c` capture only, `i` only if the piece never moved, `e` en passant capture,
+//!   `n` lame (the tiles in between must be empty), `p` hop over a piece then move or capture (cannon),
+//!   `g` land just after the first piece (grasshopper), `x` checkers jump that capture the jumped enemy and can be chained during the turn.
+//!   For `p` and `g`, the range is the maximal distance to the jumped piece.
+//! - Directions, relative to the team direction : `f` forward, `b` backward, `l` left, `r` right, `v` (`fb`), `s` (`lr`).
+//!   The orthogonal moves keep any direction given. The other moves must match the vertical and the horizontal directions given (`flF` is only forward left).
+//!
+//! Ex : the chess pawn is `mfWcefFifmnD`, the archbishop `BN`, the grasshopper `gQ`.
+
+use super::*;
+use std::sync::{Arc, OnceLock};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum BetzaJump
+{
+    /// Move to the destination, the riders stop on the first piece
+    #[default]
+    Leap,
+    /// `n` : the tiles in between must be empty
+    Lame,
+    /// `p` : jump over a piece, then move or capture behind it
+    Hop,
+    /// `g` : land just after the first piece
+    Grasshopper,
+    /// `x` : jump over an adjacent enemy to the empty tile behind it and capture it
+    Checkers,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BetzaMove
+{
+    /// Relative to the team direction : `x` to the right, `y` forward
+    pub deltas : SmallVec<[At; 8]>,
+    /// Maximal number of steps. 1 for a leaper, `BetzaMove::UNLIMITED` for a rider
+    pub range : AtIntType,
+    pub can_move : bool,
+    pub can_capture : bool,
+    pub initial_only : bool,
+    pub en_passant : bool,
+    pub jump : BetzaJump,
+    /// The deltas depend on the team direction
+    pub directional : bool,
+}
+
+impl BetzaMove
+{
+    pub const UNLIMITED : AtIntType = AtIntType::MAX;
+
+    /// The 4 or 8 deltas of an atom, in the same order as the rotation tricks of the knight
+    fn atom_deltas(atom : At) -> SmallVec<[At; 8]>
+    {
+        let mut deltas = SmallVec::new();
+        let mut delta = atom;
+        for _ in 0..4 { deltas.push(delta); delta = delta.y_rx(); }
+        if atom.x != 0 && atom.x != atom.y
+        {
+            delta.y = -delta.y;
+            for _ in 0..4 { deltas.push(delta); delta = delta.y_rx(); }
+        }
+        deltas
+    }
+
+    fn new(atom : At, range : AtIntType, modifiers : &str) -> Result<Self, BetzaError>
+    {
+        let have = |c : char| modifiers.contains(c);
+
+        let jumps : Vec<BetzaJump> = [('n', BetzaJump::Lame), ('p', BetzaJump::Hop), ('g', BetzaJump::Grasshopper), ('x', BetzaJump::Checkers)]
+            .into_iter().filter(|(c, _)| have(*c)).map(|(_, j)| j).collect();
+        if jumps.len() > 1 { return Err(BetzaError::IncompatibleModifiers); }
+
+        let (f, b) = (have('f') || have('v'), have('b') || have('v'));
+        let (l, r) = (have('l') || have('s'), have('r') || have('s'));
+        let directional = f || b || l || r;
+        let matches = |d : At| -> bool
+        {
+            let vertical   = (d.y > 0 && f) || (d.y < 0 && b);
+            let horizontal = (d.x > 0 && r) || (d.x < 0 && l);
+            if !directional { return true; }
+            if d.x == 0 || d.y == 0 { return vertical || horizontal; }
+            (vertical || !(f || b)) && (horizontal || !(l || r))
+        };
+        let deltas : SmallVec<[At; 8]> = Self::atom_deltas(atom).into_iter().filter(|d| matches(*d)).collect();
+        if deltas.is_empty() { return Err(BetzaError::IncompatibleModifiers); }
+
+        Ok(Self
+        {
+            deltas,
+            range,
+            can_move: !have('c') || have('m'),
+            can_capture: !have('m') || have('c'),
+            initial_only: have('i'),
+            en_passant: have('e'),
+            jump: jumps.first().copied().unwrap_or_default(),
+            directional,
+        })
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct PieceMoves
+{
+    pub moves : Vec<BetzaMove>,
+    /// `O` : castling with a piece with the chess rook ability
+    pub castling : bool,
+    betza : String,
+}
+
+impl PieceMoves
+{
+    /// In the order of the bits of the standard abilities : bishop, rook, knight, pawn, king, checkers king, checkers pawn
+    pub const STANDARD_BETZA : [&'static str; PieceFlags::STANDARD_ABILITY_COUNT as usize] = ["B", "R", "N", "mfWcefFifmnD", "KO", "mFxF", "mfFxfF"];
+
+    const MODIFIERS : &'static str = "mcienpgxfblrvs";
+
+    /// The movements of the standard abilities, parsed once
+    pub fn standard() -> &'static [PieceMoves]
+    {
+        static STANDARD : OnceLock<Vec<PieceMoves>> = OnceLock::new();
+        STANDARD.get_or_init(|| Self::STANDARD_BETZA.iter().map(|b| Self::from_betza(b).unwrap()).collect())
+    }
+
+    /// The base deltas `(x, y)` of an atom and its range
+    fn atoms(c : char) -> Option<(&'static [(AtIntType, AtIntType)], AtIntType)>
+    {
+        Some(match c
+        {
+            'W' => (&[(0, 1)], 1),
+            'F' => (&[(1, 1)], 1),
+            'D' => (&[(0, 2)], 1),
+            'N' => (&[(1, 2)], 1),
+            'A' => (&[(2, 2)], 1),
+            'H' => (&[(0, 3)], 1),
+            'C' => (&[(1, 3)], 1),
+            'Z' => (&[(2, 3)], 1),
+            'G' => (&[(3, 3)], 1),
+            'R' => (&[(0, 1)], BetzaMove::UNLIMITED),
+            'B' => (&[(1, 1)], BetzaMove::UNLIMITED),
+            'Q' => (&[(0, 1), (1, 1)], BetzaMove::UNLIMITED),
+            'K' => (&[(0, 1), (1, 1)], 1),
+            _ => return None,
+        })
+    }
+
+    pub fn from_betza(betza : &str) -> Result<Self, BetzaError>
+    {
+        let betza = betza.trim();
+        let chars : Vec<char> = betza.chars().collect();
+        let mut s = Self { betza: betza.to_owned(), ..Default::default() };
+        let mut modifiers = String::new();
+        let mut idx = 0;
+
+        while idx < chars.len()
+        {
+            let c = chars[idx];
+            idx += 1;
+
+            if Self::MODIFIERS.contains(c) { modifiers.push(c); continue; }
+            if c == 'O' && modifiers.is_empty() { s.castling = true; continue; }
+            if c == ' ' && modifiers.is_empty() { continue; }
+
+            let (atoms, mut range) = Self::atoms(c).ok_or(BetzaError::UnexpectedChar(idx - 1, c))?;
+            let digits : String = chars[idx..].iter().take_while(|d| d.is_ascii_digit()).collect();
+            if !digits.is_empty()
+            {
+                range = digits.parse().ok().filter(|r| *r > 0).ok_or(BetzaError::InvalidRange(idx))?;
+                idx += digits.len();
+            }else if range == 1 && chars.get(idx) == Some(&c) && atoms.len() == 1
+            {
+                range = BetzaMove::UNLIMITED;
+                idx += 1;
+            }
+
+            for (x, y) in atoms { s.moves.push(BetzaMove::new(at(*x, *y), range, &modifiers)?); }
+            modifiers.clear();
+        }
+
+        if !modifiers.is_empty() { return Err(BetzaError::MissingAtom); }
+        if s.moves.is_empty() && !s.castling { return Err(BetzaError::Empty); }
+        Ok(s)
+    }
+
+    /// Contains a checkers jump : after the first action of the turn, only the piece that moved can continue to capture
+    pub fn is_chained_capture(&self) -> bool { self.moves.iter().any(|m| m.jump == BetzaJump::Checkers) }
+
+    /// The standard abilities that move the most like this one, to draw it
+    pub fn look_like(&self) -> PieceFlags
+    {
+        let mut flags = PieceFlags::ZERO;
+        for m in self.moves.iter()
+        {
+            let d = m.deltas[0];
+            flags |= match (m.range > 1, d.x.abs().min(d.y.abs()), d.x.abs().max(d.y.abs()))
+            {
+                (_, _, _) if m.jump == BetzaJump::Checkers => PieceFlags::AB_DAME_KING,
+                (true, 0, _) => PieceFlags::AB_CHESS_ROOK,
+                (true, a, b) if a == b => PieceFlags::AB_CHESS_BISHOP,
+                (false, _, 1) if !m.can_capture || !m.can_move => PieceFlags::AB_CHESS_PAWN,
+                (false, _, 1) => PieceFlags::AB_CHESS_KING,
+                _ => PieceFlags::AB_CHESS_KNIGHT,
+            };
+        }
+        flags
+    }
+}
+impl Display for PieceMoves { fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult { write!(f, "{}", self.betza) }}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BetzaError
+{
+    /// Index and char
+    UnexpectedChar(usize, char),
+    /// The range at this index is not a positive number
+    InvalidRange(usize),
+    /// Modifiers after the last atom
+    MissingAtom,
+    IncompatibleModifiers,
+    Empty,
+}
+impl Display for BetzaError
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult
+    {
+        match self
+        {
+            BetzaError::UnexpectedChar(idx, c) => write!(f, "unexpected char '{}' at {}", c, idx),
+            BetzaError::InvalidRange(idx) => write!(f, "invalid range at {}", idx),
+            BetzaError::MissingAtom => write!(f, "missing an atom after the modifiers"),
+            BetzaError::IncompatibleModifiers => write!(f, "incompatible modifiers"),
+            BetzaError::Empty => write!(f, "no movement"),
+        }
+    }
+}
+impl std::error::Error for BetzaError {}
+
+/// An ability of the `PieceFlags::AB_CUSTOM` slots, defined by the game
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CustomAbility
+{
+    pub name : String,
+    pub moves : PieceMoves,
+}
+impl CustomAbility
+{
+    pub fn new(name : &str, betza : &str) -> Result<Self, BetzaError> { Ok(Self { name: name.to_owned(), moves: PieceMoves::from_betza(betza)? }) }
+}
+
+pub type CustomAbilities = Arc<Vec<CustomAbility>>;
+
+impl BoardGameNotStarted
+{
+    /// The custom abilities are in the `PieceFlags::AB_CUSTOM` slots in this order
+    pub fn with_custom_abilities(mut self, abilities : Vec<CustomAbility>) -> Self { self.custom_abilities = Arc::new(abilities); self }
+
+    /// The movements of the ability at the bit `ability_idx`. `None` for a custom ability not defined in this game
+    pub fn ability_moves(&self, ability_idx : usize) -> Option<&PieceMoves>
+    {
+        match ability_idx.checked_sub(PieceFlags::STANDARD_ABILITY_COUNT as usize)
+        {
+            None => PieceMoves::standard().get(ability_idx),
+            Some(idx) => self.custom_abilities.get(idx).map(|a| &a.moves),
+        }
+    }
+
+    /// Replace the custom abilities by the standard abilities that look like them
+    pub fn look_like_standard(&self, flags : PieceFlags) -> PieceFlags
+    {
+        let mut look = flags.without_flag(PieceFlags::AB_CUSTOM_MASK);
+        for (ability, custom) in PieceFlags::AB_CUSTOM.iter().zip(self.custom_abilities.iter())
+        {
+            if flags.have_flag(*ability) { look |= custom.moves.look_like(); }
+        }
+        look
+    }
+}
+
+impl BoardGameFixedTime
+{
+    pub(crate) fn actions_piece_moves(&self, actions : &mut Actions, src : At, moves : &PieceMoves, apply_anticipation : bool)
+    {
+        let mut only_chained_capture = false;
+        if moves.is_chained_capture() && self.current_nb_action_this_turn > 0
+        {
+            if self[src].last_turn_moved != self.turn { return; }
+            only_chained_capture = true;
+        }
+
+        for m in moves.moves.iter()
+        {
+            if only_chained_capture && m.jump != BetzaJump::Checkers { continue; }
+            if m.initial_only && self[src].already_move() { continue; }
+
+            if m.directional
+            {
+                for t in self[src].iter_team() { self.actions_betza_move(actions, src, m, self.team_direction(t)); }
+            }else
+            {
+                self.actions_betza_move(actions, src, m, At::Y);
+            }
+        }
+
+        if moves.castling && !only_chained_capture { self.actions_castling(actions, src, apply_anticipation); }
+    }
+
+    fn actions_betza_move(&self, actions : &mut Actions, src : At, m : &BetzaMove, forward : At)
+    {
+        let right = forward.y_x();
+        for d in m.deltas.iter()
+        {
+            let delta = right * d.x + forward * d.y;
+            match m.jump
+            {
+                BetzaJump::Leap | BetzaJump::Lame => self.actions_betza_ride(actions, src, m, delta),
+                BetzaJump::Hop => self.actions_betza_hop(actions, src, m, delta),
+                BetzaJump::Grasshopper => self.actions_betza_grasshopper(actions, src, m, delta),
+                BetzaJump::Checkers => self.actions_betza_checkers(actions, src, delta),
+            }
+            if m.en_passant { self.actions_en_passant(actions, src, delta, forward); }
+        }
+    }
+
+    /// Move or capture on `dest` if the move allow it
+    fn betza_land(&self, actions : &mut Actions, src : At, dest : At, m : &BetzaMove)
+    {
+        if !m.can_move && !self.are_capturable_enemy(src, dest) { return; }
+        self.can_move_to_custom(actions, src, dest, m.can_capture, -1);
+    }
+
+    /// The tiles between `pos` and `pos + delta` are empty. For the oblique deltas, this is the first orthogonal step
+    fn betza_path_is_empty(&self, pos : At, delta : At) -> bool
+    {
+        let (ax, ay) = (delta.x.abs(), delta.y.abs());
+        if ax == 0 || ay == 0 || ax == ay
+        {
+            let unit = at(delta.x.signum(), delta.y.signum());
+            (1..ax.max(ay)).all(|i| self.is_empty_tile(pos + unit * i))
+        }else
+        {
+            let step = if ax > ay { at(delta.x.signum(), 0) } else { at(0, delta.y.signum()) };
+            self.is_empty_tile(pos + step)
+        }
+    }
+
+    fn actions_betza_ride(&self, actions : &mut Actions, src : At, m : &BetzaMove, delta : At)
+    {
+        let mut dest = src;
+        for _ in 0..m.range
+        {
+            if m.jump == BetzaJump::Lame && !self.betza_path_is_empty(dest, delta) { return; }
+            dest += delta;
+            if !self.is_inside(dest) { return; }
+            self.betza_land(actions, src, dest, m);
+            if !self.is_empty_tile(dest) { return; }
+        }
+    }
+
+    fn actions_betza_hop(&self, actions : &mut Actions, src : At, m : &BetzaMove, delta : At)
+    {
+        let mut dest = src;
+        for _ in 0..m.range
+        {
+            dest += delta;
+            if !self.is_inside(dest) { return; }
+            if self.is_empty_tile(dest) { continue; }
+
+            loop
+            {
+                dest += delta;
+                if !self.is_inside(dest) { return; }
+                self.betza_land(actions, src, dest, m);
+                if !self.is_empty_tile(dest) { return; }
+            }
+        }
+    }
+
+    fn actions_betza_grasshopper(&self, actions : &mut Actions, src : At, m : &BetzaMove, delta : At)
+    {
+        let mut dest = src;
+        for _ in 0..m.range
+        {
+            dest += delta;
+            if !self.is_inside(dest) { return; }
+            if self.is_empty_tile(dest) { continue; }
+
+            if self.is_inside(dest + delta) { self.betza_land(actions, src, dest + delta, m); }
+            return;
+        }
+    }
+
+    fn actions_betza_checkers(&self, actions : &mut Actions, src : At, delta : At)
+    {
+        let (jumped, dest) = (src + delta, src + delta * 2);
+        if !self.is_inside(jumped) || !self.are_capturable_enemy(src, jumped) || !self.is_inside(dest) || !self[dest].is_none_flag() { return; }
+
+        let _ok = self.can_move_to_custom(actions, src, dest, false, 0).can_move;
+        debug_assert!(_ok);
+        actions.last_mut().unwrap().push(self, UnitAction::Capture(None, jumped));
+    }
+
+    /// Capture a chess pawn that moved 2 tiles during the last turn, next to `src`, by moving to `src + delta`
+    fn actions_en_passant(&self, actions : &mut Actions, src : At, delta : At, forward : At)
+    {
+        let dest = src + delta;
+        let attack = dest - forward;
+        if attack == src || !self.is_inside(attack) || !self.is_inside(dest) || !self.are_capturable_enemy(src, attack) { return; }
+
+        let pawn = &self[attack];
+        if pawn.is_also_chess_pawn() && self.was_moved_last_turn_or_this_turn(attack) && pawn.distance_travel_total == Saturating(2) && pawn.nb_time_moved == Saturating(1)
+        {
+            // the destination is not always empty (relics, more than 2 teams)
+            if !self.can_move_to(actions, src, dest).can_move { return; }
+            actions.last_mut().unwrap().push(self, UnitAction::Capture(None, attack));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn destinations(b : &BoardGame, src : At) -> Vec<At>
+    {
+        let mut dest : Vec<At> = b.actions.iter().filter(|a| a.id.src() == src).map(|a| a.id.dest()).collect();
+        dest.sort_by_key(|d| (d.y, d.x));
+        dest.dedup();
+        dest
+    }
+
+    /// The custom ability is in the first slot
+    fn custom_game(placement : &str, betza : &str) -> BoardGame
+    {
+        let fen = format!("8x8 {} w 1 0 - 1 1 0 w,b", placement);
+        let custom = vec![CustomAbility::new("custom", betza).unwrap()];
+        BoardGame::new_from_fixed_time(BoardGameFixedTime::from_chessito_fen_with_setup(&fen, |b| b.custom_abilities = Arc::new(custom)).unwrap())
+    }
+
+    #[test]
+    fn betza_parser()
+    {
+        let pawn = PieceMoves::from_betza("mfWcefFifmnD").unwrap();
+        assert_eq!(pawn.moves.len(), 3);
+        assert_eq!(pawn.moves[0].deltas.as_slice(), &[at(0, 1)]);
+        assert!(pawn.moves[0].can_move && !pawn.moves[0].can_capture);
+        assert_eq!(pawn.moves[1].deltas.as_slice(), &[at(1, 1), at(-1, 1)]);
+        assert!(pawn.moves[1].en_passant && !pawn.moves[1].can_move);
+        assert!(pawn.moves[2].initial_only && pawn.moves[2].jump == BetzaJump::Lame);
+
+        let nightrider = PieceMoves::from_betza("NN").unwrap();
+        assert_eq!(nightrider.moves.len(), 1);
+        assert_eq!(nightrider.moves[0].deltas.len(), 8);
+        assert_eq!(nightrider.moves[0].range, BetzaMove::UNLIMITED);
+
+        assert_eq!(PieceMoves::from_betza("Q").unwrap().moves.len(), 2);
+        assert_eq!(PieceMoves::from_betza("W3").unwrap().moves[0].range, 3);
+        assert_eq!(PieceMoves::from_betza("flF").unwrap().moves[0].deltas.as_slice(), &[at(-1, 1)]);
+        assert_eq!(PieceMoves::from_betza("fsW").unwrap().moves[0].deltas.len(), 3);
+        assert!(PieceMoves::from_betza("KO").unwrap().castling);
+        assert_eq!(PieceMoves::from_betza("BN").unwrap().to_string(), "BN");
+
+        assert_eq!(PieceMoves::from_betza("mfY"), Err(BetzaError::UnexpectedChar(2, 'Y')));
+        assert_eq!(PieceMoves::from_betza("W0"), Err(BetzaError::InvalidRange(1)));
+        assert_eq!(PieceMoves::from_betza("Nm"), Err(BetzaError::MissingAtom));
+        assert_eq!(PieceMoves::from_betza("pgR"), Err(BetzaError::IncompatibleModifiers));
+        assert_eq!(PieceMoves::from_betza(""), Err(BetzaError::Empty));
+
+        // range overflow, 2 kinds of jump, modifiers before the castling or in the middle of a range, no direction left
+        assert_eq!(PieceMoves::from_betza("W99999"), Err(BetzaError::InvalidRange(1)));
+        assert_eq!(PieceMoves::from_betza("npR"), Err(BetzaError::IncompatibleModifiers));
+        assert_eq!(PieceMoves::from_betza("mO"), Err(BetzaError::UnexpectedChar(1, 'O')));
+        assert_eq!(PieceMoves::from_betza("R1m2"), Err(BetzaError::UnexpectedChar(3, '2')));
+        assert_eq!(PieceMoves::from_betza("lfW").unwrap().moves[0].deltas.len(), 2);
+        assert_eq!(PieceMoves::from_betza("  "), Err(BetzaError::Empty));
+        assert!(PieceMoves::from_betza("O").unwrap().moves.is_empty());
+    }
+
+    #[test]
+    fn betza_custom_pieces()
+    {
+        let (d4, center) = (at(3, 3), "4k3/8/8/8/3{w:1}4/8/8/4K3");
+        assert_eq!(destinations(&custom_game(center, "BN"), d4).len(), 13 + 8);
+        assert_eq!(destinations(&custom_game(center, "RN"), d4).len(), 14 + 8);
+        // e1 is the king
+        assert_eq!(destinations(&custom_game(center, "C"), d4), vec![at(2, 0), at(0, 2), at(6, 2), at(0, 4), at(6, 4), at(2, 6), at(4, 6)]);
+
+        let nightrider = custom_game("4k3/8/8/8/8/8/8/{w:1}3K3", "NN");
+        assert_eq!(destinations(&nightrider, at(0, 0)), vec![at(2, 1), at(1, 2), at(4, 2), at(6, 3), at(2, 4), at(3, 6)]);
+
+        // over the rook to capture the pawn, over the white pawn to the empty tile, nothing on the empty lines
+        let grasshopper = custom_game("4k3/8/3p4/3r4/1P1{w:1}4/8/8/4K3", "gQ");
+        assert_eq!(destinations(&grasshopper, d4), vec![at(0, 3), at(3, 5)]);
+        assert_eq!(grasshopper.action_id_to_san(ActionID::Move(d4, at(3, 5))).unwrap(), "[1]xd6");
+    }
+
+    #[test]
+    fn betza_blocked_and_edge()
+    {
+        // the lame knight is blocked by d5 and d3, not by c4 and e4
+        let lame = custom_game("4k3/8/8/3P4/3{w:1}4/3P4/8/4K3", "nN");
+        assert_eq!(destinations(&lame, at(3, 3)), vec![at(1, 2), at(5, 2), at(1, 4), at(5, 4)]);
+
+        // the range stop after 3 tiles, and before the king on c1
+        let corner = custom_game("4k3/8/8/8/8/8/8/{w:1}1K5", "W3");
+        assert_eq!(destinations(&corner, at(0, 0)), vec![at(1, 0), at(0, 1), at(0, 2), at(0, 3)]);
+
+        // nothing to hop over : no action
+        let cannon = custom_game("4k3/8/8/8/8/8/8/{w:1}6K", "pR");
+        assert_eq!(destinations(&cannon, at(0, 0)), vec![]);
+    }
+
+    #[test]
+    fn betza_standard_pieces()
+    {
+        assert!(PieceMoves::standard()[PieceFlags::AB_CHESS_KING.0.trailing_zeros() as usize].castling);
+        assert_eq!(PieceMoves::standard().len(), PieceFlags::STANDARD_ABILITY_COUNT as usize);
+
+        let b = custom_game("4k3/8/8/8/3{w:1}4/8/8/4K3", "BN");
+        assert_eq!(b.look_like_standard(PieceFlags::AB_CHESS_KNIGHT), PieceFlags::AB_CHESS_KNIGHT);
+        assert_eq!(b.look_like_standard(PieceFlags::AB_CUSTOM[0]), PieceFlags::AB_CHESS_BISHOP | PieceFlags::AB_CHESS_KNIGHT);
+    }
+}
@@ -0,0 +1,460 @@
+//! Opening book for the standard chess : a Polyglot `.bin` book, or a text book of SAN move sequences.
+//!
+//! The positions are looked up with the Polyglot hash (computed from the FEN), and a move is choosen randomly according to its weight.
+//! The book is only used for the standard chess (8x8, only white and black, no relic except `Anticipation` that only remove the losing actions),
+//! because the variants invalidate the theory.
+//!
+//! The Polyglot hash need the 781 official keys (`Random64` in the Polyglot book format). They are not shipped with the crate :
+//! they are loaded with `PolyglotRandom::load`, the C array of the specification can be pasted as it is in a text file.
+//! `cargo test --release -p board -- --ignored book_polyglot_official` check a key file against the hashes published by the specification.
+//! The text books use their own keys.
+
+use std::collections::HashMap;
+
+use super::*;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BookError
+{
+    Io(String, String),
+    /// A Polyglot book is a sequence of 16 bytes entries
+    InvalidPolyglotSize(usize),
+    /// 781 keys are needed
+    InvalidPolyglotRandom(String),
+    InvalidLine { line : usize, reason : String },
+}
+impl Display for BookError
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult
+    {
+        match self
+        {
+            BookError::Io(path, e) => write!(f, "can't read {} : {}", path, e),
+            BookError::InvalidPolyglotSize(size) => write!(f, "invalid Polyglot book size : {} bytes is not a multiple of {}", size, PolyglotEntry::SIZE),
+            BookError::InvalidPolyglotRandom(s) => write!(f, "invalid Polyglot keys : {}", s),
+            BookError::InvalidLine { line, reason } => write!(f, "line {} : {}", line, reason),
+        }
+    }
+}
+impl std::error::Error for BookError {}
+
+/// The 781 keys used to hash a position : 768 for the pieces, 4 for the castling, 8 for the en passant file and 1 for the turn
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PolyglotRandom(Vec<u64>);
+impl PolyglotRandom
+{
+    pub const LENGHT : usize = 781;
+    const CASTLING_OFFSET : usize = 768;
+    const EN_PASSANT_OFFSET : usize = 772;
+    const TURN_OFFSET : usize = 780;
+
+    /// Read the hexadecimal numbers of the text (`0x` prefix, `ULL` suffix, commas, brackets and C comments are ignored)
+    pub fn parse(s : &str) -> Result<Self, BookError>
+    {
+        let mut keys = vec![];
+        for word in s.split(|c : char| !c.is_ascii_alphanumeric()).filter(|w| w.starts_with("0x") || w.starts_with("0X"))
+        {
+            let hex = word[2..].trim_end_matches(['U', 'u', 'L', 'l']);
+            keys.push(u64::from_str_radix(hex, 16).map_err(|_| BookError::InvalidPolyglotRandom(format!("\"{}\" is not a key", word)))?);
+        }
+        if keys.len() != Self::LENGHT { return Err(BookError::InvalidPolyglotRandom(format!("{} keys instead of {}", keys.len(), Self::LENGHT))); }
+        Ok(Self(keys))
+    }
+
+    pub fn load(path : &str) -> Result<Self, BookError>
+    {
+        Self::parse(&std::fs::read_to_string(path).map_err(|e| BookError::Io(path.to_owned(), e.to_string()))?)
+    }
+
+    /// Pseudo random keys (splitmix64), for the books that are not in the Polyglot format
+    pub fn splitmix(seed : u64) -> Self
+    {
+        let mut z = seed;
+        Self((0..Self::LENGHT).map(|_|
+        {
+            z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut x = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            x ^ (x >> 31)
+        }).collect())
+    }
+
+    /// Polyglot hash of a FEN. None if the FEN is not a 8x8 board
+    pub fn key(&self, fen : &str) -> Option<u64>
+    {
+        let fields : Vec<&str> = fen.split_whitespace().collect();
+        let (placement, turn, castling, en_passant) = (fields.first()?, fields.get(1)?, fields.get(2)?, fields.get(3)?);
+
+        let ranks : Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 { return None; }
+
+        let mut board = [[None; 8]; 8];
+        let mut h = 0;
+        for (i, rank) in ranks.iter().enumerate()
+        {
+            let row = 7 - i;
+            let mut file = 0;
+            for c in rank.chars()
+            {
+                if let Some(d) = c.to_digit(10) { file += d as usize; continue; }
+                if file >= 8 { return None; }
+                let kind = "pPnNbBrRqQkK".find(c)?;
+                board[row][file] = Some(c);
+                h ^= self.0[64 * kind + 8 * row + file];
+                file += 1;
+            }
+            if file != 8 { return None; }
+        }
+
+        for (i, c) in "KQkq".chars().enumerate()
+        {
+            if castling.contains(c) { h ^= self.0[Self::CASTLING_OFFSET + i]; }
+        }
+
+        // only if a pawn can capture en passant
+        let white = *turn == "w";
+        if let Some(file) = en_passant.chars().next().filter(|c| ('a'..='h').contains(c)).map(|c| c as usize - 'a' as usize)
+        {
+            let (row, pawn) = if white { (4, 'P') } else { (3, 'p') };
+            if [file.wrapping_sub(1), file + 1].iter().any(|f| *f < 8 && board[row][*f] == Some(pawn))
+            {
+                h ^= self.0[Self::EN_PASSANT_OFFSET + file];
+            }
+        }
+
+        if white { h ^= self.0[Self::TURN_OFFSET]; }
+        Some(h)
+    }
+}
+
+/// An entry of a Polyglot book
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PolyglotEntry
+{
+    pub key : u64,
+    /// to file, to row, from file, from row, promotion (3 bits each). The castling is the king moving to its rook
+    pub mv : u16,
+    pub weight : u16,
+    pub learn : u32,
+}
+impl PolyglotEntry
+{
+    pub const SIZE : usize = 16;
+
+    pub fn from_bytes(b : &[u8]) -> Self
+    {
+        Self
+        {
+            key: u64::from_be_bytes(b[0..8].try_into().unwrap()),
+            mv: u16::from_be_bytes(b[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(b[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(b[12..16].try_into().unwrap()),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE]
+    {
+        let mut b = [0; Self::SIZE];
+        b[0..8].copy_from_slice(&self.key.to_be_bytes());
+        b[8..10].copy_from_slice(&self.mv.to_be_bytes());
+        b[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        b[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        b
+    }
+}
+
+/// A move of the book for a position
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BookMove
+{
+    pub action_id : ActionID,
+    pub weight : u32,
+}
+
+static CHESS_DEFAULT_BOOK : std::sync::OnceLock<OpeningBook> = std::sync::OnceLock::new();
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct OpeningBook
+{
+    random : PolyglotRandom,
+    /// Polyglot move and weight for each key
+    entries : HashMap<u64, Vec<(u16, u32)>>,
+}
+impl OpeningBook
+{
+    /// Seed of the keys of the text books
+    const TEXT_SEED : u64 = 0xB00C;
+
+    pub fn from_polyglot(bytes : &[u8], random : PolyglotRandom) -> Result<Self, BookError>
+    {
+        if !bytes.len().is_multiple_of(PolyglotEntry::SIZE) { return Err(BookError::InvalidPolyglotSize(bytes.len())); }
+
+        let mut entries : HashMap<u64, Vec<(u16, u32)>> = HashMap::new();
+        for e in bytes.chunks_exact(PolyglotEntry::SIZE).map(PolyglotEntry::from_bytes)
+        {
+            entries.entry(e.key).or_default().push((e.mv, e.weight as u32));
+        }
+        Ok(Self { random, entries })
+    }
+
+    pub fn load_polyglot(path : &str, random : PolyglotRandom) -> Result<Self, BookError>
+    {
+        Self::from_polyglot(&std::fs::read(path).map_err(|e| BookError::Io(path.to_owned(), e.to_string()))?, random)
+    }
+
+    /// One sequence of SAN moves from the start position per line (`1. e4 e5 2. Nf3`). Each line add 1 to the weight of its moves.
+    /// The empty lines and the `#` comments are ignored
+    pub fn from_text(text : &str) -> Result<Self, BookError>
+    {
+        let mut book = Self { random: PolyglotRandom::splitmix(Self::TEXT_SEED), entries: HashMap::new() };
+        for (line_idx, line) in text.lines().enumerate()
+        {
+            let line = line.split('#').next().unwrap();
+            let err = |reason : String| BookError::InvalidLine { line: line_idx + 1, reason };
+
+            let mut b = BoardGame::new_chess();
+            for san in line.split_whitespace().filter(|w| !w.ends_with('.'))
+            {
+                let id = b.san_to_action_id(san).map_err(|e| err(format!("\"{}\" : {}", san, e)))?;
+                let key = b.book_key(&book.random).ok_or_else(|| err("not a standard chess position".to_owned()))?;
+                let mv = b.book_action_id_to_polyglot(id);
+
+                let moves = book.entries.entry(key).or_default();
+                match moves.iter_mut().find(|(m, _)| *m == mv)
+                {
+                    Some((_, w)) => *w += 1,
+                    None => moves.push((mv, 1)),
+                }
+                b.execute(id);
+            }
+        }
+        Ok(book)
+    }
+
+    pub fn load_text(path : &str) -> Result<Self, BookError>
+    {
+        Self::from_text(&std::fs::read_to_string(path).map_err(|e| BookError::Io(path.to_owned(), e.to_string()))?)
+    }
+
+    /// The book of `board/book/chess.txt`, unless replaced with `set_chess_default`
+    pub fn chess_default() -> &'static Self
+    {
+        CHESS_DEFAULT_BOOK.get_or_init(|| Self::from_text(include_str!("../book/chess.txt")).unwrap())
+    }
+
+    /// Replace the book used by the cpu (a Polyglot book for example). Give back the book if `chess_default` was already used
+    pub fn set_chess_default(book : Self) -> Result<(), Self> { CHESS_DEFAULT_BOOK.set(book) }
+
+    pub fn nb_position(&self) -> usize { self.entries.len() }
+
+    /// The valid moves of the book for this position. Empty if the book can't be used
+    pub fn probe(&self, game : &BoardGameFixedTime) -> Vec<BookMove>
+    {
+        if !game.is_book_applicable() { return vec![]; }
+        let Some(moves) = game.book_key(&self.random).and_then(|k| self.entries.get(&k)) else { return vec![]; };
+
+        moves.iter().filter(|(_, w)| *w > 0)
+            .filter_map(|(mv, weight)| game.book_polyglot_to_action_id(*mv).map(|action_id| BookMove { action_id, weight: *weight }))
+            .collect()
+    }
+
+    /// A random move of the book, according to the weights
+    pub fn choose(&self, game : &BoardGameFixedTime, rng : &mut Random) -> Option<ActionID>
+    {
+        let moves = self.probe(game);
+        let total : u32 = moves.iter().map(|m| m.weight).sum();
+        if total == 0 { return None; }
+
+        let mut r = rng.gen_range(0..total as usize) as u32;
+        for m in moves.iter()
+        {
+            if r < m.weight { return Some(m.action_id); }
+            r -= m.weight;
+        }
+        None
+    }
+}
+
+/// The promotions of a Polyglot move, from 1
+const POLYGLOT_PROMOTION : [PieceFlags; 4] = [PieceFlags::AB_CHESS_KNIGHT, PieceFlags::AB_CHESS_BISHOP, PieceFlags::AB_CHESS_ROOK, PieceFlags::AB_CHESS_QUEEN];
+
+impl BoardGameFixedTime
+{
+    /// Standard chess : 8x8, only white and black, no relic except `Anticipation`, and only chess pieces
+    pub fn is_book_applicable(&self) -> bool
+    {
+        let relics_ok = Team::iter().all(|t| self.team_data[t].relics & !Relics::ZERO.with_flag_add(Relic::Anticipation) == 0);
+        let teams_ok = Team::iter().all(|t| self.team_data[t].is_present == matches!(t, Team::White | Team::Black));
+        self.size() == at(8, 8) && relics_ok && teams_ok && !self.is_end_of_the_game() && self.to_fen().is_ok()
+    }
+
+    fn book_key(&self, random : &PolyglotRandom) -> Option<u64> { random.key(&self.to_fen().ok()?) }
+
+    fn book_action_id_to_polyglot(&self, id : ActionID) -> u16
+    {
+        let (src, mut dest) = (id.src(), id.dest());
+        // castling : the king move to its rook
+        if self[src].is_also_chess_king() && (dest.x - src.x).abs() == 2 { dest.x = if dest.x > src.x { 7 } else { 0 }; }
+
+        let promotion = match id
+        {
+            ActionID::Promote(_, _, p) => POLYGLOT_PROMOTION.iter().position(|e| *e == p).map_or(0, |i| i as u16 + 1),
+            _ => 0,
+        };
+        (dest.x | dest.y << 3 | src.x << 6 | src.y << 9) as u16 | promotion << 12
+    }
+
+    fn book_polyglot_to_action_id(&self, mv : u16) -> Option<ActionID>
+    {
+        let square = |s : u16| at((s & 7) as AtIntType, ((s >> 3) & 7) as AtIntType);
+        let (src, mut dest) = (square(mv >> 6), square(mv));
+        if self[src].is_also_chess_king() && self[dest].is_also_chess_rook() && self[dest].is_also_team(self.current_team)
+        {
+            dest.x = src.x + if dest.x > src.x { 2 } else { -2 };
+        }
+
+        let id = match (mv >> 12) & 7
+        {
+            0 => ActionID::Move(src, dest),
+            p => ActionID::Promote(src, dest, POLYGLOT_PROMOTION[(p as usize - 1).min(3)]),
+        };
+        self.action_id_is_valid(id).then_some(id)
+    }
+
+    /// The book move if there is one, otherwise the search
+    pub fn minimax_with_book(&self, book : &OpeningBook, rng : &mut Random, budget : SearchBudget, cancel : &CancellationToken) -> MinimaxResult
+    {
+        match book.choose(self, rng)
+        {
+            Some(action_id) => MinimaxResult { action_id: Some(action_id), from_book: true, ..___() },
+            None => self.minimax_budget(budget, cancel),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn play(sans : &[&str]) -> BoardGame
+    {
+        let mut b = BoardGame::new_chess();
+        for san in sans { b.execute(b.san_to_action_id(san).unwrap()); }
+        b
+    }
+
+    #[test]
+    fn book_text()
+    {
+        let book = OpeningBook::from_text("# comment\n1. e4 e5 2. Nf3\n1. e4 c5\n\n1. d4 d5 2. c4 e6 3. Nc3 Nf6\n1. d4 Nf6 2. c4 e6 3. Nc3 d5").unwrap();
+
+        let start = book.probe(BoardGame::new_chess().current());
+        let mut start : Vec<_> = start.iter().map(|m| (BoardGame::new_chess().action_id_to_san(m.action_id).unwrap(), m.weight)).collect();
+        start.sort();
+        assert_eq!(start, [("d4".to_owned(), 2), ("e4".to_owned(), 2)]);
+
+        // transposition
+        let b = play(&["d4", "d5", "c4", "e6", "Nc3", "Nf6"]);
+        assert!(book.probe(b.current()).is_empty());
+        let b = play(&["d4", "d5", "c4", "e6", "Nc3"]);
+        assert_eq!(book.probe(b.current()).len(), 1);
+
+        // out of book
+        assert!(book.probe(play(&["a4"]).current()).is_empty());
+        // variants
+        assert!(book.probe(BoardGame::new_chess_custom(2, true, Relics::ZERO.with_flag_add(Relic::MoveTwiceInATurn)).current()).is_empty());
+        assert!(book.probe(BoardGame::new_chess_custom(4, true, Relics::ZERO).current()).is_empty());
+
+        assert!(matches!(OpeningBook::from_text("e4 e5\ne4 Ke7 Ke5"), Err(BookError::InvalidLine { line: 2, .. })));
+        assert!(OpeningBook::chess_default().nb_position() > 50);
+    }
+
+    #[test]
+    fn book_weighted_choice()
+    {
+        let book = OpeningBook::from_text("e4\ne4\ne4\nd4").unwrap();
+        let b = BoardGame::new_chess();
+        let e4 = b.san_to_action_id("e4").unwrap();
+
+        let mut rng = Random::new(3);
+        let nb_e4 = (0..400).filter(|_| book.choose(b.current(), &mut rng) == Some(e4)).count();
+        assert!((250..350).contains(&nb_e4), "{}", nb_e4);
+
+        let r = b.minimax_with_book(&book, &mut rng, SearchBudget::depth(2), &CancellationToken::new());
+        assert!(r.from_book);
+        let out = play(&["a4"]);
+        let r = out.minimax_with_book(&book, &mut rng, SearchBudget::depth(2), &CancellationToken::new());
+        assert!(!r.from_book && r.stat_nb_depth_evaluated == 2);
+    }
+
+    #[test]
+    fn book_polyglot()
+    {
+        let random = PolyglotRandom::splitmix(1);
+        let text : String = random.0.iter().map(|k| format!("0x{:016X}ULL, ", k)).collect();
+        assert_eq!(PolyglotRandom::parse(&format!("const uint64 Random64[781] = {{ {} }};", text)), Ok(random.clone()));
+        assert!(PolyglotRandom::parse("0x12, 0x34").is_err());
+
+        // e2e4, and the white king side castling
+        let start = BoardGame::new_chess();
+        let castling = play(&["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5"]);
+        let entries = [(start.current(), 0b000_001_100_011_100u16, 3), (castling.current(), 0b000_000_100_000_111, 1)];
+
+        let mut bytes = vec![];
+        for (state, mv, weight) in entries
+        {
+            bytes.extend(PolyglotEntry { key: state.book_key(&random).unwrap(), mv, weight, learn: 0 }.to_bytes());
+        }
+        let book = OpeningBook::from_polyglot(&bytes, random.clone()).unwrap();
+
+        assert_eq!(book.probe(start.current()), [BookMove { action_id: start.san_to_action_id("e4").unwrap(), weight: 3 }]);
+        assert_eq!(book.probe(castling.current()), [BookMove { action_id: castling.san_to_action_id("O-O").unwrap(), weight: 1 }]);
+        assert_eq!(castling.book_action_id_to_polyglot(castling.san_to_action_id("O-O").unwrap()), 0b000_000_100_000_111);
+
+        // the en passant is only hashed when a pawn can capture
+        let no_capture = random.key("rnbqkbnr/pppp1ppp/8/8/4p3/8/PPPPPPPP/RNBQKBNR w KQkq e6 0 2").unwrap();
+        assert_eq!(no_capture, random.key("rnbqkbnr/pppp1ppp/8/8/4p3/8/PPPPPPPP/RNBQKBNR w KQkq - 0 2").unwrap());
+        let capture = random.key("rnbqkbnr/pppp1ppp/8/3Pp3/8/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 2").unwrap();
+        assert_ne!(capture, random.key("rnbqkbnr/pppp1ppp/8/3Pp3/8/8/PPP1PPPP/RNBQKBNR w KQkq - 0 2").unwrap());
+
+        // the promotions : knight 1, bishop 2, rook 3, queen 4
+        let promotion = BoardGame::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", Relics::ZERO).unwrap();
+        for (san, code) in [("a8=N", 1), ("a8=B", 2), ("a8=R", 3), ("a8=Q", 4)]
+        {
+            let id = promotion.san_to_action_id(san).unwrap();
+            let mv = promotion.book_action_id_to_polyglot(id);
+            assert_eq!(mv >> 12, code, "{}", san);
+            assert_eq!(promotion.book_polyglot_to_action_id(mv), Some(id), "{}", san);
+        }
+
+        assert_eq!(OpeningBook::from_polyglot(&[0; 17], random), Err(BookError::InvalidPolyglotSize(17)));
+    }
+
+    /// Need the official keys : `POLYGLOT_RANDOM=random64.txt cargo test --release -p board -- --ignored book_polyglot_official`
+    #[test]
+    #[ignore]
+    fn book_polyglot_official()
+    {
+        let path = std::env::var("POLYGLOT_RANDOM").expect("POLYGLOT_RANDOM must be the path of the official keys");
+        let random = PolyglotRandom::load(&path).unwrap();
+
+        // the examples of the specification
+        let expected =
+        [
+            (&[][..], 0x463b96181691fc9c),
+            (&["e4"], 0x823c9b50fd114196),
+            (&["e4", "d5"], 0x0756b94461c50fb0),
+            (&["e4", "d5", "e5"], 0x662fafb965db29d4),
+            (&["e4", "d5", "e5", "f5"], 0x22a48b5a8e47ff78),
+            (&["e4", "d5", "e5", "f5", "Ke2"], 0x652a607ca3f242c1),
+            (&["e4", "d5", "e5", "f5", "Ke2", "Kf7"], 0x00fdd303c946bdd9),
+            (&["a4", "b5", "h4", "b4", "c4"], 0x3c8123ea7b067637),
+            (&["a4", "b5", "h4", "b4", "c4", "bxc3", "Ra3"], 0x5c3f9b829b279560),
+        ];
+        for (sans, key) in expected
+        {
+            assert_eq!(play(sans).book_key(&random), Some(key), "{:?}", sans);
+        }
+    }
+}
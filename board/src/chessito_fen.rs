@@ -0,0 +1,483 @@
+//! Chessito FEN : a superset of the FEN notation that can describe any position of the engine
+//! (any board size, up to 4 teams, pieces belonging to multiple teams with multiple abilities, relics...)
+//!
+//! The 10 fields are separated by a whitespace :
+//!
+//! `<size> <placement> <current team> <energy> <nb action this turn> <en passant> <turn> <nb actions> <halfmove clock> <teams>`
+//!
+//! - `size` : `<width>x<height>`, ex `8x8`
+//! - `placement` : the ranks from the top to the bottom separated by `/`. Consecutive empty tiles are written as a number (can be greater than 9).
+//!   A piece is either :
+//!     - a FEN letter (`KQRBNP` for white, `kqrbnp` for black). The king have an implicit crown.
+//!     - `{<teams>:<abilities><flags>}` otherwise. Teams are `w`, `b`, `y`, `g` (no team is allowed).
+//!       Abilities are `P` pawn, `N` knight, `B` bishop, `R` rook, `Q` (bishop + rook), `K` king, `x` checkers pawn, `X` checkers king,
+//!       `1` to `5` the custom abilities of the game (their movements are not saved).
+//!       Flags are `+` crown, `~` partial pin, `!` total pin, `@` duck.
+//!       ex : a white and black knight + rook with a crown is `{wb:NR+}`, the duck is `{:@}`
+//!
+//!   The piece can be followed by `*` if it already moved, or `^` if it moved during this turn.
+//!   A void tile (outside of a non rectangular board) is `#`.
+//! - `current team` : `w`, `b`, `y` or `g`, followed by `@` if the team must place the duck
+//! - `energy` and `nb action this turn` : `current_nb_energy` and `current_nb_action_this_turn`
+//! - `en passant` : the pawns that can be captured en passant (moved once by 2 tiles during the last turn) separated by `,`, or `-`.
+//!   Unlike FEN, this is the tile of the pawn, not the tile skipped by the pawn
+//! - `turn`, `nb actions` and `halfmove clock` : the counters of `BoardGameFixedTime`
+//! - `teams` : the teams present in the game separated by `,`. Each team is written as its letter,
+//!   followed by `-` if the team lost, followed by the letter of its relics :
+//!   `A` Anticipation, `E` Explosive, `D` DuckButDifferent, `K` Absorb, `T` MoveTwiceInATurn
+//!
+//! The initial chess position with the Anticipation relic is :
+//!
+//! `8x8 rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w 1 0 - 1 1 0 wA,bA`
+//!
+//! The captured pieces and the draw state are not saved.
+
+use super::*;
+
+impl Team
+{
+    pub fn fen_char(self) -> char
+    {
+        match self
+        {
+            Team::White  => 'w',
+            Team::Black  => 'b',
+            Team::Yellow => 'y',
+            Team::Green  => 'g',
+        }
+    }
+    pub fn from_fen_char(c : char) -> Option<Self> { Self::iter().find(|t| t.fen_char() == c) }
+}
+
+impl Relic
+{
+    pub fn fen_char(self) -> char
+    {
+        match self
+        {
+            Relic::Anticipation     => 'A',
+            Relic::Explosive        => 'E',
+            Relic::DuckButDifferent => 'D',
+            Relic::Absorb           => 'K',
+            Relic::MoveTwiceInATurn => 'T',
+        }
+    }
+    pub fn from_fen_char(c : char) -> Option<Self> { Self::iter().find(|r| r.fen_char() == c) }
+}
+
+impl PieceFlags
+{
+    pub const CHESSITO_FEN_ABILITY : [(PieceFlags, char); 12] =
+    [
+        (Self::AB_CHESS_PAWN, 'P'), (Self::AB_CHESS_KNIGHT, 'N'), (Self::AB_CHESS_BISHOP, 'B'), (Self::AB_CHESS_ROOK, 'R'), (Self::AB_CHESS_KING, 'K'),
+        (Self::AB_DAME_PAWN, 'x'), (Self::AB_DAME_KING, 'X'),
+        (Self::AB_CUSTOM[0], '1'), (Self::AB_CUSTOM[1], '2'), (Self::AB_CUSTOM[2], '3'), (Self::AB_CUSTOM[3], '4'), (Self::AB_CUSTOM[4], '5'),
+    ];
+    pub const CHESSITO_FEN_FLAGS : [(PieceFlags, char); 4] = [(Self::CROWN, '+'), (Self::PARTIAL_PIN, '~'), (Self::TOTAL_PIN, '!'), (Self::DUCK, '@')];
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ChessitoFenMoved
+{
+    Never,
+    Before,
+    ThisTurn,
+}
+
+struct ChessitoFenTile
+{
+    void : bool,
+    teams : TeamsFlags,
+    flags : PieceFlags,
+    moved : ChessitoFenMoved,
+}
+
+fn parse_number<T : std::str::FromStr>(s : &str) -> Result<T, FenError> { s.parse::<T>().map_err(|_| FenError::InvalidNumber(s.to_owned())) }
+
+fn parse_chessito_fen_piece(it : &mut std::iter::Peekable<std::str::Chars>, c : char) -> Result<(TeamsFlags, PieceFlags), FenError>
+{
+    if c != '{'
+    {
+        let (t, flags) = PieceFlags::from_fen_char(c).ok_or(FenError::InvalidPiece(c))?;
+        return Ok((t.flags(), flags));
+    }
+
+    let mut teams = TeamsFlags::ZERO;
+    loop
+    {
+        match it.next()
+        {
+            Some(':') => break,
+            Some(c) => teams |= Team::from_fen_char(c).ok_or(FenError::InvalidTeam(c.to_string()))?.flags(),
+            None => return Err(FenError::InvalidPiece('{')),
+        }
+    }
+
+    let mut flags = PieceFlags::ZERO;
+    loop
+    {
+        match it.next()
+        {
+            Some('}') => break,
+            Some('Q') => flags |= PieceFlags::AB_CHESS_QUEEN,
+            Some(c) =>
+            {
+                let (f, _) = PieceFlags::CHESSITO_FEN_ABILITY.iter().chain(PieceFlags::CHESSITO_FEN_FLAGS.iter()).find(|(_, letter)| *letter == c).ok_or(FenError::InvalidPiece(c))?;
+                flags |= *f;
+            }
+            None => return Err(FenError::InvalidPiece('{')),
+        }
+    }
+    Ok((teams, flags))
+}
+
+fn parse_chessito_fen_rank(rank : &str) -> Result<Vec<Option<ChessitoFenTile>>, FenError>
+{
+    let mut tiles = vec![];
+    let mut nb_empty : usize = 0;
+    let mut it = rank.chars().peekable();
+
+    while let Some(c) = it.next()
+    {
+        if let Some(digit) = c.to_digit(10)
+        {
+            nb_empty = nb_empty * 10 + digit as usize;
+            continue;
+        }
+        for _ in 0..nb_empty { tiles.push(None); }
+        nb_empty = 0;
+
+        if c == '#'
+        {
+            tiles.push(Some(ChessitoFenTile { void: true, teams: TeamsFlags::ZERO, flags: PieceFlags::ZERO, moved: ChessitoFenMoved::Never }));
+            continue;
+        }
+        let (teams, flags) = parse_chessito_fen_piece(&mut it, c)?;
+        let moved = match it.peek()
+        {
+            Some('*') => { it.next(); ChessitoFenMoved::Before },
+            Some('^') => { it.next(); ChessitoFenMoved::ThisTurn },
+            _ => ChessitoFenMoved::Never,
+        };
+        tiles.push(Some(ChessitoFenTile { void: false, teams, flags, moved }));
+    }
+    for _ in 0..nb_empty { tiles.push(None); }
+    Ok(tiles)
+}
+
+impl BoardGameFixedTime
+{
+    /// The legal actions are calculated like `BoardGame::execute()` does (with the Anticipation relic applied)
+    pub fn from_chessito_fen(fen : &str) -> Result<Self, FenError> { Self::from_chessito_fen_with_setup(fen, |_| {}) }
+
+    /// `setup` can change the rules before the first actions are calculated
+    pub(crate) fn from_chessito_fen_with_setup(fen : &str, setup : impl FnOnce(&mut BoardGameNotStarted)) -> Result<Self, FenError>
+    {
+        let mut it = fen.split_whitespace();
+        let mut next = |field : &'static str| it.next().ok_or(FenError::MissingField(field));
+
+        let size_str = next("board size")?;
+        let placement = next("piece placement")?;
+        let team_str = next("current team")?;
+        let current_nb_energy : Energy = parse_number(next("energy")?)?;
+        let current_nb_action_this_turn : Turn = parse_number(next("nb action this turn")?)?;
+        let en_passant = next("en passant")?;
+        let turn : Turn = parse_number(next("turn")?)?;
+        let nb_actions : Turn = parse_number(next("nb actions")?)?;
+        let half_move_clock : Turn = parse_number(next("halfmove clock")?)?;
+        let teams = next("teams")?;
+
+        let invalid_size = || FenError::InvalidSize(size_str.to_owned());
+        let size = match size_str.split_once('x')
+        {
+            Some((x, y)) => at(x.parse().map_err(|_| invalid_size())?, y.parse().map_err(|_| invalid_size())?),
+            None => return Err(invalid_size()),
+        };
+        if size.x <= 0 || size.y <= 0 { return Err(invalid_size()); }
+
+        let mut current_team_chars = team_str.chars();
+        let (current_team, duck_to_place) = match (current_team_chars.next().and_then(Team::from_fen_char), current_team_chars.next(), current_team_chars.next())
+        {
+            (Some(t), None, None) => (t, false),
+            (Some(t), Some('@'), None) => (t, true),
+            _ => return Err(FenError::InvalidTeam(team_str.to_owned())),
+        };
+
+        let mut data = BoardGameNotStarted::new_empty(size);
+
+        let ranks : Vec<&str> = placement.split('/').collect();
+        if ranks.len() != size.y as usize { return Err(invalid_size()); }
+
+        // The first rank is the top one
+        for (rank_idx, rank) in ranks.into_iter().enumerate()
+        {
+            let y = size.y - 1 - rank_idx as AtIntType;
+            let tiles = parse_chessito_fen_rank(rank)?;
+            if tiles.len() != size.x as usize { return Err(FenError::BoardSizeMismatch { rank: rank_idx as AtIntType, expected: size.x, got: tiles.len() as AtIntType }); }
+
+            for (x, tile) in tiles.into_iter().enumerate()
+            {
+                let Some(tile) = tile else { continue; };
+                let a = at(x as AtIntType, y);
+                if tile.void { data.set_void(a, true); continue; }
+                data.piece_add_teams_flags(a, tile.teams);
+                data.piece_set_flags(a, tile.flags);
+
+                let p = &mut data[a];
+                match tile.moved
+                {
+                    ChessitoFenMoved::Never => {},
+                    ChessitoFenMoved::Before =>
+                    {
+                        p.last_turn_moved = (turn - 2).max(0);
+                        p.last_action_moved = (nb_actions - 3).max(0);
+                    },
+                    ChessitoFenMoved::ThisTurn =>
+                    {
+                        p.last_turn_moved = turn;
+                        p.last_action_moved = nb_actions - 1;
+                    },
+                }
+                if tile.moved != ChessitoFenMoved::Never
+                {
+                    p.nb_time_moved = Saturating(1);
+                    p.distance_travel_total = Saturating(1);
+                }
+            }
+        }
+
+        if en_passant != "-"
+        {
+            for tile in en_passant.split(',')
+            {
+                let invalid = || FenError::InvalidEnPassant(tile.to_owned());
+                let pawn = at_from_str(tile).filter(|e| data.is_inside(*e) && data[*e].is_also_chess_pawn()).ok_or_else(invalid)?;
+                let dir = data[pawn].iter_team().next().map(|t| data.team_direction(t)).ok_or_else(invalid)?;
+
+                let p = &mut data[pawn];
+                p.old_pos = pawn - dir * 2;
+                p.nb_time_moved = Saturating(1);
+                p.distance_travel_total = Saturating(2);
+                if p.last_turn_moved != turn
+                {
+                    p.last_turn_moved = turn - 1;
+                    p.last_action_moved = nb_actions - 2;
+                }
+            }
+        }
+
+        for t in Team::iter() { data.team_data[t].alive = false; }
+        for team in teams.split(',').filter(|e| !e.is_empty())
+        {
+            let mut chars = team.chars().peekable();
+            let c = chars.next().unwrap();
+            let t = Team::from_fen_char(c).ok_or(FenError::InvalidTeam(c.to_string()))?;
+            let alive = if chars.peek() == Some(&'-') { chars.next(); false } else { true };
+
+            let mut relics = Relics::ZERO;
+            for c in chars
+            {
+                relics.flag_add(Relic::from_fen_char(c).ok_or(FenError::InvalidRelic(c))?);
+            }
+            let team_data = &mut data.team_data[t];
+            team_data.is_present = true;
+            team_data.alive = alive;
+            team_data.relics = relics;
+        }
+        data.nb_team_alive = data.team_data.iter().filter(|(_, e)| e.alive).count() as u8;
+
+        data.current_team = current_team;
+        data.current_nb_energy = current_nb_energy;
+        data.current_nb_action_this_turn = current_nb_action_this_turn;
+        data.duck_to_place = duck_to_place;
+        setup(&mut data);
+
+        let mut s = Self { data, turn, nb_actions, half_move_clock, ..___() };
+        s.zobrist_init();
+        if !s.is_end_of_the_game() { s.update_actions(true); }
+        Ok(s)
+    }
+
+    fn chessito_fen_piece(&self, a : At) -> String
+    {
+        let p = &self[a];
+        let mut s = String::new();
+
+        let mut teams = p.iter_team();
+        let standard = match (teams.next(), teams.next())
+        {
+            (Some(t), None) => p.flags.fen_char(t).filter(|c| PieceFlags::from_fen_char(*c) == Some((t, p.flags))),
+            _ => None,
+        };
+
+        match standard
+        {
+            Some(c) => s.push(c),
+            None =>
+            {
+                s.push('{');
+                for t in p.iter_team() { s.push(t.fen_char()); }
+                s.push(':');
+                for (f, c) in PieceFlags::CHESSITO_FEN_ABILITY.iter().chain(PieceFlags::CHESSITO_FEN_FLAGS.iter())
+                {
+                    if p.flags.have_flag(*f) { s.push(*c); }
+                }
+                s.push('}');
+            }
+        }
+
+        if p.last_turn_moved == self.turn && p.nb_time_moved != Saturating(0)
+        {
+            s.push('^');
+        } else if p.already_move() || p.nb_time_moved != Saturating(0)
+        {
+            s.push('*');
+        }
+        s
+    }
+
+    pub fn to_chessito_fen(&self) -> String
+    {
+        let size = self.size();
+        let mut s = format!("{}x{} ", size.x, size.y);
+
+        for y in (0..size.y).rev()
+        {
+            let mut nb_empty = 0;
+            for x in 0..size.x
+            {
+                let a = at(x, y);
+                let void = self.is_void(a);
+                if !void && self[a].is_none_flag() && self[a].teams_flags().is_none_flag() { nb_empty += 1; continue; }
+                if nb_empty != 0 { s.push_str(&nb_empty.to_string()); nb_empty = 0; }
+                s.push_str(&if void { "#".to_owned() } else { self.chessito_fen_piece(a) });
+            }
+            if nb_empty != 0 { s.push_str(&nb_empty.to_string()); }
+            if y != 0 { s.push('/'); }
+        }
+
+        let en_passant : Vec<String> = self.iter_idx().filter(|a|
+            {
+                let p = &self[*a];
+                p.is_also_chess_pawn() && p.nb_time_moved == Saturating(1) && p.distance_travel_total == Saturating(2) && self.was_moved_last_turn_or_this_turn(*a)
+            }).map(at_to_string).collect();
+        let en_passant = if en_passant.is_empty() { "-".to_owned() } else { en_passant.join(",") };
+
+        let teams : Vec<String> = self.iter_team_data().filter(|(_, e)| e.is_present).map(|(t, e)|
+            {
+                let mut s = t.fen_char().to_string();
+                if !e.alive { s.push('-'); }
+                for r in Relic::iter().filter(|r| e.relics.flag_have(*r)) { s.push(r.fen_char()); }
+                s
+            }).collect();
+
+        s.push_str(&format!(" {}{} {} {} {} {} {} {} {}", self.current_team.fen_char(), if self.duck_to_place { "@" } else { "" }, self.current_nb_energy, self.current_nb_action_this_turn, en_passant, self.turn, self.nb_actions, self.half_move_clock, teams.join(",")));
+        s
+    }
+}
+
+impl BoardGame
+{
+    pub fn from_chessito_fen(fen : &str) -> Result<Self, FenError>
+    {
+        Ok(Self::new_from_fixed_time(BoardGameFixedTime::from_chessito_fen(fen)?))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn assert_same_position(a : &BoardGameFixedTime, b : &BoardGameFixedTime)
+    {
+        let fen = a.to_chessito_fen();
+        assert_eq!(a.size(), b.size(), "{}", fen);
+        for idx in a.iter_idx()
+        {
+            assert_eq!(a[idx].flags, b[idx].flags, "{}", fen);
+            assert_eq!(a[idx].teams_flags(), b[idx].teams_flags(), "{}", fen);
+            assert_eq!(a.is_void(idx), b.is_void(idx), "{}", fen);
+        }
+        assert_eq!(a.current_team, b.current_team, "{}", fen);
+        assert_eq!(a.current_nb_energy, b.current_nb_energy, "{}", fen);
+        assert_eq!(a.current_nb_action_this_turn, b.current_nb_action_this_turn, "{}", fen);
+        assert_eq!(a.duck_to_place, b.duck_to_place, "{}", fen);
+        assert_eq!((a.turn, a.nb_actions, a.half_move_clock), (b.turn, b.nb_actions, b.half_move_clock), "{}", fen);
+        for (t, data) in a.iter_team_data().filter(|(_, e)| e.is_present)
+        {
+            assert_eq!(data.relics, b.team_data[t].relics, "{}", fen);
+            assert_eq!(data.alive, b.team_data[t].alive, "{}", fen);
+            assert_eq!(data.alive_piece_value, b.team_data[t].alive_piece_value, "{}", fen);
+        }
+        assert_eq!(a.nb_team_alive, b.nb_team_alive, "{}", fen);
+        assert_eq!(a.actions.len(), b.actions.len(), "{}", fen);
+        assert!(a.actions.iter().all(|e| b.action_id_is_valid(e.id)), "{}", fen);
+        assert_eq!(fen, b.to_chessito_fen());
+    }
+
+    #[test]
+    fn chessito_fen_initial_chess()
+    {
+        let chess = BoardGame::new_chess();
+        assert_eq!(chess.to_chessito_fen(), "8x8 rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w 1 0 - 1 1 0 wA,bA");
+
+        let fen = "10x3 {wb:NR+}*9/10/{w:x}1{:X~}^5{y:BR!}1 b 2 1 - 7 13 3 wA,b-EK,yDT";
+        assert_eq!(BoardGameFixedTime::from_chessito_fen(fen).unwrap().to_chessito_fen(), fen);
+
+        assert_eq!(BoardGameFixedTime::from_chessito_fen("8x7 8/8/8/8/8/8/8/8 w 1 0 - 1 1 0 w").unwrap_err(), FenError::InvalidSize("8x7".to_owned()));
+        assert_eq!(BoardGameFixedTime::from_chessito_fen("2x1 {w:Z} w 1 0 - 1 1 0 w").unwrap_err(), FenError::InvalidPiece('Z'));
+        assert_eq!(BoardGameFixedTime::from_chessito_fen("2x1 2 w 1 0 - 1 1 0 wZ").unwrap_err(), FenError::InvalidRelic('Z'));
+    }
+
+    /// Play random games and check that every position round-trips
+    #[test]
+    fn chessito_fen_round_trip_reachable_position()
+    {
+        let mut rng = Random::new(42);
+
+        let setups =
+        [
+            BoardGame::new_chess(),
+            BoardGame::new_chess_custom(2, true, Relics::ZERO.with_flag_add(Relic::Explosive).with_flag_add(Relic::Absorb)),
+            BoardGame::new_chess_custom(2, true, Relics::ZERO.with_flag_add(Relic::MoveTwiceInATurn)),
+            BoardGame::new_chess_custom(4, true, Relics::ZERO.with_flag_add(Relic::Absorb)),
+            BoardGame::new_chess_custom(2, true, Relics::ZERO.with_flag_add(Relic::DuckButDifferent)),
+            BoardGame::new_checker(),
+        ];
+
+        for setup in setups
+        {
+            for _ in 0..2
+            {
+                let mut b = setup.clone();
+                assert_same_position(b.current(), &BoardGameFixedTime::from_chessito_fen(&b.to_chessito_fen()).unwrap());
+
+                for _ in 0..40
+                {
+                    if b.is_end_of_the_game() { break; }
+                    let action_id = b.actions[rng.gen_range(0..b.actions.len())].id;
+                    b.execute(action_id);
+                    assert_same_position(b.current(), &BoardGameFixedTime::from_chessito_fen(&b.to_chessito_fen()).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn chessito_fen_void_tiles()
+    {
+        let fen = "5x5 #k2#/5/R3P/5/#K2# w 1 0 - 1 1 0 w,b";
+        let b = BoardGame::from_chessito_fen(fen).unwrap();
+        assert_eq!(b.to_chessito_fen(), fen);
+        assert!(b.is_void(at(0, 0)) && !b.is_inside(at(4, 4)) && b.is_inside(at(3, 4)));
+
+        let dest = |src : At| b.actions.iter().filter(|a| a.id.src() == src).map(|a| a.id.dest()).collect::<Vec<_>>();
+        // the void tiles block the rook and the king
+        assert_eq!(dest(at(0, 2)).len(), 5);
+        assert!(!dest(at(0, 2)).contains(&at(0, 4)));
+        assert_eq!(dest(at(1, 0)).len(), 4);
+        // e4 is the last tile of the column
+        assert_eq!(b.actions.iter().filter(|a| a.id.src() == at(4, 2) && a.id.promotion().is_some()).count(), 4);
+    }
+}
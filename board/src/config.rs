@@ -0,0 +1,734 @@
+//! Rules of a game (board size, teams, starting layout, relics, draw rules...) from which a `BoardGame` is built.
+//!
+//! A config can be saved and loaded as TOML, with serde :
+//!
+//! ```toml
+//! # 4 players chess
+//! layout = "chess"
+//! size = "14x14"
+//! void_corners = 3
+//! pawns = true
+//! draw_rules = ["Stalemate"]
+//! legal_moves = false
+//! fog_of_war = false
+//! # "standard", "chess960" or "random_army", from the seed
+//! back_rank = "chess960"
+//! seed = 518
+//! energy_per_turn = 1
+//! promotion = "QNRB"
+//!
+//! [team.White]
+//! relics = ["Anticipation"]
+//! direction = "up"
+//!
+//! # the knights move like an archbishop (Betza notation, see `PieceMoves`)
+//! [ability.Archbishop]
+//! betza = "BN"
+//! replace = "N"
+//!
+//! # weights of the AI, see `EvalWeights`
+//! [eval]
+//! material = 128
+//! mobility = 1
+//! ```
+//!
+//! The missing keys keep the value of `GameConfig::default()`, the unknown keys are an error. The `[team.X]` sections replace the default teams.
+//! The `[ability.X]` sections define the custom abilities, in the order of `PieceFlags::AB_CUSTOM`.
+
+use super::*;
+use serde::{Serialize, Deserialize};
+
+/// Where the pieces are at the start
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum GameLayout
+{
+    /// The chess back rank centered on the side of each team, with or without the pawns in front of it
+    Chess { with_pawn : bool },
+    /// Lines of checker pawns on the dark tiles. Only for the teams going up or down
+    Checker { nb_line_of_pawn : AtIntType },
+    /// Standard FEN, only for White and Black
+    Fen(String),
+}
+impl GameLayout
+{
+    pub const NAMES : [&'static str; 3] = ["chess", "checker", "fen"];
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            GameLayout::Chess { .. } => Self::NAMES[0],
+            GameLayout::Checker { .. } => Self::NAMES[1],
+            GameLayout::Fen(_) => Self::NAMES[2],
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TeamConfig
+{
+    pub team : Team,
+    pub relics : Relics,
+    /// `None` for `Team::default_direction()`
+    pub direction : Option<At>,
+}
+impl TeamConfig
+{
+    pub fn new(team : Team, relics : Relics) -> Self { Self { team, relics, direction: None } }
+    pub fn with_direction(mut self, direction : At) -> Self { self.direction = Some(direction); self }
+
+    pub fn direction(&self) -> At { self.direction.unwrap_or(self.team.default_direction()) }
+}
+
+/// A custom ability, and the standard ability it replace in the starting layout
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AbilityConfig
+{
+    pub ability : CustomAbility,
+    /// Ex : `AB_CHESS_KNIGHT` to replace the knights
+    pub replace : Option<PieceFlags>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(try_from = "GameConfigToml", into = "GameConfigToml")]
+pub struct GameConfig
+{
+    /// `None` for the default size of the layout
+    pub size : Option<At>,
+    /// Size of the void square removed in each corner of the board, 0 for a rectangle
+    pub void_corners : AtIntType,
+    pub layout : GameLayout,
+    /// The teams present
+    pub teams : Vec<TeamConfig>,
+    pub draw_rules : DrawRules,
+    pub legal_moves : bool,
+    /// Each team only see the tiles its pieces occupy or can move to
+    pub fog_of_war : bool,
+    /// Only for `GameLayout::Chess`
+    pub back_rank : BackRank,
+    /// Seed of a random `back_rank`. `None` is seed 0, the front ends choose one to show it
+    pub seed : Option<u64>,
+    /// Number of actions in a turn, without the `MoveTwiceInATurn` relic
+    pub energy_per_turn : Energy,
+    /// The abilities a chess pawn can choose when promoted, the best one first
+    pub promotion : Vec<PieceFlags>,
+    pub abilities : Vec<AbilityConfig>,
+    /// Weights of the `WeightedEvaluator` of the AI. `None` for the `DefaultEvaluator`
+    pub eval : Option<EvalWeights>,
+}
+/// Chess
+impl Default for GameConfig { fn default() -> Self { Self::chess() } }
+
+impl GameConfig
+{
+    pub const MAX_SIZE : AtIntType = 26;
+    pub const DIRECTION_NAMES : [&'static str; 4] = ["up", "down", "right", "left"];
+
+    pub fn chess() -> Self
+    {
+        Self { draw_rules: DrawRules::CHESS, ..Self::chess_custom(2, true, Relics::ZERO.with_flag_add(Relic::Anticipation)) }
+    }
+
+    /// The teams are added in this order : Black, White, Yellow, Green
+    pub fn chess_custom(nb_player : usize, with_pawn : bool, relics : Relics) -> Self
+    {
+        Self
+        {
+            size: None,
+            // the cross shaped board of the 4 players chess
+            void_corners: if nb_player > 2 { 3 } else { 0 },
+            layout: GameLayout::Chess { with_pawn },
+            teams: [Team::Black, Team::White, Team::Yellow, Team::Green].into_iter().take(nb_player).map(|t| TeamConfig::new(t, relics)).collect(),
+            draw_rules: DrawRules::NONE,
+            legal_moves: false,
+            fog_of_war: false,
+            back_rank: BackRank::Standard,
+            seed: None,
+            energy_per_turn: 1,
+            promotion: PieceFlags::CHESS_PROMOTION.to_vec(),
+            abilities: vec![],
+            eval: None,
+        }
+    }
+
+    pub fn checker() -> Self { Self::checker_custom_size(at(10, 10), 4) }
+    pub fn checker_custom_size(size : At, nb_line_of_pawn : AtIntType) -> Self
+    {
+        Self
+        {
+            size: Some(size),
+            layout: GameLayout::Checker { nb_line_of_pawn },
+            teams: [Team::White, Team::Black].into_iter().map(|t| TeamConfig::new(t, Relics::ZERO)).collect(),
+            ..Self::chess_custom(2, true, Relics::ZERO)
+        }
+    }
+
+    /// The chess draw rules are used, like `BoardGame::from_fen()`
+    pub fn fen(fen : &str, relics : Relics) -> Self
+    {
+        Self { layout: GameLayout::Fen(fen.to_owned()), teams: [Team::White, Team::Black].into_iter().map(|t| TeamConfig::new(t, relics)).collect(), ..Self::chess() }
+    }
+
+    pub fn with_size(mut self, size : At) -> Self { self.size = Some(size); self }
+    pub fn with_void_corners(mut self, void_corners : AtIntType) -> Self { self.void_corners = void_corners; self }
+    pub fn with_draw_rules(mut self, draw_rules : DrawRules) -> Self { self.draw_rules = draw_rules; self }
+    pub fn with_legal_moves(mut self, legal_moves : bool) -> Self { self.legal_moves = legal_moves; self }
+    pub fn with_fog_of_war(mut self, fog_of_war : bool) -> Self { self.fog_of_war = fog_of_war; self }
+    pub fn with_back_rank(mut self, back_rank : BackRank) -> Self { self.back_rank = back_rank; self }
+    pub fn with_seed(mut self, seed : u64) -> Self { self.seed = Some(seed); self }
+    pub fn with_energy_per_turn(mut self, energy_per_turn : Energy) -> Self { self.energy_per_turn = energy_per_turn; self }
+    pub fn with_ability(mut self, ability : CustomAbility, replace : Option<PieceFlags>) -> Self { self.abilities.push(AbilityConfig { ability, replace }); self }
+    pub fn with_eval(mut self, eval : EvalWeights) -> Self { self.eval = Some(eval); self }
+
+    pub fn team(&self, t : Team) -> Option<&TeamConfig> { self.teams.iter().find(|e| e.team == t) }
+
+    /// 14x14 for the chess with more than 2 teams, 10x10 for the checker
+    pub fn default_size(&self) -> At
+    {
+        match self.layout
+        {
+            GameLayout::Chess { .. } => At::splat(if self.teams.len() > 2 { 14 } else { 8 }),
+            _ => At::splat(10),
+        }
+    }
+
+    fn team_relics(&self) -> [Relics; Team::LENGHT]
+    {
+        let mut relics = [Relics::ZERO; Team::LENGHT];
+        for t in self.teams.iter() { relics[t.team as usize] = t.relics; }
+        relics
+    }
+
+    fn check(&self) -> Result<(), GameConfigError>
+    {
+        if self.teams.is_empty() { return Err(GameConfigError::Invalid("no team".to_owned())); }
+        for (idx, t) in self.teams.iter().enumerate()
+        {
+            if self.teams[..idx].iter().any(|e| e.team == t.team) { return Err(GameConfigError::Invalid(format!("the team {} is present twice", t.team))); }
+            if t.direction().length_manhattan() != 1 { return Err(GameConfigError::Invalid(format!("invalid direction for the team {}", t.team))); }
+        }
+        if self.energy_per_turn < 1 { return Err(GameConfigError::Invalid("energy_per_turn must be at least 1".to_owned())); }
+        if self.promotion.is_empty() || self.promotion.iter().any(|p| p.is_empty_ability()) { return Err(GameConfigError::Invalid("invalid promotion".to_owned())); }
+        if self.abilities.len() > PieceFlags::AB_CUSTOM.len() { return Err(GameConfigError::Invalid(format!("at most {} custom abilities", PieceFlags::AB_CUSTOM.len()))); }
+        for (idx, a) in self.abilities.iter().enumerate()
+        {
+            if self.abilities[..idx].iter().any(|e| e.ability.name == a.ability.name) { return Err(GameConfigError::Invalid(format!("the ability {} is present twice", a.ability.name))); }
+        }
+        if self.back_rank != BackRank::Standard && !matches!(self.layout, GameLayout::Chess { .. }) { return Err(GameConfigError::Invalid(format!("the back rank {} need the chess layout", self.back_rank.name()))); }
+        let size = self.size.unwrap_or(self.default_size());
+        if self.void_corners < 0 || self.void_corners * 2 >= size.x.min(size.y) { return Err(GameConfigError::Invalid("the void corners are too big".to_owned())); }
+        if let Some(size) = self.size
+        {
+            if size.x < 1 || size.y < 1 || size.x > Self::MAX_SIZE || size.y > Self::MAX_SIZE
+            {
+                return Err(GameConfigError::Invalid(format!("the size must be between 1x1 and {}x{}", Self::MAX_SIZE, Self::MAX_SIZE)));
+            }
+        }
+        Ok(())
+    }
+
+    fn add_piece(board : &mut BoardGameNotStarted, pos : At, t : Team, flags : PieceFlags) -> Result<(), GameConfigError>
+    {
+        if board.is_void(pos) { return Err(GameConfigError::Invalid(format!("the team {} is on a void tile", t))); }
+        if !board.is_inside(pos) { return Err(GameConfigError::Invalid(format!("the board is too small for the team {}", t))); }
+        if board[pos].teams_flags() != TeamsFlags::ZERO { return Err(GameConfigError::Invalid(format!("the team {} overlap another team", t))); }
+        board.piece_add_team_and_set_flags(pos, t, flags);
+        Ok(())
+    }
+
+    /// The back rank of each team is on the side opposite to its direction
+    fn add_chess_layout(&self, board : &mut BoardGameNotStarted, with_pawn : bool) -> Result<(), GameConfigError>
+    {
+        let s = board.size();
+        let seed = self.seed.unwrap_or(0);
+        // the random army can use the custom abilities that don't replace a piece
+        let custom : Vec<PieceFlags> = self.abilities.iter().zip(PieceFlags::AB_CUSTOM).filter(|(a, _)| a.replace.is_none()).map(|(_, c)| c).collect();
+        // the back rank along the x axis (teams going up or down) and the y axis
+        let back_x = self.back_rank.pieces(s.x - 2 * self.void_corners, seed, &custom);
+        let back_y = self.back_rank.pieces(s.y - 2 * self.void_corners, seed, &custom);
+        if self.back_rank.use_seed() { board.layout_seed = Some(seed); }
+
+        // centered on the side of each team, also used by the castling
+        for t in self.teams.iter()
+        {
+            let (size_along, len) = if t.direction().x == 0 { (s.x, back_x.len()) } else { (s.y, back_y.len()) };
+            board.team_data[t.team].back_rank = Some(((size_along - len as AtIntType) / 2, len as AtIntType));
+        }
+
+        for idx in 0..back_x.len().max(back_y.len())
+        {
+            for t in [Team::Black, Team::White, Team::Yellow, Team::Green].into_iter().filter_map(|t| self.team(t))
+            {
+                let dir = t.direction();
+                let back_rank = if dir.x == 0 { &back_x } else { &back_y };
+                let Some(p) = back_rank.get(idx).copied() else { continue; };
+                let margin = board.team_data[t.team].back_rank.unwrap().0;
+                let idx = idx as AtIntType;
+                // (back rank, pawn) for the directions up, down, right, left
+                let (back, pawn) = match (dir.x, dir.y)
+                {
+                    (0, 1) => (at(margin + idx, 0), at(margin + idx, 1)),
+                    (0, _) => (at(margin + idx, s.y - 1), at(margin + idx, s.y - 2)),
+                    (1, _) => (at(0, margin + idx), at(1, margin + idx)),
+                    _      => (at(s.x - 1, margin + idx), at(s.x - 2, margin + idx)),
+                };
+                // keep the same order of the pieces as the chess start position
+                let order = if dir.y > 0 { [(pawn, PieceFlags::AB_CHESS_PAWN), (back, p)] } else { [(back, p), (pawn, PieceFlags::AB_CHESS_PAWN)] };
+                for (pos, flags) in order
+                {
+                    if flags == PieceFlags::AB_CHESS_PAWN && !with_pawn { continue; }
+                    Self::add_piece(board, pos, t.team, flags)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add_checker_layout(&self, board : &mut BoardGameNotStarted, nb_line_of_pawn : AtIntType) -> Result<(), GameConfigError>
+    {
+        let s = board.size();
+        for y in 0..nb_line_of_pawn
+        {
+            for x in (0..s.x / 2).map(|x| 2 * x)
+            {
+                for t in [Team::Black, Team::White].into_iter().filter_map(|t| self.team(t))
+                {
+                    let pos = if t.direction() == At::Y { at(x + y % 2, y) } else { at(x + (y + 1) % 2, s.y - 1 - y) };
+                    Self::add_piece(board, pos, t.team, PieceFlags::AB_DAME_PAWN)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_rules(&self, board : &mut BoardGameNotStarted)
+    {
+        for t in self.teams.iter()
+        {
+            board.team_data[t.team].relics = t.relics;
+            board.team_data[t.team].direction = t.direction();
+        }
+        board.draw_rules = self.draw_rules;
+        board.legal_moves = self.legal_moves;
+        board.fog_of_war = self.fog_of_war;
+        board.energy_per_turn = self.energy_per_turn;
+        board.chess_promotion = SmallVec::from_slice(&self.promotion);
+        board.custom_abilities = CustomAbilities::new(self.abilities.iter().map(|a| a.ability.clone()).collect());
+    }
+
+    /// Give the custom abilities to the pieces of the layout they replace
+    fn apply_abilities_replace(&self, board : &mut BoardGameNotStarted)
+    {
+        for (a, custom) in self.abilities.iter().zip(PieceFlags::AB_CUSTOM)
+        {
+            let Some(replace) = a.replace else { continue; };
+            for pos in board.iter_idx()
+            {
+                let flags = board[pos].flags;
+                if flags.ability() == replace { board[pos].flags = flags.without_flag(PieceFlags::ABILITY_MASK) | custom; }
+            }
+        }
+    }
+
+    pub fn build(&self) -> Result<BoardGame, GameConfigError>
+    {
+        self.check()?;
+
+        let start = match &self.layout
+        {
+            GameLayout::Fen(fen) =>
+            {
+                if let Some(t) = self.teams.iter().find(|t| t.team != Team::White && t.team != Team::Black) { return Err(GameConfigError::Invalid(format!("the team {} can't be in a FEN", t.team))); }
+                if self.teams.iter().any(|t| t.direction() != t.team.default_direction()) { return Err(GameConfigError::Invalid("the directions can't be changed with a FEN".to_owned())); }
+                if self.void_corners != 0 { return Err(GameConfigError::Invalid("the void corners can't be used with a FEN".to_owned())); }
+
+                let start = BoardGameFixedTime::from_fen_with_setup(fen, self.team_relics(), |board| { self.apply_rules(board); self.apply_abilities_replace(board); }).map_err(GameConfigError::Fen)?;
+                if self.size.is_some_and(|size| size != start.size()) { return Err(GameConfigError::Invalid(format!("the size of the FEN is {}x{}", start.size().x, start.size().y))); }
+                start
+            },
+            GameLayout::Chess { with_pawn } =>
+            {
+                let mut board = BoardGameNotStarted::new_empty(self.size.unwrap_or(self.default_size()));
+                board.set_void_corners(self.void_corners);
+                self.apply_rules(&mut board);
+                self.add_chess_layout(&mut board, *with_pawn)?;
+                self.apply_abilities_replace(&mut board);
+                BoardGameFixedTime::new(board)
+            },
+            GameLayout::Checker { nb_line_of_pawn } =>
+            {
+                if let Some(t) = self.teams.iter().find(|t| t.direction().x != 0) { return Err(GameConfigError::Invalid(format!("the team {} must go up or down in the checker layout", t.team))); }
+                let mut board = BoardGameNotStarted::new_empty(self.size.unwrap_or(self.default_size()));
+                board.set_void_corners(self.void_corners);
+                self.apply_rules(&mut board);
+                self.add_checker_layout(&mut board, *nb_line_of_pawn)?;
+                self.apply_abilities_replace(&mut board);
+                BoardGameFixedTime::new(board)
+            },
+        };
+        Ok(BoardGame::new_from_fixed_time(start))
+    }
+
+    pub fn direction_name(direction : At) -> Option<&'static str>
+    {
+        [At::Y, -At::Y, At::X, -At::X].iter().position(|d| *d == direction).map(|idx| Self::DIRECTION_NAMES[idx])
+    }
+    pub fn direction_from_name(name : &str) -> Option<At>
+    {
+        Self::DIRECTION_NAMES.iter().position(|n| *n == name).map(|idx| [At::Y, -At::Y, At::X, -At::X][idx])
+    }
+
+    pub fn to_toml(&self) -> String { toml::to_string(self).unwrap() }
+
+    /// Parse a TOML config. The missing keys keep the value of `GameConfig::default()`
+    pub fn from_toml(config : &str) -> Result<Self, GameConfigError>
+    {
+        let file : GameConfigToml = toml::from_str(config).map_err(toml_error)?;
+        Self::try_from(file)
+    }
+
+    pub fn load(path : &str) -> Result<Self, GameConfigError>
+    {
+        let config = std::fs::read_to_string(path).map_err(|e| GameConfigError::Io(path.to_owned(), e.to_string()))?;
+        Self::from_toml(&config)
+    }
+    pub fn save(&self, path : &str) -> Result<(), GameConfigError>
+    {
+        std::fs::write(path, self.to_toml()).map_err(|e| GameConfigError::Io(path.to_owned(), e.to_string()))
+    }
+}
+
+/// The TOML file of a `GameConfig`, see the module doc
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GameConfigToml
+{
+    layout : String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pawns : Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pawn_lines : Option<AtIntType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fen : Option<String>,
+    /// `"WxH"`, or `"N"` for a square
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size : Option<String>,
+    #[serde(skip_serializing_if = "is_zero")]
+    void_corners : AtIntType,
+    draw_rules : Vec<String>,
+    legal_moves : bool,
+    #[serde(skip_serializing_if = "is_false")]
+    fog_of_war : bool,
+    back_rank : String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed : Option<u64>,
+    energy_per_turn : Energy,
+    /// The FEN letters, ex : `"QNRB"`
+    promotion : String,
+    /// `[team.X]`, in the order of the file
+    team : toml::Table,
+    /// `[ability.X]`, in the order of the file
+    #[serde(skip_serializing_if = "toml::Table::is_empty")]
+    ability : toml::Table,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    eval : Option<EvalWeights>,
+}
+impl Default for GameConfigToml { fn default() -> Self { GameConfig::default().into() } }
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct TeamToml
+{
+    relics : Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    direction : Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AbilityToml
+{
+    betza : String,
+    /// The FEN letter of the replaced piece
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    replace : Option<String>,
+}
+
+fn is_zero(v : &AtIntType) -> bool { *v == 0 }
+fn is_false(v : &bool) -> bool { !*v }
+fn toml_error(e : impl Display) -> GameConfigError { GameConfigError::Toml(e.to_string().trim_end().to_owned()) }
+
+impl From<GameConfig> for GameConfigToml
+{
+    fn from(c : GameConfig) -> Self
+    {
+        let layout = c.layout.name().to_owned();
+        let (pawns, pawn_lines, fen) = match c.layout
+        {
+            GameLayout::Chess { with_pawn } => (Some(with_pawn), None, None),
+            GameLayout::Checker { nb_line_of_pawn } => (None, Some(nb_line_of_pawn), None),
+            GameLayout::Fen(fen) => (None, None, Some(fen)),
+        };
+        let team = c.teams.iter().map(|t|
+        {
+            let relics = Relic::iter().filter(|r| t.relics.flag_have(*r)).map(|r| format!("{:?}", r)).collect();
+            let direction = t.direction.and_then(GameConfig::direction_name).map(|n| n.to_owned());
+            (t.team.to_string(), toml::Value::try_from(TeamToml { relics, direction }).unwrap())
+        }).collect();
+        let ability = c.abilities.iter().map(|a|
+        {
+            let replace = a.replace.and_then(|r| r.fen_char(Team::White)).map(|c| c.to_string());
+            (a.ability.name.clone(), toml::Value::try_from(AbilityToml { betza: a.ability.moves.to_string(), replace }).unwrap())
+        }).collect();
+
+        Self
+        {
+            layout, pawns, pawn_lines, fen,
+            size: c.size.map(|s| format!("{}x{}", s.x, s.y)),
+            void_corners: c.void_corners,
+            draw_rules: c.draw_rules.to_names().split(',').filter(|n| !n.is_empty()).map(|n| n.to_owned()).collect(),
+            legal_moves: c.legal_moves,
+            fog_of_war: c.fog_of_war,
+            back_rank: c.back_rank.name().to_owned(),
+            seed: c.seed,
+            energy_per_turn: c.energy_per_turn,
+            promotion: c.promotion.iter().filter_map(|p| p.fen_char(Team::White)).collect(),
+            team,
+            ability,
+            eval: c.eval,
+        }
+    }
+}
+
+impl TryFrom<GameConfigToml> for GameConfig
+{
+    type Error = GameConfigError;
+
+    fn try_from(file : GameConfigToml) -> Result<Self, GameConfigError>
+    {
+        let invalid = |key : &str, value : &str| GameConfigError::InvalidValue(key.to_owned(), value.to_owned());
+
+        let layout = match file.layout.as_str()
+        {
+            "chess" => GameLayout::Chess { with_pawn: file.pawns.unwrap_or(true) },
+            "checker" => GameLayout::Checker { nb_line_of_pawn: file.pawn_lines.unwrap_or(4) },
+            "fen" => GameLayout::Fen(file.fen.ok_or_else(|| GameConfigError::Invalid("the fen layout need a fen".to_owned()))?),
+            _ => return Err(invalid("layout", &file.layout)),
+        };
+        if let GameLayout::Checker { nb_line_of_pawn } = layout
+        {
+            if nb_line_of_pawn < 0 { return Err(invalid("pawn_lines", &nb_line_of_pawn.to_string())); }
+        }
+
+        let size = match &file.size
+        {
+            Some(size) =>
+            {
+                let (x, y) = size.split_once('x').unwrap_or((size, size));
+                Some(at(x.trim().parse().map_err(|_| invalid("size", size))?, y.trim().parse().map_err(|_| invalid("size", size))?))
+            },
+            None => None,
+        };
+
+        let draw_rules = DrawRules::from_names(&file.draw_rules.join(",")).ok_or_else(|| invalid("draw_rules", &file.draw_rules.join(", ")))?;
+        let back_rank = BackRank::from_name(&file.back_rank).ok_or_else(|| invalid("back_rank", &file.back_rank))?;
+        let promotion = file.promotion.chars()
+            .map(|ch| PieceFlags::from_fen_char(ch).map(|(_, f)| f.ability()).filter(|f| PieceFlags::CHESS_PROMOTION.contains(f)))
+            .collect::<Option<Vec<_>>>().ok_or_else(|| invalid("promotion", &file.promotion))?;
+
+        let mut teams = vec![];
+        for (name, value) in file.team
+        {
+            let team = Team::iter().find(|t| t.to_string() == name).ok_or_else(|| GameConfigError::UnknownSection(format!("team.{}", name)))?;
+            let t : TeamToml = value.try_into().map_err(toml_error)?;
+            let mut relics = Relics::ZERO;
+            for r in t.relics.iter()
+            {
+                relics.flag_add(Relic::iter().find(|e| format!("{:?}", e) == *r).ok_or_else(|| invalid("relics", r))?);
+            }
+            let direction = match &t.direction
+            {
+                Some(d) => Some(Self::direction_from_name(d).ok_or_else(|| invalid("direction", d))?),
+                None => None,
+            };
+            teams.push(TeamConfig { team, relics, direction });
+        }
+
+        let mut abilities = vec![];
+        for (name, value) in file.ability
+        {
+            let a : AbilityToml = value.try_into().map_err(toml_error)?;
+            let ability = CustomAbility::new(&name, &a.betza).map_err(|e| invalid("betza", &format!("{} ({})", a.betza, e)))?;
+            let replace = match &a.replace
+            {
+                Some(r) =>
+                {
+                    let mut chars = r.chars();
+                    let replace = match (chars.next(), chars.next()) { (Some(c), None) => PieceFlags::from_fen_char(c).map(|(_, f)| f.ability()), _ => None };
+                    Some(replace.ok_or_else(|| invalid("replace", r))?)
+                },
+                None => None,
+            };
+            abilities.push(AbilityConfig { ability, replace });
+        }
+
+        if let Some(eval) = &file.eval { eval.check()?; }
+
+        let c = GameConfig
+        {
+            size, void_corners: file.void_corners, layout, teams, draw_rules, legal_moves: file.legal_moves, fog_of_war: file.fog_of_war,
+            back_rank, seed: file.seed, energy_per_turn: file.energy_per_turn, promotion, abilities, eval: file.eval,
+        };
+        c.check()?;
+        Ok(c)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum GameConfigError
+{
+    Io(String, String),
+    /// Invalid TOML syntax, unknown key or value of the wrong type
+    Toml(String),
+    UnknownSection(String),
+    InvalidValue(String, String),
+    Fen(FenError),
+    /// The rules can't be used to build a game
+    Invalid(String),
+}
+impl Display for GameConfigError
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult
+    {
+        match self
+        {
+            GameConfigError::Io(path, e) => write!(f, "can't read {} : {}", path, e),
+            GameConfigError::Toml(e) => write!(f, "invalid toml : {}", e),
+            GameConfigError::UnknownSection(s) => write!(f, "unknown section [{}] (expected [team.White], [team.Black]... or [ability.Name])", s),
+            GameConfigError::InvalidValue(key, s) => write!(f, "invalid value \"{}\" for {}", s, key),
+            GameConfigError::Fen(e) => write!(f, "invalid fen : {}", e),
+            GameConfigError::Invalid(s) => write!(f, "{}", s),
+        }
+    }
+}
+impl std::error::Error for GameConfigError {}
+
+impl BoardGame
+{
+    pub fn from_config(config : &GameConfig) -> Result<Self, GameConfigError> { config.build() }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn assert_same_start(a : &BoardGame, b : &BoardGame)
+    {
+        assert_eq!(a.size(), b.size());
+        for idx in a.iter_idx()
+        {
+            assert_eq!(a[idx].flags, b[idx].flags);
+            assert_eq!(a[idx].teams_flags(), b[idx].teams_flags());
+        }
+        assert_eq!(a.current_team, b.current_team);
+        assert_eq!(a.actions.len(), b.actions.len());
+        assert!(a.actions.iter().all(|e| b.action_id_is_valid(e.id)));
+    }
+
+    #[test]
+    fn config_toml_round_trip()
+    {
+        let four_players = GameConfig::from_toml(include_str!("../config/chess_4_players.toml")).unwrap();
+        assert_eq!(four_players.teams.len(), 4);
+        assert_eq!(four_players.team(Team::White).unwrap().relics, Relics::ZERO.with_flag_add(Relic::Anticipation));
+
+        for c in [GameConfig::chess(), GameConfig::checker(), GameConfig::fen("8/8/8/8/8/8/8/K6k w - - 0 1", Relics::ZERO), four_players,
+            GameConfig::chess_custom(2, false, Relics::ZERO).with_size(at(10, 8)).with_legal_moves(true).with_energy_per_turn(2),
+            GameConfig::from_toml(include_str!("../config/dark_chess.toml")).unwrap(), GameConfig::from_toml(include_str!("../config/chess960.toml")).unwrap(),
+            GameConfig::from_toml(include_str!("../config/random_army_4_players.toml")).unwrap()]
+        {
+            assert_eq!(GameConfig::from_toml(&c.to_toml()).unwrap(), c);
+        }
+
+        assert_eq!(GameConfig::from_toml("layout = \"go\""), Err(GameConfigError::InvalidValue("layout".to_owned(), "go".to_owned())));
+        assert_eq!(GameConfig::from_toml("[team.Red]"), Err(GameConfigError::UnknownSection("team.Red".to_owned())));
+        assert!(GameConfig::from_toml("energy_per_turn = 0").is_err());
+
+        let fairy = GameConfig::from_toml(include_str!("../config/fairy_pieces.toml")).unwrap();
+        assert_eq!(fairy.abilities.len(), 3);
+        assert_eq!(fairy.abilities[0].replace, Some(PieceFlags::AB_CHESS_KNIGHT));
+        assert_eq!(GameConfig::from_toml(&fairy.to_toml()).unwrap(), fairy);
+        assert!(GameConfig::from_toml(include_str!("../config/dark_chess.toml")).unwrap().build().unwrap().fog_of_war);
+        assert!(matches!(GameConfig::from_toml("[ability.Wrong]\nbetza = \"Y\""), Err(GameConfigError::InvalidValue(..))));
+        assert!(GameConfig::from_toml("layout = \"checker\"\nback_rank = \"chess960\"").is_err());
+    }
+
+    #[test]
+    fn config_toml_invalid()
+    {
+        let is_toml_error = |config : &str| matches!(GameConfig::from_toml(config), Err(GameConfigError::Toml(_)));
+        // syntax, unknown key, wrong type, key present twice, ability without a betza
+        assert!(is_toml_error("layout = "));
+        assert!(is_toml_error("[team.White"));
+        assert!(is_toml_error("pawn = true"));
+        assert!(is_toml_error("[team.White]\ncolor = \"red\""));
+        assert!(is_toml_error("legal_moves = \"yes\""));
+        assert!(is_toml_error("energy_per_turn = 1000"));
+        assert!(is_toml_error("[team.White]\n[team.White]"));
+        assert!(is_toml_error("[ability.Camel]\nreplace = \"B\""));
+
+        assert_eq!(GameConfig::from_toml("size = \"8x\""), Err(GameConfigError::InvalidValue("size".to_owned(), "8x".to_owned())));
+        assert_eq!(GameConfig::from_toml("promotion = \"QK\""), Err(GameConfigError::InvalidValue("promotion".to_owned(), "QK".to_owned())));
+        assert_eq!(GameConfig::from_toml("[team.White]\nrelics = [\"Wings\"]"), Err(GameConfigError::InvalidValue("relics".to_owned(), "Wings".to_owned())));
+        assert_eq!(GameConfig::from_toml("layout = \"checker\"\npawn_lines = -1"), Err(GameConfigError::InvalidValue("pawn_lines".to_owned(), "-1".to_owned())));
+        assert_eq!(GameConfig::from_toml("layout = \"fen\""), Err(GameConfigError::Invalid("the fen layout need a fen".to_owned())));
+
+        // the rest of the TOML syntax : multi-line arrays, inline tables, literal strings
+        let c = GameConfig::from_toml("size = '10x8'\ndraw_rules = [\n  \"Stalemate\", # comment\n]\nteam = { White = {}, Black = { direction = \"down\" } }").unwrap();
+        assert_eq!((c.size, c.draw_rules, c.teams.len()), (Some(at(10, 8)), DrawRules::from_names("Stalemate").unwrap(), 2));
+        assert_eq!(c.teams[0].relics, Relics::ZERO);
+    }
+
+    #[test]
+    fn config_build()
+    {
+        assert_same_start(&GameConfig::chess().build().unwrap(), &BoardGame::from_fen(BoardGame::FEN_CHESS, Relics::ZERO.with_flag_add(Relic::Anticipation)).unwrap());
+        let four_players = GameConfig::chess_custom(4, true, Relics::ZERO).build().unwrap();
+        assert_eq!(four_players.size(), At::splat(14));
+        assert!(four_players.is_void(at(2, 2)) && four_players.is_void(at(11, 13)) && !four_players.is_void(at(3, 0)));
+        assert_eq!(GameConfig::from_toml(include_str!("../config/chess_4_players.toml")).unwrap().void_corners, 3);
+        assert!(GameConfig::chess().with_void_corners(4).build().is_err());
+
+        // the seed 518 of Chess960 is the standard position
+        let chess960 = GameConfig::from_toml(include_str!("../config/chess960.toml")).unwrap();
+        assert_eq!((chess960.back_rank, chess960.seed), (BackRank::Chess960, Some(518)));
+        assert_same_start(&chess960.build().unwrap(), &GameConfig::chess().build().unwrap());
+        assert_eq!(GameConfig::chess().build().unwrap().layout_seed, None);
+
+        // the pawns of White go to the right, like Yellow
+        let mut c = GameConfig::chess_custom(2, true, Relics::ZERO).with_size(at(12, 8));
+        c.teams[1].direction = Some(At::X);
+        let b = c.build().unwrap();
+        assert!(b[at(0, 0)].is_also_team(Team::White) && b[at(1, 0)].is_also_chess_pawn());
+        assert_eq!(c.with_size(at(8, 8)).build().err(), Some(GameConfigError::Invalid("the team White overlap another team".to_owned())));
+
+        // 2 actions by turn, and no promotion to a queen
+        let mut c = GameConfig::fen("8/P7/8/8/8/8/7p/K6k w - - 0 1", Relics::ZERO).with_energy_per_turn(2);
+        c.promotion = vec![PieceFlags::AB_CHESS_KNIGHT];
+        let mut b = c.build().unwrap();
+        assert_eq!(b.actions.iter().filter(|a| a.id.src() == at(0, 6)).count(), 1);
+        b.execute(b.san_to_action_id("a8=N").unwrap());
+        assert_eq!(b.current_team, Team::White);
+
+        // the knights are archbishops
+        let archbishop = CustomAbility::new("Archbishop", "BN").unwrap();
+        let mut b = GameConfig::chess().with_ability(archbishop, Some(PieceFlags::AB_CHESS_KNIGHT)).build().unwrap();
+        assert_eq!(b[at(1, 0)].ability(), PieceFlags::AB_CUSTOM[0]);
+        assert_eq!(b.actions.len(), 20);
+        b.execute(b.san_to_action_id("f3").unwrap());
+        b.execute(b.san_to_action_id("e6").unwrap());
+        assert_eq!(b.action_id_to_san(ActionID::Move(at(6, 0), at(4, 2))).unwrap(), "[1]e3");
+        assert!(b.action_id_is_valid(ActionID::Move(at(6, 0), at(7, 2))));
+    }
+}
@@ -0,0 +1,189 @@
+use super::*;
+
+/// Which draw rules are used by a game. The variants don't use any by default.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct DrawRules
+{
+    /// A team without any action that can't be captured is a draw instead of a defeat (only with 2 teams alive)
+    pub stalemate : bool,
+    /// The same position 3 times with the same team to play
+    pub threefold_repetition : bool,
+    /// 100 actions without capture or pawn move
+    pub fifty_move : bool,
+    /// No team have enough pieces to capture a crown
+    pub insufficient_material : bool,
+}
+impl DrawRules
+{
+    pub const NONE : Self = Self { stalemate: false, threefold_repetition: false, fifty_move: false, insufficient_material: false };
+    pub const CHESS : Self = Self { stalemate: true, threefold_repetition: true, fifty_move: true, insufficient_material: true };
+
+    pub const FIFTY_MOVE_NB_ACTIONS : Turn = 100;
+
+    pub const NAMES : [&'static str; 4] = ["Stalemate", "ThreefoldRepetition", "FiftyMove", "InsufficientMaterial"];
+
+    fn rules(self) -> [bool; 4] { [self.stalemate, self.threefold_repetition, self.fifty_move, self.insufficient_material] }
+    fn from_rules(r : [bool; 4]) -> Self { Self { stalemate: r[0], threefold_repetition: r[1], fifty_move: r[2], insufficient_material: r[3] } }
+
+    pub fn is_none(self) -> bool { self == Self::NONE }
+
+    /// The name of the used rules separated by `,`
+    pub fn to_names(self) -> String
+    {
+        Self::NAMES.iter().zip(self.rules()).filter(|(_, used)| *used).map(|(n, _)| *n).collect::<Vec<_>>().join(",")
+    }
+    pub fn from_names(names : &str) -> Option<Self>
+    {
+        let mut r = [false; 4];
+        for name in names.split(',').map(|e| e.trim()).filter(|e| !e.is_empty())
+        {
+            r[Self::NAMES.iter().position(|n| *n == name)?] = true;
+        }
+        Some(Self::from_rules(r))
+    }
+}
+
+impl BoardGameNotStarted
+{
+    /// Only bare kings, or kings with a single knight or bishop, or kings with bishops on the same tile color
+    pub fn is_insufficient_material(&self) -> bool
+    {
+        let mut nb_knight = 0;
+        let mut bishop_tile_color = [false; 2];
+
+        for at in self.iter_idx()
+        {
+            let p = &self[at];
+            match p.ability()
+            {
+                PieceFlags::ZERO | PieceFlags::AB_CHESS_KING => {},
+                PieceFlags::AB_CHESS_KNIGHT => nb_knight += 1,
+                PieceFlags::AB_CHESS_BISHOP => bishop_tile_color[(at.x + at.y) as usize % 2] = true,
+                _ => return false,
+            }
+        }
+
+        let nb_bishop_color = bishop_tile_color.iter().filter(|e| **e).count();
+        (nb_knight == 0 && nb_bishop_color <= 1) || (nb_knight == 1 && nb_bishop_color == 0)
+    }
+}
+
+impl BoardGameFixedTime
+{
+    /// End the game in a draw
+    pub fn execute_draw(&mut self)
+    {
+        self.is_draw = true;
+        for t in Team::iter()
+        {
+            self.execute_team_lose(t);
+        }
+    }
+
+    /// Can a team other than `t` capture a crown of `t` ?
+    pub fn is_crown_attacked(&self, t : Team) -> bool
+    {
+        Team::iter().filter(|other| *other != t && self.team_data[*other].alive).any(|other|
+            self.actions_for_team(other).iter().any(|a| a.iter().any(|e| match e
+            {
+                UnitAction::Capture(_, dest) => self[*dest].have_flag(PieceFlags::CROWN) && self[*dest].is_also_team(t),
+                _ => false,
+            }))
+        )
+    }
+
+    /// Called when the current team can't do any action at the begining of its turn
+    pub(crate) fn execute_no_action_left(&mut self)
+    {
+        if self.draw_rules.stalemate && self.nb_team_alive <= 2 && !self.is_crown_attacked(self.current_team)
+        {
+            self.execute_draw();
+        }else
+        {
+            self.execute_team_lose(self.current_team);
+        }
+    }
+
+    /// The draw rules that only depend on the current position
+    pub(crate) fn apply_draw_rules(&mut self)
+    {
+        if self.is_end_of_the_game() { return; }
+
+        if (self.draw_rules.fifty_move && self.half_move_clock >= DrawRules::FIFTY_MOVE_NB_ACTIONS) ||
+           (self.draw_rules.insufficient_material && self.is_insufficient_material())
+        {
+            self.execute_draw();
+        }
+    }
+}
+
+impl BoardGame
+{
+    /// Number of time the current position was already reached
+    pub fn nb_repetition(&self) -> usize
+    {
+        let hash = self.zobrist;
+        self.played_positions().iter().filter(|p| p.zobrist == hash).count()
+    }
+
+    pub(crate) fn apply_threefold_repetition(&mut self)
+    {
+        if self.draw_rules.threefold_repetition && !self.is_end_of_the_game() && self.nb_repetition() >= 3
+        {
+            let cur = self.current_mut();
+            cur.execute_draw();
+            cur.actions.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn chess_from_fen(fen : &str) -> BoardGame { BoardGame::from_fen(fen, Relics::ZERO.with_flag_add(Relic::Anticipation)).unwrap() }
+
+    #[test]
+    fn draw_stalemate()
+    {
+        let mut b = chess_from_fen("7k/8/6Q1/8/8/8/8/K7 w - - 0 1");
+        b.execute(b.san_to_action_id("Qf7").unwrap());
+        assert_eq!(b.end_game_result(), Some(BoardResult::Draw));
+
+        // without the rule, the team without action lose
+        let mut b = BoardGameNotStarted::from_fen("7k/8/6Q1/8/8/8/8/K7 w - - 0 1", Relics::ZERO.with_flag_add(Relic::Anticipation)).unwrap();
+        b.draw_rules = DrawRules::NONE;
+        let mut b = BoardGame::new(b);
+        b.execute(b.san_to_action_id("Qf7").unwrap());
+        assert_eq!(b.end_game_result(), Some(BoardResult::WinnerIs(Team::White)));
+    }
+
+    #[test]
+    fn draw_threefold_repetition()
+    {
+        let mut b = BoardGame::new_chess();
+        for san in ["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1"]
+        {
+            b.execute(b.san_to_action_id(san).unwrap());
+            assert!(!b.is_end_of_the_game());
+        }
+        b.execute(b.san_to_action_id("Ng8").unwrap());
+        assert_eq!(b.nb_repetition(), 3);
+        assert_eq!(b.end_game_result(), Some(BoardResult::Draw));
+    }
+
+    #[test]
+    fn draw_fifty_move_and_insufficient_material()
+    {
+        let mut b = chess_from_fen("7k/8/8/8/8/8/8/KR6 w - - 99 80");
+        b.execute(b.san_to_action_id("Rb2").unwrap());
+        assert_eq!(b.end_game_result(), Some(BoardResult::Draw));
+
+        let mut b = chess_from_fen("7k/8/8/8/8/8/Kp6/2B5 b - - 0 1");
+        assert!(!b.is_end_of_the_game());
+        b.execute(b.san_to_action_id("Kg7").unwrap());
+        b.execute(b.san_to_action_id("Kxb2").unwrap());
+        assert_eq!(b.end_game_result(), Some(BoardResult::Draw));
+    }
+}
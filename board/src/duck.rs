@@ -0,0 +1,97 @@
+//! The `DuckButDifferent` relic : a duck that belong to no team is placed on an empty tile after each action of a team with the relic.
+//!
+//! The duck is a tile with the `PieceFlags::DUCK` flag. It block the moves of every piece and can't be captured.
+//! After an action, the team keep the hand with `duck_to_place` and its only actions are the `ActionID::Duck` placements
+//! on the empty tiles (the duck must move). The first placement add the duck on the board.
+
+use super::*;
+
+impl BoardGameNotStarted
+{
+    /// Tile of the duck, if it was already placed
+    pub fn duck_pos(&self) -> Option<At> { self.iter_idx().find(|a| self[*a].is_duck()) }
+
+    /// Not a void tile, and without piece or duck
+    pub fn is_empty_tile(&self, at : At) -> bool { self.is_inside(at) && self[at].is_none_flag() && self[at].teams_flags().is_none_flag() }
+}
+
+impl BoardGameFixedTime
+{
+    /// One placement for each empty tile
+    pub(crate) fn actions_duck(&self, actions : &mut Actions)
+    {
+        let old = self.duck_pos();
+        for dest in self.iter_idx().filter(|a| self.is_empty_tile(*a))
+        {
+            let mut action = Action::new(ActionID::Duck(dest), self.current_team.flags());
+            action.push(self, UnitAction::DuckPlace(old, dest));
+            actions.push(action);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn duck_chess() -> BoardGame { BoardGame::new_chess_custom(2, true, Relics::ZERO.with_flag_add(Relic::DuckButDifferent)) }
+
+    #[test]
+    fn duck_placement_is_mandatory()
+    {
+        let mut b = duck_chess();
+        b.execute(b.san_to_action_id("e4").unwrap());
+        assert_eq!(b.current_team, Team::White);
+        assert!(b.duck_to_place);
+        assert_eq!(b.actions.len(), 32);
+        assert!(b.actions.iter().all(|a| matches!(a.id, ActionID::Duck(_))));
+
+        b.execute(ActionID::Duck(at(4, 5)));
+        assert_eq!(b.current_team, Team::Black);
+        assert_eq!(b.duck_pos(), Some(at(4, 5)));
+        assert!(!b.actions.iter().any(|a| a.id.src() == at(4, 6)));
+
+        b.execute(b.san_to_action_id("d5").unwrap());
+        assert!(!b.action_id_is_valid(ActionID::Duck(at(4, 5))));
+        assert_eq!(b.action_id_to_san(ActionID::Duck(at(4, 4))).unwrap(), "@e5");
+        b.execute(b.san_to_action_id("@e5").unwrap());
+        assert_eq!(b.duck_pos(), Some(at(4, 4)));
+        assert!(b.is_empty_tile(at(4, 5)));
+        assert_eq!(b.zobrist, b.current().zobrist_compute());
+
+        assert_eq!(duck_chess().perft(2), 20 * 32);
+    }
+
+    #[test]
+    fn duck_blocks_and_cant_be_captured()
+    {
+        let b = BoardGame::from_chessito_fen("8x8 4k3/8/8/8/R2{:@}4/8/8/4K3 w 1 0 - 1 1 0 wD,bD").unwrap();
+        let rook : Vec<At> = b.actions.iter().filter(|a| a.id.src() == at(0, 3) && a.id.dest().y == 3).map(|a| a.id.dest()).collect();
+        assert_eq!(rook, vec![at(1, 3), at(2, 3)]);
+        assert!(!b.can_be_captured_by_any(at(3, 3)));
+    }
+
+    #[test]
+    fn duck_not_on_void_tiles()
+    {
+        // 25 tiles, 4 void corners and 4 pieces
+        let mut b = BoardGame::from_chessito_fen("5x5 #k2#/5/R3P/5/#K2# w 1 0 - 1 1 0 wD,bD").unwrap();
+        b.execute(ActionID::Move(at(0, 2), at(0, 1)));
+        assert!(b.duck_to_place);
+        assert_eq!(b.actions.len(), 25 - 4 - 4);
+        assert!(b.actions.iter().all(|a| b.is_inside(a.id.dest()) && b.is_empty_tile(a.id.dest())));
+        assert!(!b.action_id_is_valid(ActionID::Duck(at(0, 0))));
+    }
+
+    #[test]
+    fn duck_searched_by_the_ai()
+    {
+        let mut b = duck_chess();
+        b.execute(b.san_to_action_id("e4").unwrap());
+        let r = b.minimax_custom(2);
+        assert!(matches!(r.action_id, Some(ActionID::Duck(_))));
+        b.execute(r.action_id.unwrap());
+        assert!(!matches!(b.minimax_custom(2).action_id, Some(ActionID::Duck(_))));
+    }
+}
@@ -0,0 +1,221 @@
+//! Evaluation of a position by the minimax search.
+//!
+//! The search only call the `Evaluator` on a position that is not the end of the game (the victory, defeat and draw are scored by the search).
+//! The score is seen by the team `t`, and is better when higher.
+//!
+//! The weights of the `WeightedEvaluator` are the `[eval]` table of a `GameConfig` TOML file :
+//! ```toml
+//! [eval]
+//! material = 128
+//! mobility = 1
+//! king_safety = 8
+//! ```
+
+use super::*;
+use serde::{Serialize, Deserialize};
+
+pub trait Evaluator : Clone + Debug + Send + Sync
+{
+    fn eval(&self, game : &BoardGameFixedTime, t : Team) -> Score;
+}
+
+/// Piece value times 128, promotion distance times 4, and mobility. Squared for multiplayer
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct DefaultEvaluator;
+impl Evaluator for DefaultEvaluator
+{
+    fn eval(&self, game : &BoardGameFixedTime, t : Team) -> Score
+    {
+        let mut score = 0 as Score;
+        for team in Team::iter()
+        {
+            let piece_score = if game.team_data[team].alive { game.team_data[team].alive_piece_value as Score } else { 0. };
+            let nb_action_score = game.eval_mobility(team);
+            let piece_positionnal_score = game.eval_promotion_distance(team);
+
+            let local_score = piece_score * 128. + piece_positionnal_score * 4. + nb_action_score;
+
+            // squared for multiplayer in order to minimize each player score equaly
+            score += (local_score * local_score) * if t == team { 1. } else { -1. };
+        }
+        score
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EvalWeights
+{
+    /// Sum of the value of the alive pieces
+    pub material : Score,
+    /// Number of action
+    pub mobility : Score,
+    /// Opposite of the distance to the promoting tile
+    pub promotion_distance : Score,
+    /// Number of ally pieces around the crowns
+    pub king_safety : Score,
+    /// Opposite of the distance of the pieces to the closest enemy crown
+    pub crown_proximity : Score,
+    /// Number of relics
+    pub relic : Score,
+}
+/// Same score as the `DefaultEvaluator`
+impl Default for EvalWeights { fn default() -> Self { Self { material: 128., mobility: 1., promotion_distance: 4., king_safety: 0., crown_proximity: 0., relic: 0. } } }
+impl EvalWeights
+{
+    pub const NAMES : [&'static str; 6] = ["material", "mobility", "promotion_distance", "king_safety", "crown_proximity", "relic"];
+
+    fn weights(&self) -> [Score; 6] { [self.material, self.mobility, self.promotion_distance, self.king_safety, self.crown_proximity, self.relic] }
+
+    /// The weights must be finite
+    pub fn check(&self) -> Result<(), GameConfigError>
+    {
+        match Self::NAMES.iter().zip(self.weights()).find(|(_, w)| !w.is_finite())
+        {
+            Some((name, w)) => Err(GameConfigError::InvalidValue(name.to_string(), w.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Parse the `[eval]` table of a TOML config. The missing weights keep their default value
+    pub fn from_toml(config : &str) -> Result<Self, GameConfigError> { Ok(GameConfig::from_toml(config)?.eval.unwrap_or_default()) }
+    /// Only the `[eval]` table
+    pub fn to_toml(&self) -> String { toml::to_string(&EvalWeightsToml { eval: *self }).unwrap() }
+
+    pub fn load(path : &str) -> Result<Self, GameConfigError> { Ok(GameConfig::load(path)?.eval.unwrap_or_default()) }
+}
+
+#[derive(Serialize)]
+struct EvalWeightsToml
+{
+    eval : EvalWeights,
+}
+
+/// Sum of weighted features for each team. Squared for multiplayer like the `DefaultEvaluator`
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct WeightedEvaluator
+{
+    pub weights : EvalWeights,
+}
+impl WeightedEvaluator
+{
+    pub fn new(weights : EvalWeights) -> Self { Self { weights } }
+}
+impl Evaluator for WeightedEvaluator
+{
+    fn eval(&self, game : &BoardGameFixedTime, t : Team) -> Score
+    {
+        let w = &self.weights;
+        let mut score = 0 as Score;
+        for team in Team::iter()
+        {
+            let data = &game.team_data[team];
+            let mut local_score = 0 as Score;
+
+            if data.alive && w.material != 0. { local_score += data.alive_piece_value as Score * w.material; }
+            if w.mobility != 0. { local_score += game.eval_mobility(team) * w.mobility; }
+            if w.promotion_distance != 0. { local_score += game.eval_promotion_distance(team) * w.promotion_distance; }
+            if w.king_safety != 0. { local_score += game.eval_king_safety(team) * w.king_safety; }
+            if w.crown_proximity != 0. { local_score += game.eval_crown_proximity(team) * w.crown_proximity; }
+            if w.relic != 0. { local_score += data.relics.count_ones() as Score * w.relic; }
+
+            score += (local_score * local_score) * if t == team { 1. } else { -1. };
+        }
+        score
+    }
+}
+
+/// Features used by the evaluators
+impl BoardGameFixedTime
+{
+    pub fn eval_mobility(&self, team : Team) -> Score
+    {
+        if team == self.current_team { self.actions.len() as Score } else { self.actions_for_team_with_anticipation(team, true).len() as Score }
+    }
+
+    pub fn eval_promotion_distance(&self, team : Team) -> Score
+    {
+        self.iter_idx_team(team).map(|at| self.piece_positional_value(at)).sum()
+    }
+
+    pub fn eval_king_safety(&self, team : Team) -> Score
+    {
+        self.iter_idx_team(team).filter(|at| self[*at].have_flag(PieceFlags::CROWN)).map(|crown|
+        {
+            let mut nb = 0;
+            for y in -1..=1 { for x in -1..=1
+            {
+                let n = crown + at(x, y);
+                if n != crown && self.is_inside(n) && self[n].is_also_team(team) { nb += 1; }
+            }}
+            nb as Score
+        }).sum()
+    }
+
+    pub fn eval_crown_proximity(&self, team : Team) -> Score
+    {
+        let crowns : Vec<At> = Team::iter().filter(|t| *t != team && self.team_data[*t].alive)
+            .flat_map(|t| self.iter_idx_team(t).filter(move |at| self[*at].have_flag(PieceFlags::CROWN) && !self[*at].is_also_team(team))).collect();
+        if crowns.is_empty() { return 0.; }
+
+        self.iter_idx_team(team).map(|a| crowns.iter().map(|c| (a.x - c.x).abs().max((a.y - c.y).abs())).min().unwrap()).map(|d| -d as Score).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn eval_default_weights_same_as_default_evaluator()
+    {
+        let mut b = BoardGame::new_chess_custom(4, true, Relics::ZERO);
+        let mut rng = Random::new(5);
+        for _ in 0..20
+        {
+            for t in Team::iter()
+            {
+                assert_eq!(DefaultEvaluator.eval(b.current(), t), WeightedEvaluator::default().eval(b.current(), t));
+            }
+            let id = b.actions[rng.gen_range(0..b.actions.len())].id;
+            b.execute(id);
+        }
+
+        let r = b.minimax_budget(SearchBudget::depth(2), &CancellationToken::new());
+        let w = b.minimax_evaluator(SearchBudget::depth(2), &CancellationToken::new(), &WeightedEvaluator::default());
+        assert_eq!(r.score, w.score);
+        assert_eq!(r.action_id, w.action_id);
+    }
+
+    #[test]
+    fn eval_config()
+    {
+        let w = EvalWeights::from_toml("# only the material\n[eval]\nmaterial = 100\nmobility = 0 # no mobility\n\nking_safety = 2.5").unwrap();
+        assert_eq!(w, EvalWeights { material: 100., mobility: 0., king_safety: 2.5, ..___() });
+        assert_eq!(EvalWeights::from_toml(&w.to_toml()), Ok(w));
+        assert_eq!(EvalWeights::from_toml("layout = \"checker\""), Ok(EvalWeights::default()));
+
+        // in a game config
+        let c = GameConfig::chess().with_eval(w);
+        assert_eq!(GameConfig::from_toml(&c.to_toml()), Ok(c));
+
+        assert!(matches!(EvalWeights::from_toml("[eval]\nmaterial 3"), Err(GameConfigError::Toml(_))));
+        assert!(matches!(EvalWeights::from_toml("[eval]\nspeed = 3"), Err(GameConfigError::Toml(_))));
+        assert!(matches!(EvalWeights::from_toml("[eval]\nrelic = \"a lot\""), Err(GameConfigError::Toml(_))));
+        assert!(matches!(EvalWeights::from_toml("material = 3"), Err(GameConfigError::Toml(_))));
+        assert_eq!(EvalWeights::from_toml("[eval]\nrelic = nan"), Err(GameConfigError::InvalidValue("relic".to_owned(), "NaN".to_owned())));
+        assert_eq!(EvalWeights::from_toml("[eval]\nmobility = -inf"), Err(GameConfigError::InvalidValue("mobility".to_owned(), "-inf".to_owned())));
+    }
+
+    #[test]
+    fn eval_king_safety_and_crown_proximity()
+    {
+        let b = BoardGame::from_fen("4k3/8/8/8/8/8/3PPP2/4K2R w - - 0 1", Relics::ZERO).unwrap();
+        assert_eq!(b.eval_king_safety(Team::White), 3.);
+        assert_eq!(b.eval_king_safety(Team::Black), 0.);
+        // the white king is the closest to the black king
+        assert_eq!(b.eval_crown_proximity(Team::Black), -7.);
+        assert_eq!(b.eval_crown_proximity(Team::White), -(6. * 3. + 7. * 2.));
+    }
+}
@@ -0,0 +1,498 @@
+use super::*;
+
+/// Error when reading or writing a position in the FEN notation
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum FenError
+{
+    MissingField(&'static str),
+    /// More than the 6 fields
+    ExtraField(String),
+    InvalidPiece(char),
+    /// Every rank must have the same number of files
+    BoardSizeMismatch { rank : AtIntType, expected : AtIntType, got : AtIntType },
+    EmptyBoard,
+    InvalidSize(String),
+    InvalidTeam(String),
+    InvalidCastling(char),
+    InvalidEnPassant(String),
+    InvalidNumber(String),
+    InvalidRelic(char),
+    /// The piece can't be written in FEN (checkers piece, multi teams piece, multi ability piece...)
+    UnsupportedPiece(At),
+    /// Only the white and black team exist in FEN
+    UnsupportedTeam(Team),
+}
+impl Display for FenError
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult
+    {
+        match self
+        {
+            FenError::MissingField(field) => write!(f, "missing field : {}", field),
+            FenError::ExtraField(field) => write!(f, "unexpected field \"{}\" after the fullmove number", field),
+            FenError::InvalidPiece(c) => write!(f, "invalid piece '{}'", c),
+            FenError::BoardSizeMismatch { rank, expected, got } => write!(f, "rank {} have {} files instead of {}", rank + 1, got, expected),
+            FenError::EmptyBoard => write!(f, "empty board"),
+            FenError::InvalidSize(s) => write!(f, "invalid board size \"{}\"", s),
+            FenError::InvalidTeam(s) => write!(f, "invalid side to move \"{}\"", s),
+            FenError::InvalidCastling(c) => write!(f, "invalid castling right '{}'", c),
+            FenError::InvalidEnPassant(s) => write!(f, "invalid en passant tile \"{}\"", s),
+            FenError::InvalidNumber(s) => write!(f, "invalid number \"{}\"", s),
+            FenError::InvalidRelic(c) => write!(f, "invalid relic '{}'", c),
+            FenError::UnsupportedPiece(a) => { write!(f, "piece at ")?; display_at(*a, f)?; write!(f, " can't be written in FEN") },
+            FenError::UnsupportedTeam(t) => write!(f, "team {} can't be written in FEN", t),
+        }
+    }
+}
+impl std::error::Error for FenError {}
+
+/// `a1`, `h8`, `j10`...
+pub fn at_to_string(a : At) -> String { format!("{}{}", char::from_u32('a' as u32 + a.x as u32).unwrap(), a.y + 1) }
+/// `a1`, `h8`, `j10`...
+pub fn at_from_str(s : &str) -> Option<At>
+{
+    let mut it = s.chars();
+    let file = it.next()?;
+    if !file.is_ascii_lowercase() { return None; }
+    let rank : AtIntType = it.as_str().parse().ok()?;
+    if rank < 1 { return None; }
+    Some(at(file as AtIntType - 'a' as AtIntType, rank - 1))
+}
+
+impl PieceFlags
+{
+    pub fn from_fen_char(c : char) -> Option<(Team, PieceFlags)>
+    {
+        let team = if c.is_ascii_uppercase() { Team::White } else { Team::Black };
+        let flags = match c.to_ascii_lowercase()
+        {
+            'p' => Self::AB_CHESS_PAWN,
+            'n' => Self::AB_CHESS_KNIGHT,
+            'b' => Self::AB_CHESS_BISHOP,
+            'r' => Self::AB_CHESS_ROOK,
+            'q' => Self::AB_CHESS_QUEEN,
+            'k' => Self::AB_CHESS_KING.with_flag(Self::CROWN),
+            _ => return None,
+        };
+        Some((team, flags))
+    }
+
+    /// Pin flags are ignored. The crown is implicit for the king
+    pub fn fen_char(self, t : Team) -> Option<char>
+    {
+        let c = match self.ability()
+        {
+            Self::AB_CHESS_PAWN   => 'p',
+            Self::AB_CHESS_KNIGHT => 'n',
+            Self::AB_CHESS_BISHOP => 'b',
+            Self::AB_CHESS_ROOK   => 'r',
+            Self::AB_CHESS_QUEEN  => 'q',
+            Self::AB_CHESS_KING   => 'k',
+            _ => return None,
+        };
+        match t
+        {
+            Team::White => Some(c.to_ascii_uppercase()),
+            Team::Black => Some(c),
+            _ => None,
+        }
+    }
+}
+
+/// The 6 fields of a FEN string. The 4 first fields (EPD) are also accepted, the clocks are then 0 and 1
+struct FenFields<'a>
+{
+    placement : &'a str,
+    team : Team,
+    castling : &'a str,
+    en_passant : &'a str,
+    half_move_clock : Turn,
+    /// Number of action already played since the begining, from the fullmove number
+    nb_ply : Turn,
+}
+impl<'a> FenFields<'a>
+{
+    fn parse(fen : &'a str) -> Result<Self, FenError>
+    {
+        let mut it = fen.split_whitespace();
+        let placement = it.next().ok_or(FenError::MissingField("piece placement"))?;
+        let team = match it.next().ok_or(FenError::MissingField("side to move"))?
+        {
+            "w" => Team::White,
+            "b" => Team::Black,
+            other => return Err(FenError::InvalidTeam(other.to_owned())),
+        };
+        let castling = it.next().ok_or(FenError::MissingField("castling"))?;
+        let en_passant = it.next().ok_or(FenError::MissingField("en passant"))?;
+        let (half_move_clock, full_move) = match (it.next(), it.next())
+        {
+            (None, _) => (0, "1"),
+            (Some(_), None) => return Err(FenError::MissingField("fullmove number")),
+            (Some(half_move), Some(full_move)) => (Self::parse_number(half_move)?, full_move),
+        };
+        if let Some(extra) = it.next() { return Err(FenError::ExtraField(extra.to_owned())); }
+        let nb_ply = Self::parse_nb_ply(full_move, team).ok_or_else(|| FenError::InvalidNumber(full_move.to_owned()))?;
+        Ok(Self { placement, team, castling, en_passant, half_move_clock, nb_ply })
+    }
+
+    /// `None` if the number of actions don't fit in a `Turn`
+    fn parse_nb_ply(full_move : &str, team : Team) -> Option<Turn>
+    {
+        let full_move = Self::parse_number(full_move).ok()?.max(1);
+        let nb_ply = (full_move - 1).checked_mul(2)?.checked_add(if team == Team::Black { 1 } else { 0 })?;
+        nb_ply.checked_mul(2)?.checked_add(1).map(|_| nb_ply)
+    }
+
+    fn parse_number(s : &str) -> Result<Turn, FenError> { s.parse::<Turn>().ok().filter(|n| *n >= 0).ok_or_else(|| FenError::InvalidNumber(s.to_owned())) }
+
+    fn turn(&self) -> Turn { 1 + self.nb_ply }
+    fn nb_actions(&self) -> Turn { 1 + 2 * self.nb_ply }
+}
+
+impl BoardGameNotStarted
+{
+    /// The relics are given to each team, because FEN don't store them. The chess draw rules are used.
+    /// The halfmove clock and fullmove number are ignored, use `BoardGameFixedTime::from_fen()` to keep them
+    pub fn from_fen(fen : &str, relics : Relics) -> Result<Self, FenError> { Self::from_fen_with_teams_relics(fen, [relics; Team::LENGHT]) }
+    pub fn from_fen_with_teams_relics(fen : &str, relics : [Relics; Team::LENGHT]) -> Result<Self, FenError> { Self::from_fen_fields(&FenFields::parse(fen)?, relics) }
+
+    fn from_fen_fields(fen : &FenFields, relics : [Relics; Team::LENGHT]) -> Result<Self, FenError>
+    {
+        let mut ranks = vec![];
+        for (rank_idx, rank) in fen.placement.split('/').enumerate()
+        {
+            let mut tiles = vec![];
+            let mut nb_empty : usize = 0;
+            for c in rank.chars()
+            {
+                if let Some(digit) = c.to_digit(10)
+                {
+                    nb_empty = nb_empty * 10 + digit as usize;
+                    continue;
+                }
+                for _ in 0..nb_empty { tiles.push(None); }
+                nb_empty = 0;
+                tiles.push(Some(PieceFlags::from_fen_char(c).ok_or(FenError::InvalidPiece(c))?));
+            }
+            for _ in 0..nb_empty { tiles.push(None); }
+
+            if let Some(first) = ranks.first().map(|e : &Vec<_>| e.len())
+            {
+                if first != tiles.len() { return Err(FenError::BoardSizeMismatch { rank: rank_idx as AtIntType, expected: first as AtIntType, got: tiles.len() as AtIntType }); }
+            }
+            ranks.push(tiles);
+        }
+        if ranks.is_empty() || ranks[0].is_empty() { return Err(FenError::EmptyBoard); }
+
+        let size = at(ranks[0].len() as AtIntType, ranks.len() as AtIntType);
+        let mut board = Self::new_empty(size);
+
+        // The first rank in FEN is the top one
+        for (rank_idx, tiles) in ranks.into_iter().enumerate()
+        {
+            let y = size.y - 1 - rank_idx as AtIntType;
+            for (x, tile) in tiles.into_iter().enumerate()
+            {
+                if let Some((t, flags)) = tile
+                {
+                    board.piece_add_team_and_set_flags(at(x as AtIntType, y), t, flags);
+                }
+            }
+        }
+
+        for t in Team::iter()
+        {
+            board.team_data[t].relics = relics[t as usize];
+        }
+        board.draw_rules = DrawRules::CHESS;
+
+        // `BoardGameFixedTime::new()` give the hand to the next team
+        board.current_team = if fen.team == Team::White { Team::Black } else { Team::White };
+
+        board.fen_apply_moved_state(fen)?;
+        Ok(board)
+    }
+
+    fn fen_mark_as_moved(&mut self, at : At, turn : Turn, nb_actions : Turn)
+    {
+        let p = &mut self[at];
+        p.nb_time_moved = Saturating(1);
+        p.distance_travel_total = Saturating(1);
+        p.last_turn_moved = (turn - 2).max(0);
+        p.last_action_moved = (nb_actions - 3).max(0);
+    }
+
+    fn fen_king_pos(&self, t : Team) -> Option<At>
+    {
+        self.iter_idx_team(t).find(|e| self[*e].is_also_chess_king())
+    }
+
+    /// The outermost rook of the team on the rank of the king (X-FEN, so Chess960 positions work)
+    fn fen_castling_rook(&self, t : Team, king_side : bool) -> Option<At>
+    {
+        let king = self.fen_king_pos(t)?;
+        let is_rook = |a : &At| self[*a].is_also_chess_rook() && !self[*a].is_also_chess_bishop() && self[*a].is_also_team(t);
+        if king_side
+        {
+            (king.x + 1..self.size().x).rev().map(|x| king.with_x(x)).find(is_rook)
+        }else
+        {
+            (0..king.x).map(|x| king.with_x(x)).find(is_rook)
+        }
+    }
+
+    pub(crate) fn is_on_chess_pawn_start_tile(&self, t : Team, a : At) -> bool
+    {
+        let dir = self.team_direction(t);
+        (dir.y > 0 && a.y == 1) || (dir.y < 0 && a.y == self.size().y - 2) || (dir.x > 0 && a.x == 1) || (dir.x < 0 && a.x == self.size().x - 2)
+    }
+
+    /// FEN don't store which piece already moved, so it is deduced from the castling right, en passant tile and the pawn position
+    fn fen_apply_moved_state(&mut self, fen : &FenFields) -> Result<(), FenError>
+    {
+        let (turn, nb_actions) = (fen.turn(), fen.nb_actions());
+
+        let mut castling_rook = vec![];
+        if fen.castling != "-"
+        {
+            for c in fen.castling.chars()
+            {
+                let (t, king_side) = match c
+                {
+                    'K' => (Team::White, true),
+                    'Q' => (Team::White, false),
+                    'k' => (Team::Black, true),
+                    'q' => (Team::Black, false),
+                    _ => return Err(FenError::InvalidCastling(c)),
+                };
+                let rook = self.fen_castling_rook(t, king_side).ok_or(FenError::InvalidCastling(c))?;
+                if !self.is_inside(rook) || !self[rook].is_also_chess_rook() || !self[rook].is_also_team(t) { return Err(FenError::InvalidCastling(c)); }
+                castling_rook.push((t, rook));
+            }
+        }
+
+        for t in [Team::White, Team::Black]
+        {
+            if let Some(king) = self.fen_king_pos(t)
+            {
+                if !castling_rook.iter().any(|(team, _)| *team == t) { self.fen_mark_as_moved(king, turn, nb_actions); }
+            }
+
+            let rooks : Vec<At> = self.iter_idx_team(t).filter(|a| self[*a].is_also_chess_rook() && !self[*a].is_also_chess_bishop() && !castling_rook.contains(&(t, *a))).collect();
+            for rook in rooks { self.fen_mark_as_moved(rook, turn, nb_actions); }
+
+            let pawns : Vec<At> = self.iter_idx_team(t).filter(|e| self[*e].is_also_chess_pawn() && !self.is_on_chess_pawn_start_tile(t, *e)).collect();
+            for p in pawns { self.fen_mark_as_moved(p, turn, nb_actions); }
+        }
+
+        if fen.en_passant != "-"
+        {
+            let invalid = || FenError::InvalidEnPassant(fen.en_passant.to_owned());
+            let en_passant = at_from_str(fen.en_passant).filter(|e| self.is_inside(*e)).ok_or_else(invalid)?;
+            // the pawn was moved by the previous team
+            let t = if fen.team == Team::White { Team::Black } else { Team::White };
+            let dir = self.team_direction(t);
+            let pawn_pos = en_passant + dir;
+            if !self.is_inside(pawn_pos) || !self[pawn_pos].is_also_chess_pawn() || !self[pawn_pos].is_also_team(t) { return Err(invalid()); }
+
+            let p = &mut self[pawn_pos];
+            p.old_pos = en_passant - dir;
+            p.nb_time_moved = Saturating(1);
+            p.distance_travel_total = Saturating(2);
+            p.last_turn_moved = turn - 1;
+            p.last_action_moved = nb_actions - 1;
+        }
+        Ok(())
+    }
+
+    fn fen_placement(&self) -> Result<String, FenError>
+    {
+        let mut s = String::new();
+        let size = self.size();
+        for y in (0..size.y).rev()
+        {
+            let mut nb_empty = 0;
+            for x in 0..size.x
+            {
+                let a = at(x, y);
+                let p = &self[a];
+                if p.is_empty_ability() && !p.is_duck() { nb_empty += 1; continue; }
+
+                let mut teams = p.iter_team();
+                let c = match (teams.next(), teams.next())
+                {
+                    (Some(t), None) => p.flags.fen_char(t),
+                    _ => None,
+                }.ok_or(FenError::UnsupportedPiece(a))?;
+
+                if nb_empty != 0 { s.push_str(&nb_empty.to_string()); nb_empty = 0; }
+                s.push(c);
+            }
+            if nb_empty != 0 { s.push_str(&nb_empty.to_string()); }
+            if y != 0 { s.push('/'); }
+        }
+        Ok(s)
+    }
+
+    fn fen_castling(&self) -> String
+    {
+        let mut s = String::new();
+        for (t, king_side, c) in [(Team::White, true, 'K'), (Team::White, false, 'Q'), (Team::Black, true, 'k'), (Team::Black, false, 'q')]
+        {
+            let (Some(king), Some(rook)) = (self.fen_king_pos(t), self.fen_castling_rook(t, king_side)) else { continue; };
+            if self[king].nb_time_moved == Saturating(0) && self.is_inside(rook) && self[rook].is_also_chess_rook() && self[rook].is_also_team(t) && self[rook].nb_time_moved == Saturating(0)
+            {
+                s.push(c);
+            }
+        }
+        if s.is_empty() { s.push('-'); }
+        s
+    }
+
+    fn fen_team(t : Team) -> Result<char, FenError>
+    {
+        match t
+        {
+            Team::White => Ok('w'),
+            Team::Black => Ok('b'),
+            _ => Err(FenError::UnsupportedTeam(t)),
+        }
+    }
+
+    /// The side to move is the next playing team. The halfmove clock and fullmove number are always `0 1`
+    pub fn to_fen(&self) -> Result<String, FenError>
+    {
+        Ok(format!("{} {} {} - 0 1", self.fen_placement()?, Self::fen_team(self.next_playing_team())?, self.fen_castling()))
+    }
+}
+
+impl BoardGameFixedTime
+{
+    /// The relics are given to each team, because FEN don't store them
+    pub fn from_fen(fen : &str, relics : Relics) -> Result<Self, FenError> { Self::from_fen_with_teams_relics(fen, [relics; Team::LENGHT]) }
+    pub fn from_fen_with_teams_relics(fen : &str, relics : [Relics; Team::LENGHT]) -> Result<Self, FenError> { Self::from_fen_with_setup(fen, relics, |_| {}) }
+
+    /// `setup` can change the rules before the first actions are calculated
+    pub(crate) fn from_fen_with_setup(fen : &str, relics : [Relics; Team::LENGHT], setup : impl FnOnce(&mut BoardGameNotStarted)) -> Result<Self, FenError>
+    {
+        let fields = FenFields::parse(fen)?;
+        let mut data = BoardGameNotStarted::from_fen_fields(&fields, relics)?;
+        setup(&mut data);
+        let mut s = Self::new(data);
+        s.turn = fields.turn();
+        s.nb_actions = fields.nb_actions();
+        s.half_move_clock = fields.half_move_clock;
+        s.zobrist_init();
+
+        // the en passant depend of the turn
+        if !s.is_end_of_the_game()
+        {
+            s.actions.clear();
+            s.update_actions(false);
+        }
+        Ok(s)
+    }
+
+    fn fen_en_passant(&self) -> String
+    {
+        for a in self.iter_idx()
+        {
+            let p = &self[a];
+            if p.is_also_chess_pawn() && !p.is_also_team(self.current_team) && p.last_turn_moved == self.turn - 1 && p.nb_time_moved == Saturating(1) && p.distance_travel_total == Saturating(2)
+            {
+                return at_to_string(at((a.x + p.old_pos.x) / 2, (a.y + p.old_pos.y) / 2));
+            }
+        }
+        "-".to_owned()
+    }
+
+    pub fn to_fen(&self) -> Result<String, FenError>
+    {
+        let full_move = (self.turn - 1).max(0) / 2 + 1;
+        Ok(format!("{} {} {} {} {} {}", self.fen_placement()?, BoardGameNotStarted::fen_team(self.current_team)?, self.fen_castling(), self.fen_en_passant(), self.half_move_clock, full_move))
+    }
+}
+
+impl BoardGame
+{
+    pub const FEN_CHESS : &'static str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    /// The relics are given to each team, because FEN don't store them
+    pub fn from_fen(fen : &str, relics : Relics) -> Result<Self, FenError>
+    {
+        Ok(Self::new_from_fixed_time(BoardGameFixedTime::from_fen(fen, relics)?))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn assert_same_position(a : &BoardGameFixedTime, b : &BoardGameFixedTime)
+    {
+        assert_eq!(a.size(), b.size());
+        for idx in a.iter_idx()
+        {
+            assert_eq!(a[idx].flags, b[idx].flags);
+            assert_eq!(a[idx].teams_flags(), b[idx].teams_flags());
+            assert_eq!(a[idx].nb_time_moved, b[idx].nb_time_moved);
+            assert_eq!(a[idx].last_turn_moved, b[idx].last_turn_moved);
+        }
+        assert_eq!(a.current_team, b.current_team);
+        assert_eq!(a.turn, b.turn);
+        assert_eq!(a.nb_actions, b.nb_actions);
+        assert_eq!(a.half_move_clock, b.half_move_clock);
+        // the relics of the missing teams don't matter
+        for (t, data) in a.iter_team_data().filter(|(_, data)| data.is_present)
+        {
+            assert_eq!(data.relics, b.team_data[t].relics);
+            assert_eq!(data.alive, b.team_data[t].alive);
+            assert_eq!(data.alive_piece_value, b.team_data[t].alive_piece_value);
+        }
+        assert_eq!(a.actions.len(), b.actions.len());
+        assert!(a.actions.iter().all(|e| b.action_id_is_valid(e.id)));
+    }
+
+    #[test]
+    fn fen_round_trip()
+    {
+        let chess = BoardGame::new_chess();
+        let fen = chess.to_fen().unwrap();
+        assert_eq!(fen, BoardGame::FEN_CHESS);
+
+        let relics = chess.team_data[Team::White].relics;
+        let from_fen = BoardGame::from_fen(&fen, relics).unwrap();
+        assert_same_position(chess.current(), from_fen.current());
+
+        for fen in ["rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2", "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"]
+        {
+            assert_eq!(BoardGameFixedTime::from_fen(fen, Relics::ZERO).unwrap().to_fen().unwrap(), fen);
+        }
+    }
+
+    #[test]
+    fn fen_error()
+    {
+        assert_eq!(BoardGameFixedTime::from_fen("", Relics::ZERO), Err(FenError::MissingField("piece placement")));
+        assert_eq!(BoardGameFixedTime::from_fen("8/8 w - - 0 1", Relics::ZERO).unwrap().size(), at(8, 2));
+        assert_eq!(BoardGameFixedTime::from_fen("8/7 w - - 0 1", Relics::ZERO), Err(FenError::BoardSizeMismatch { rank: 1, expected: 8, got: 7 }));
+        assert_eq!(BoardGameFixedTime::from_fen("8/8/8/8/8/8/8/7z w - - 0 1", Relics::ZERO), Err(FenError::InvalidPiece('z')));
+        assert_eq!(BoardGameFixedTime::from_fen(BoardGame::FEN_CHESS.replace(" w ", " y ").as_str(), Relics::ZERO), Err(FenError::InvalidTeam("y".to_owned())));
+        assert_eq!(BoardGameFixedTime::from_fen("4k3/8/8/8/8/8/8/4K3 w K - 0 1", Relics::ZERO), Err(FenError::InvalidCastling('K')));
+        assert!(BoardGame::new_checker().to_fen().is_err());
+
+        // the fullmove number must fit in the turn counter
+        let huge = format!("{} {}", BoardGame::FEN_CHESS.rsplit_once(' ').unwrap().0, Turn::MAX);
+        assert_eq!(BoardGameFixedTime::from_fen(&huge, Relics::ZERO), Err(FenError::InvalidNumber(Turn::MAX.to_string())));
+        assert_eq!(BoardGameFixedTime::from_fen(&BoardGame::FEN_CHESS.replace(" 1", " -3"), Relics::ZERO), Err(FenError::InvalidNumber("-3".to_owned())));
+
+        // every field is needed, except the clocks of an EPD position
+        assert_eq!(BoardGameFixedTime::from_fen("8/8 w", Relics::ZERO), Err(FenError::MissingField("castling")));
+        assert_eq!(BoardGameFixedTime::from_fen("8/8 w -", Relics::ZERO), Err(FenError::MissingField("en passant")));
+        assert_eq!(BoardGameFixedTime::from_fen("8/8 w - - 0", Relics::ZERO), Err(FenError::MissingField("fullmove number")));
+        assert_eq!(BoardGameFixedTime::from_fen(&format!("{} 12", BoardGame::FEN_CHESS), Relics::ZERO), Err(FenError::ExtraField("12".to_owned())));
+        let epd = BoardGameFixedTime::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3", Relics::ZERO).unwrap();
+        assert_eq!(epd.to_fen().unwrap(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+    }
+}
@@ -0,0 +1,264 @@
+//! Fog of war : each team only see the tiles its pieces occupy or can move to.
+//!
+//! With `BoardGameNotStarted::fog_of_war`, the front ends only display the tiles visible by the viewer (`BoardGameFixedTime::fog_viewer()`).
+//! The cpu can't search the real position without cheating, so it search several positions sampled from what it see
+//! (determinization : the hidden pieces are placed on random hidden tiles), then vote for an action.
+
+use super::*;
+
+/// The tiles visible by a team
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Visibility
+{
+    size : At,
+    tiles : Vec<bool>,
+}
+impl Visibility
+{
+    /// Nothing is visible
+    pub fn new(size : At) -> Self { Self { size, tiles: vec![false; (size.x * size.y) as usize] } }
+
+    fn idx(&self, at : At) -> usize { at.x as usize * self.size.y as usize + at.y as usize }
+    fn is_inside(&self, at : At) -> bool { at.x >= 0 && at.y >= 0 && at.x < self.size.x && at.y < self.size.y }
+
+    pub fn is_visible(&self, at : At) -> bool { self.is_inside(at) && self.tiles[self.idx(at)] }
+    pub fn set_visible(&mut self, at : At)
+    {
+        if !self.is_inside(at) { return; }
+        let idx = self.idx(at);
+        self.tiles[idx] = true;
+    }
+
+    pub fn iter_visible(&self) -> impl Iterator<Item = At> + '_ { self.size.iter_area().filter(|a| self.is_visible(*a)) }
+    pub fn nb_visible(&self) -> usize { self.tiles.iter().filter(|v| **v).count() }
+}
+
+impl BoardGameNotStarted
+{
+    pub fn with_fog_of_war(mut self, fog_of_war : bool) -> Self { self.fog_of_war = fog_of_war; self }
+}
+
+/// Display a position from the point of view of a team, see `BoardGameFixedTime::display_for()`
+pub struct FogDisplay<'a>
+{
+    board : &'a BoardGameFixedTime,
+    viewer : Option<Team>,
+}
+impl Display for FogDisplay<'_> { fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult { self.board.fmt_for_viewer(f, self.viewer) }}
+
+impl BoardGameFixedTime
+{
+    /// Number of positions searched by `determinized_search()`
+    pub const DETERMINIZED_NB_SAMPLE : usize = 8;
+    /// A sample that change what the team see is sampled again, up to this number of time
+    const DETERMINIZE_NB_TRY : usize = 8;
+
+    /// The tiles of the pieces of the team, and the tiles they can move to or capture
+    pub fn visibility(&self, team : Team) -> Visibility
+    {
+        let mut visibility = Visibility::new(self.size());
+        let mut actions = Actions::new();
+        for src in self.iter_idx_team(team)
+        {
+            visibility.set_visible(src);
+            self.actions_piece_unfiltered(&mut actions, src, false);
+        }
+        for action in actions.iter()
+        {
+            visibility.set_visible(action.id.dest());
+            for unit in action.iter()
+            {
+                if let UnitAction::Capture(_, dest) = unit { visibility.set_visible(*dest); }
+            }
+        }
+        visibility
+    }
+
+    /// Use `visibility()` to test several tiles
+    pub fn is_visible_by(&self, team : Team, at : At) -> bool { self.visibility(team).is_visible(at) }
+
+    /// The team that see the board with `fog_of_war` : the current team, or `None` when everything is visible
+    pub fn fog_viewer(&self) -> Option<Team> { (self.fog_of_war && !self.is_end_of_the_game()).then_some(self.current_team) }
+
+    /// `None` display every tile
+    pub fn display_for(&self, viewer : Option<Team>) -> FogDisplay<'_> { FogDisplay { board: self, viewer } }
+
+    /// A position that look the same for the team : the pieces on the hidden tiles are moved to random hidden tiles.
+    /// The pawns are never placed on their promoting tile
+    pub fn determinize(&self, team : Team, rng : &mut Random) -> Self
+    {
+        let visibility = self.visibility(team);
+        let mut hidden : Vec<(At, Piece)> = self.iter_idx().filter(|a| !visibility.is_visible(*a) && !self[*a].teams_flags().is_none_flag()).map(|a| (a, self[a])).collect();
+        // the pieces that can be promoted first : their start tiles are enough to keep them out of the promoting tiles
+        hidden.sort_by_key(|(_, p)| !p.can_be_promoted());
+        if hidden.is_empty() || self.is_end_of_the_game() { return self.clone(); }
+
+        let mut sample = self.clone();
+        for _ in 0..Self::DETERMINIZE_NB_TRY
+        {
+            sample = self.clone();
+            for (a, _) in hidden.iter() { sample.set_empty_piece(*a); }
+
+            let mut free : Vec<At> = self.iter_idx().filter(|a| !visibility.is_visible(*a) && sample.is_empty_tile(*a)).collect();
+            for (_, piece) in hidden.iter()
+            {
+                let candidates : Vec<usize> = (0..free.len()).filter(|i| !(piece.can_be_promoted() && sample.is_on_promoting_tile(piece.teams_flags(), free[*i]))).collect();
+                let idx = if candidates.is_empty() { rng.gen_range(0..free.len()) } else { candidates[rng.gen_range(0..candidates.len())] };
+                let dest = free.swap_remove(idx);
+
+                sample.piece_add_teams_flags(dest, piece.teams_flags());
+                sample.piece_set_flags(dest, piece.flags);
+                let p = &mut sample[dest];
+                p.old_pos = dest;
+                p.last_turn_moved = piece.last_turn_moved;
+                p.last_action_moved = piece.last_action_moved;
+                p.nb_time_moved = piece.nb_time_moved;
+                p.distance_travel_total = piece.distance_travel_total;
+            }
+
+            sample.zobrist_init();
+            sample.clear_pin_current_team();
+            sample.update_actions(true);
+            if sample.visibility(team) == visibility { break; }
+        }
+        sample
+    }
+
+    /// `nb` positions that look the same for the current team
+    pub fn determinized_samples(&self, nb : usize, seed : u64) -> Vec<Self>
+    {
+        let mut rng = Random::new(seed);
+        (0..nb).map(|_| self.determinize(self.current_team, &mut rng)).collect()
+    }
+
+    /// The action chosen by the most searches among the valid actions of this position, then the best average score
+    pub fn determinized_vote(&self, results : &[MinimaxResult]) -> MinimaxResult
+    {
+        let mut stat = MinimaxResult::default();
+        // (action, number of vote, sum of the scores)
+        let mut votes : Vec<(ActionID, usize, Score)> = vec![];
+        for r in results
+        {
+            stat.update_stat(r);
+            stat.stat_nb_depth_evaluated = stat.stat_nb_depth_evaluated.max(r.stat_nb_depth_evaluated);
+
+            let Some(id) = r.action_id.filter(|id| self.action_id_is_valid(*id)) else { continue; };
+            match votes.iter_mut().find(|v| v.0 == id)
+            {
+                Some(v) => { v.1 += 1; v.2 += r.score; },
+                None => votes.push((id, 1, r.score)),
+            }
+        }
+
+        let best = votes.iter().max_by(|a, b| a.1.cmp(&b.1).then((a.2 / a.1 as Score).total_cmp(&(b.2 / b.1 as Score))));
+        MinimaxResult
+        {
+            action_id: best.map(|v| v.0).or(self.actions.first().map(|a| a.id)),
+            score: best.map_or(0., |v| v.2 / v.1 as Score),
+            ..stat
+        }
+    }
+
+    /// Search `DETERMINIZED_NB_SAMPLE` positions that look the same for the current team with `search`, and vote for the action.
+    /// The budget is shared between the searches
+    pub fn determinized_search(&self, budget : SearchBudget, cancel : &CancellationToken, seed : u64, mut search : impl FnMut(&Self, SearchBudget) -> MinimaxResult) -> MinimaxResult
+    {
+        let budget = budget.split(Self::DETERMINIZED_NB_SAMPLE);
+        let mut results = vec![];
+        for sample in self.determinized_samples(Self::DETERMINIZED_NB_SAMPLE, seed)
+        {
+            if cancel.is_cancelled() { break; }
+            results.push(search(&sample, budget));
+        }
+        self.determinized_vote(&results)
+    }
+}
+
+impl BoardGame
+{
+    /// Must be called before the first action
+    pub fn with_fog_of_war(mut self, fog_of_war : bool) -> Self
+    {
+        debug_assert!(self.played_actions().is_empty());
+        self.current_mut().fog_of_war = fog_of_war;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn fog_chess() -> BoardGame { BoardGame::new_chess_custom(2, true, Relics::ZERO).with_fog_of_war(true) }
+
+    #[test]
+    fn fog_visibility()
+    {
+        let mut b = fog_chess();
+        let white = b.visibility(Team::White);
+        assert_eq!(white.nb_visible(), 32);
+        assert!(white.is_visible(at(4, 1)) && white.is_visible(at(4, 3)) && !white.is_visible(at(4, 4)) && !white.is_visible(at(4, 6)));
+        assert_eq!(b.fog_viewer(), Some(Team::White));
+
+        for san in ["e4", "d5"] { b.execute(b.san_to_action_id(san).unwrap()); }
+        // the pawns see what they can capture
+        assert!(b.is_visible_by(Team::White, at(3, 4)));
+        assert!(b.is_visible_by(Team::Black, at(4, 3)));
+        assert!(!b.is_visible_by(Team::White, at(3, 6)));
+
+        let white = format!("{}", b.display_for(Some(Team::White)));
+        assert_eq!(white.matches('?').count(), 64 - b.visibility(Team::White).nb_visible());
+        assert!(white.contains("actions :"));
+        assert!(!format!("{}", b.display_for(Some(Team::Black))).contains("actions :"));
+        assert!(!format!("{}", b.display_for(None)).contains('?'));
+    }
+
+    #[test]
+    fn fog_determinize()
+    {
+        let mut b = fog_chess();
+        for san in ["e4", "d5", "Nf3"] { b.execute(b.san_to_action_id(san).unwrap()); }
+        let visibility = b.visibility(Team::Black);
+
+        let samples = b.determinized_samples(8, 3);
+        assert!(samples.iter().any(|s| s.zobrist != b.zobrist));
+        for s in samples.iter()
+        {
+            for a in visibility.iter_visible() { assert_eq!(s[a].flags, b[a].flags); }
+            for t in Team::iter() { assert_eq!(s.team_data[t].alive_piece_value, b.team_data[t].alive_piece_value); }
+            assert_eq!(s.iter_idx_team(Team::White).count(), 16);
+            assert!(!s.iter_idx_team(Team::White).any(|a| s[a].is_also_chess_pawn() && a.y == 7));
+            assert_eq!(s.zobrist, s.zobrist_compute());
+            assert_eq!(s.visibility(Team::Black), visibility);
+        }
+    }
+
+    #[test]
+    fn fog_determinize_few_hidden_tiles()
+    {
+        // the hidden tiles are e3 to h3, g1 and h1 : the pawn must take one of the 4 tiles of the 3rd rank left by the knights and the king
+        let b = BoardGame::from_fen("R7/R7/R6K/R7/R7/R2bnnnp/R7/R4b1k w - - 0 1", Relics::ZERO).unwrap().with_fog_of_war(true);
+        let visibility = b.visibility(Team::White);
+        assert_eq!(visibility.nb_visible(), 64 - 6);
+        for s in b.determinized_samples(64, 9)
+        {
+            let pawns : Vec<At> = s.iter_idx_team(Team::Black).filter(|a| s[*a].is_also_chess_pawn()).collect();
+            assert!(pawns.len() == 1 && pawns[0].y == 2);
+            assert_eq!(s.iter_idx_team(Team::Black).count(), 7);
+            assert_eq!(s.zobrist, s.zobrist_compute());
+            assert_eq!(s.visibility(Team::White), visibility);
+        }
+    }
+
+    #[test]
+    fn fog_determinized_search()
+    {
+        // the queen must be captured, whatever the position of the hidden king
+        let b = BoardGame::from_fen("4k3/8/8/8/8/8/3q4/3QK3 w - - 0 1", Relics::ZERO).unwrap().with_fog_of_war(true);
+        assert!(!b.is_visible_by(Team::White, at(4, 7)));
+        let cancel = CancellationToken::new();
+        let r = b.determinized_search(SearchBudget::depth(3), &cancel, 5, |s, budget| s.minimax_budget(budget, &cancel));
+        assert!(r.action_id.is_some_and(|id| b.action_id_is_valid(id) && id.dest() == at(3, 1)));
+    }
+}
@@ -0,0 +1,164 @@
+//! Legal moves mode : the check rule of chess without the `Anticipation` relic.
+//!
+//! With `BoardGameNotStarted::legal_moves`, the actions that leave a crown of the current team capturable by any other team are removed,
+//! the pinned pieces are marked, and the check status is calculated.
+
+use super::*;
+
+/// Status of the current team with `legal_moves`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CheckStatus
+{
+    #[default]
+    None,
+    /// A crown can be captured
+    Check,
+    /// A crown can be captured and there is no legal action
+    Checkmate,
+    /// No crown can be captured but there is no legal action
+    Stalemate,
+}
+impl Display for CheckStatus
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult
+    {
+        write!(f, "{}", match self
+        {
+            CheckStatus::None => "none",
+            CheckStatus::Check => "check",
+            CheckStatus::Checkmate => "checkmate",
+            CheckStatus::Stalemate => "stalemate",
+        })
+    }
+}
+
+impl BoardGameNotStarted
+{
+    pub fn with_legal_moves(mut self, legal_moves : bool) -> Self { self.legal_moves = legal_moves; self }
+}
+
+impl BoardGameFixedTime
+{
+    /// The action don't leave a crown of the current team capturable
+    pub fn legal_moves_judge_if_action_is_legal(&self, action : &Action) -> bool
+    {
+        if !action.team.is_also_team(self.current_team) { return true; }
+        !self.execute_action_without_update(action).is_crown_attacked(self.current_team)
+    }
+    pub fn legal_moves_apply_on(&self, actions : &mut Actions)
+    {
+        actions.retain(|e| self.legal_moves_judge_if_action_is_legal(e));
+    }
+
+    /// Also calculate the pin and the check status
+    pub fn legal_moves_apply(&mut self)
+    {
+        self.actions_filter_and_pin(Self::legal_moves_judge_if_action_is_legal, true);
+
+        self.check_status = match (self.is_crown_attacked(self.current_team), self.actions.is_empty())
+        {
+            (true, true) => CheckStatus::Checkmate,
+            (true, false) => CheckStatus::Check,
+            (false, true) => CheckStatus::Stalemate,
+            (false, false) => CheckStatus::None,
+        };
+    }
+
+    /// Enable or disable the legal moves mode on this position and update the actions
+    pub fn set_legal_moves(&mut self, legal_moves : bool)
+    {
+        self.legal_moves = legal_moves;
+        self.check_status = CheckStatus::None;
+        self.clear_pin_current_team();
+        if self.is_end_of_the_game() { return; }
+
+        self.update_actions(true);
+        if self.actions.is_empty() && self.current_nb_action_this_turn == 0 { self.execute_no_action_left(); }
+    }
+
+    pub fn is_check(&self) -> bool { matches!(self.check_status, CheckStatus::Check | CheckStatus::Checkmate) }
+}
+
+impl BoardGame
+{
+    /// Must be called before the first action
+    pub fn with_legal_moves(mut self, legal_moves : bool) -> Self
+    {
+        debug_assert!(self.played_actions().is_empty());
+        self.current_mut().set_legal_moves(legal_moves);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn legal_from_fen(fen : &str) -> BoardGame { BoardGame::new(BoardGameNotStarted::from_fen(fen, Relics::ZERO).unwrap().with_legal_moves(true)) }
+
+    #[test]
+    fn legal_moves_perft()
+    {
+        let b = BoardGame::new_chess_custom(2, true, Relics::ZERO).with_legal_moves(true);
+        assert_eq!(b.perft(2), 400);
+        // Kiwipete
+        assert_eq!(legal_from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").perft(1), 48);
+        assert_eq!(legal_from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").perft(3), 2812);
+    }
+
+    #[test]
+    fn legal_moves_check_status()
+    {
+        let mut b = BoardGame::new_chess_custom(2, true, Relics::ZERO).with_legal_moves(true);
+        for san in ["f3", "e5", "g4"]
+        {
+            b.execute(b.san_to_action_id(san).unwrap());
+            assert_eq!(b.check_status, CheckStatus::None);
+        }
+        b.execute(b.san_to_action_id("Qh4").unwrap());
+        assert_eq!(b.check_status, CheckStatus::Checkmate);
+        assert_eq!(b.end_game_result(), Some(BoardResult::WinnerIs(Team::Black)));
+
+        let b = legal_from_fen("4k3/8/8/8/8/8/8/r3K3 w - - 0 1");
+        assert!(b.is_check());
+        assert!(b.actions.iter().all(|a| a.id.src() == at(4, 0) && a.id.dest().y == 1));
+
+        let mut b = legal_from_fen("7k/8/6Q1/8/8/8/8/K7 w - - 0 1");
+        b.execute(b.san_to_action_id("Qf7").unwrap());
+        assert_eq!(b.check_status, CheckStatus::Stalemate);
+        assert_eq!(b.end_game_result(), Some(BoardResult::Draw));
+    }
+
+    #[test]
+    fn legal_moves_pin()
+    {
+        let b = legal_from_fen("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1");
+        assert!(b[at(4, 1)].have_flag(PieceFlags::TOTAL_PIN));
+        assert!(!b.actions.iter().any(|a| a.id.src() == at(4, 1)));
+
+        let b = legal_from_fen("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1");
+        assert!(b[at(4, 1)].have_flag(PieceFlags::PARTIAL_PIN));
+        assert!(b.actions.iter().filter(|a| a.id.src() == at(4, 1)).all(|a| a.id.dest().x == 4));
+    }
+
+    #[test]
+    fn legal_moves_edge_cases()
+    {
+        // double check by the rook and the knight : only the king can move, capturing the knight is not enough
+        let b = legal_from_fen("4r2k/8/8/8/8/R2n4/8/4K3 w - - 0 1");
+        assert!(b.is_check());
+        assert!(!b.actions.is_empty() && b.actions.iter().all(|a| a.id.src() == at(4, 0)));
+
+        // the protected queen can't be captured by the king
+        let b = legal_from_fen("4k3/8/8/8/8/2b5/3q4/4K3 w - - 0 1");
+        assert_eq!(b.actions.iter().map(|a| a.id).collect::<Vec<_>>(), vec![ActionID::Move(at(4, 0), at(5, 0))]);
+
+        // the en passant would remove the 2 pawns between the king and the rook
+        let b = legal_from_fen("4k3/8/8/K2pP2r/8/8/8/8 w - d6 0 2");
+        assert!(b.action_id_is_valid(ActionID::Move(at(4, 4), at(4, 5))));
+        assert!(!b.action_id_is_valid(ActionID::Move(at(4, 4), at(3, 5))));
+        let b = legal_from_fen("4k3/8/8/3pP2r/8/8/8/K7 w - d6 0 2");
+        assert!(b.action_id_is_valid(ActionID::Move(at(4, 4), at(3, 5))));
+    }
+}
@@ -3,7 +3,7 @@
 #![allow(unused_mut)]
 
 use std::{default, fmt::{format, write, Debug, Display, Formatter, Result as DisplayResult}, mem, num::{NonZeroU8, Saturating, Wrapping}, ops::*, time::{Duration, Instant}};
-use std::{marker::PhantomData, ops::{Index, IndexMut}};
+use std::{marker::PhantomData, ops::{Index, IndexMut}, sync::Arc};
 
 use math::*;
 use text_extension::console_color::*;
@@ -11,6 +11,71 @@ use util::*;
 
 use smallvec::{SmallVec,smallvec};
 
+pub mod fen;
+pub use fen::*;
+
+pub mod chessito_fen;
+pub use chessito_fen::*;
+
+pub mod san;
+pub use san::*;
+
+pub mod pgn;
+pub use pgn::*;
+
+pub mod draw;
+pub use draw::*;
+
+pub mod legal;
+pub use legal::*;
+
+pub mod config;
+pub use config::*;
+
+pub mod zobrist;
+pub use zobrist::*;
+
+pub mod transposition;
+pub use transposition::*;
+
+pub mod search;
+pub use search::*;
+
+pub mod eval;
+pub use eval::*;
+
+pub mod multiplayer;
+pub use multiplayer::*;
+
+pub mod mcts;
+pub use mcts::*;
+
+pub mod book;
+pub use book::*;
+
+pub mod tablebase;
+pub use tablebase::*;
+
+pub mod perft;
+pub use perft::*;
+
+pub mod duck;
+pub use duck::*;
+
+pub mod betza;
+pub use betza::*;
+
+pub mod fog;
+pub use fog::*;
+
+pub mod back_rank;
+pub use back_rank::*;
+
+#[cfg(feature = "parallel")]
+pub mod parallel;
+#[cfg(feature = "parallel")]
+pub use parallel::*;
+
 macro_rules! custom_assert {
     ($condition:expr) => {
         debug_assert!($condition);
@@ -25,7 +90,7 @@ pub enum BoardResult
     Draw,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 #[repr(u8)]
 pub enum Team
 {
@@ -51,6 +116,18 @@ impl Team
     }
 
     pub fn from_usize(val : usize) -> Self { Self::ALL[val] }
+
+    /// Where the pawns move by default
+    pub fn default_direction(self) -> At
+    {
+        match self
+        {
+            Team::White    =>  At::Y,
+            Team::Black     => -At::Y,
+            Team::Yellow  =>  At::X,
+            Team::Green   => -At::X,
+        }
+    }
 }
 impl Display for Team { fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult { write!(f, "{:?}", self) }}
 
@@ -118,13 +195,15 @@ impl BitXorAssign for TeamsFlags { fn bitxor_assign(&mut self, rhs: Self)  { *se
 
 
 pub type PieceFlagsType = u16;
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
 pub struct PieceFlags(pub PieceFlagsType);
 ///! Current can hold 8 bit info, no more because of u8
 impl PieceFlags
 {
-    /// Move Ability
-    pub const ABILITY_COUNT      : PieceFlagsType = 7;
+    /// Move Ability. The movements are in `PieceMoves`
+    pub const ABILITY_COUNT      : PieceFlagsType = 12;
+    /// The chess and checkers abilities, before the custom ones
+    pub const STANDARD_ABILITY_COUNT : PieceFlagsType = 7;
     pub const ABILITY_OFFSET     : PieceFlagsType = 0; // use the previous ???_OFFSET 
     pub const ABILITY_MASK       : PieceFlags = Self(((1 << (Self::ABILITY_COUNT))-1) << Self::ABILITY_OFFSET);
 
@@ -136,6 +215,14 @@ impl PieceFlags
     pub const AB_DAME_KING       : PieceFlags = Self(0b100000 << Self::ABILITY_OFFSET);
     pub const AB_DAME_PAWN       : PieceFlags = Self(0b1000000 << Self::ABILITY_OFFSET);
 
+    /// Abilities defined by each game, see `BoardGameNotStarted::custom_abilities`
+    pub const AB_CUSTOM          : [PieceFlags; 5] =
+    [
+        Self(0b10000000 << Self::ABILITY_OFFSET), Self(0b100000000 << Self::ABILITY_OFFSET), Self(0b1000000000 << Self::ABILITY_OFFSET),
+        Self(0b10000000000 << Self::ABILITY_OFFSET), Self(0b100000000000 << Self::ABILITY_OFFSET),
+    ];
+    pub const AB_CUSTOM_MASK     : PieceFlags = Self(0b111110000000 << Self::ABILITY_OFFSET);
+
     pub const AB_CHESS_QUEEN           : PieceFlags = Self(Self::AB_CHESS_BISHOP.0 | Self::AB_CHESS_ROOK.0);
 
     pub fn ability(self) -> Self { self & Self::ABILITY_MASK }
@@ -174,7 +261,8 @@ impl PieceFlags
 
         if self.have_flag(Self::AB_DAME_PAWN  ) { val += 1; }
         if self.have_flag(Self::AB_DAME_KING  ) { val += 3; }
-
+        // a custom ability is valued like a knight
+        val += 3 * (self & Self::AB_CUSTOM_MASK).0.count_ones() as PieceValue;
 
         let nb_ability = self.ability().0.count_ones() as PieceValue;
         // +1 bonus for each extra ability. So value(QUEEN) = value(ROOK) + value(FOOL) + 1
@@ -182,12 +270,27 @@ impl PieceFlags
         val
     }
 
-    pub fn promote(self, have_relic_kirby : bool) -> Self
+    pub const CHESS_PROMOTION : [PieceFlags; 4] = [Self::AB_CHESS_QUEEN, Self::AB_CHESS_KNIGHT, Self::AB_CHESS_ROOK, Self::AB_CHESS_BISHOP];
+
+    /// The abilities the piece can choose when promoted, the best one first. `chess_promotion` is used by the chess pawns
+    pub fn promotion_options(self, chess_promotion : &[PieceFlags]) -> SmallVec<[PieceFlags; 4]>
     {
-        let mut promotion = Self::ZERO;
-        if self.is_also_chess_pawn() { promotion |= Self::AB_CHESS_QUEEN; }
-        if self.is_also_dame_pawn()  { promotion |= Self::AB_DAME_KING; }
+        let dame = if self.is_also_dame_pawn() { Self::AB_DAME_KING } else { Self::ZERO };
+        if self.is_also_chess_pawn() 
+        { 
+            chess_promotion.iter().map(|p| *p | dame).collect()
+        }else if self.is_also_dame_pawn()
+        {
+            smallvec![dame]
+        }else
+        {
+            smallvec![]
+        }
+    }
 
+    /// `promotion` is the ability choosen in `promotion_options`
+    pub fn promote(self, promotion : PieceFlags, have_relic_kirby : bool) -> Self
+    {
         if have_relic_kirby
         {
             self | promotion
@@ -211,7 +314,7 @@ impl PieceFlags
 
 
     // Flags
-    pub const FLAGS_COUNT    : PieceFlagsType = 3;
+    pub const FLAGS_COUNT    : PieceFlagsType = 4;
     pub const FLAGS_OFFSET   : PieceFlagsType = Self::ABILITY_OFFSET + Self::ABILITY_COUNT; // use the previous ???_OFFSET 
     pub const FLAGS_MASK     : PieceFlagsType = ((1 << (Self::FLAGS_COUNT))-1) << Self::FLAGS_OFFSET;
 
@@ -219,6 +322,10 @@ impl PieceFlags
     pub const CROWN          : PieceFlags = Self(0b1 << Self::FLAGS_OFFSET);
     pub const PARTIAL_PIN    : PieceFlags = Self(0b10 << Self::FLAGS_OFFSET);
     pub const TOTAL_PIN      : PieceFlags = Self(0b100 << Self::FLAGS_OFFSET);
+    /// Neutral blocker of the `DuckButDifferent` relic : belong to no team and can't be captured
+    pub const DUCK           : PieceFlags = Self(0b1000 << Self::FLAGS_OFFSET);
+
+    pub fn is_duck(self) -> bool { self.have_flag(Self::DUCK) }
 
 
     /// Bit Flags related
@@ -258,6 +365,11 @@ impl PieceFlags
         if self.is_exactly_flag(Self::AB_CHESS_KNIGHT) { c = 'N'; }
         if self.is_exactly_flag(Self::AB_DAME_PAWN) { c = 'x'; }
         if self.is_exactly_flag(Self::AB_DAME_KING) { c = 'X'; }
+        for (idx, custom) in Self::AB_CUSTOM.iter().enumerate()
+        {
+            if self.ability() == *custom { c = char::from_digit(idx as u32 + 1, 10).unwrap(); }
+        }
+        if self.is_duck() { c = '@'; }
 
         c
     }
@@ -348,15 +460,36 @@ pub enum ActionID
 {
     /// Move(src, dest)
     Move(At,At),
+    /// Promote(src, dest, promotion) : move then promote with the `promotion` ability
+    Promote(At,At,PieceFlags),
+    /// Duck(dest) : place the duck of the `DuckButDifferent` relic
+    Duck(At),
     // potion...
 }
 impl ActionID
 {
-    pub fn is_move_src(self, src : At) -> bool
+    pub fn is_move_src(self, src : At) -> bool { self.src() == src }
+
+    pub fn src(self) -> At
+    {
+        match self
+        {
+            ActionID::Move(src, _) | ActionID::Promote(src, _, _) | ActionID::Duck(src) => src,
+        }
+    }
+    pub fn dest(self) -> At
+    {
+        match self
+        {
+            ActionID::Move(_, dest) | ActionID::Promote(_, dest, _) | ActionID::Duck(dest) => dest,
+        }
+    }
+    pub fn promotion(self) -> Option<PieceFlags>
     {
         match self
         {
-            ActionID::Move(move_src, _) => move_src == src,
+            ActionID::Move(_, _) | ActionID::Duck(_) => None,
+            ActionID::Promote(_, _, promotion) => Some(promotion),
         }
     }
 }
@@ -367,6 +500,8 @@ impl Display for ActionID
         match self.clone()
         {
             ActionID::Move(src, dest) => { write!(f, "move ")?; display_at(src, f)?; write!(f, " to ")?; display_at(dest, f)?; },
+            ActionID::Promote(src, dest, promotion) => { write!(f, "move ")?; display_at(src, f)?; write!(f, " to ")?; display_at(dest, f)?; write!(f, " and promote to {}", promotion)?; },
+            ActionID::Duck(dest) => { write!(f, "place the duck on ")?; display_at(dest, f)?; },
         }
         Ok(())
     }
@@ -391,13 +526,15 @@ impl Action
 { 
     pub fn new(id : ActionID, team : TeamsFlags) -> Self { Self { id, all: smallvec![], team, score : 0. }}
     pub fn into_inter(self) -> impl Iterator<Item = UnitAction> { self.all.into_iter() }
+    /// Capture (including the explosions) or promotion, searched by the quiescence search
+    pub fn is_capture_or_promote(&self) -> bool { self.iter().any(|e| e.is_capture() || e.is_promote()) }
 
     pub fn push(&mut self, game : &BoardGameFixedTime, unit_action : UnitAction) 
     {
         match unit_action
         {
             UnitAction::Capture(_, at) => { self.score += game[at].ai_value() as Score * 10.; },
-            UnitAction::Promote(_) => { self.score += PieceFlags::AB_CHESS_QUEEN.ai_value() as Score * 10.; },
+            UnitAction::Promote(_, promotion) => { self.score += promotion.ai_value() as Score * 10.; },
             UnitAction::EnergyAdd(how_many) => { self.score += how_many as Score; },
             //UnitAction::Swap(a, b) => { self.score += (a - b).length_manhattan() as Score * 0.1; },
             _ => {}
@@ -455,13 +592,17 @@ pub enum UnitAction
     Capture(Option<At>, At),
     Swap(At, At),
     EnergyAdd(Energy),
-    Promote(At),
+    Promote(At, PieceFlags),
+    /// DuckPlace(old, dest) : move the duck, or add it if it is not on the board
+    DuckPlace(Option<At>, At),
 }
 impl UnitAction
 {
     pub fn is_capture(&self) -> bool { matches!(self, UnitAction::Capture(_,_)) }
     pub fn is_swap   (&self) -> bool { matches!(self, UnitAction::Swap(_,_)) }
     pub fn is_energy (&self) -> bool { matches!(self, UnitAction::EnergyAdd(_)) }
+    pub fn is_promote(&self) -> bool { matches!(self, UnitAction::Promote(_,_)) }
+    pub fn is_duck   (&self) -> bool { matches!(self, UnitAction::DuckPlace(_,_)) }
 }
 
 #[derive(Clone, PartialEq, Eq, Default)]
@@ -490,6 +631,12 @@ pub enum Relic
     Absorb,
     MoveTwiceInATurn,
 }
+impl Relic
+{
+    pub const LENGHT : usize = 5;
+    pub const ALL : [Relic; Self::LENGHT] = [Relic::Anticipation, Relic::Explosive, Relic::DuckButDifferent, Relic::Absorb, Relic::MoveTwiceInATurn];
+    pub fn iter() -> impl Iterator<Item = Relic> { Self::ALL.iter().copied() }
+}
 impl From<Relic> for Relics
 {
     fn from(value: Relic) -> Self {
@@ -514,6 +661,11 @@ pub struct TeamData
     pub piece_pos : SmallVec<[TeamPieceInfo; 16]>,
 
     pub relics : Relics,
+    /// Where the pawns move, and where they are promoted
+    pub direction : At,
+    /// First tile and number of tiles of the back rank along the rank, for the castling.
+    /// `None` for all the tiles of the rank of the king
+    pub back_rank : Option<(AtIntType, AtIntType)>,
 }
 
 
@@ -529,11 +681,15 @@ pub struct ActionAndResult
 }
 
 
-#[derive(Clone, PartialEq, Debug, Default)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct TeamsData
 {
     val : [TeamData; Team::LENGHT],
 }
+impl Default for TeamsData
+{
+    fn default() -> Self { Self { val: std::array::from_fn(|idx| TeamData { direction: Team::from_usize(idx).default_direction(), ..___() }) } }
+}
 impl Index<Team> for TeamsData { type Output=TeamData; fn index(&self, index: Team) -> &Self::Output { &self.val[index as u8 as usize] }}
 impl IndexMut<Team> for TeamsData { fn index_mut(&mut self, index: Team) -> &mut Self::Output { &mut self.val[index as u8 as usize] }}
 
@@ -571,6 +727,8 @@ pub struct BoardGrid
 {
     tiles : Vec<Piece>,
     size : At,
+    /// Empty if all the tiles exist. The void tiles are outside the board : nothing can be on them or go through them
+    void_tiles : Arc<Vec<bool>>,
 }
 impl Index<At> for BoardGrid 
 { type Output=Piece; fn index(&self, index: At) -> &Self::Output { self.get(index) }}
@@ -607,7 +765,8 @@ impl BoardGrid
                     v.push(___());
                 }
                 v
-            }
+            },
+            void_tiles: ___(),
         };
         for at in s.iter_idx()
         {
@@ -625,18 +784,41 @@ impl BoardGrid
     #[inline] pub fn is_inside_x(&self, x : AtIntType) -> bool { x >= 0 && x < self.size.x }
     #[inline] pub fn is_inside_y(&self, y : AtIntType) -> bool { y >= 0 && y < self.size.y }
 
-    pub fn is_inside(&self, idx : At) -> bool { self.is_inside_x(idx.x) && self.is_inside_y(idx.y) }
+    /// Inside the rectangle of the grid and not a void tile
+    pub fn is_inside(&self, idx : At) -> bool { self.is_inside_grid(idx) && (self.void_tiles.is_empty() || !self.void_tiles[self.coordinate_to_idx(idx)]) }
+    /// Inside the rectangle of the grid, the void tiles included
+    pub fn is_inside_grid(&self, idx : At) -> bool { self.is_inside_x(idx.x) && self.is_inside_y(idx.y) }
+
+    pub fn is_void(&self, idx : At) -> bool { self.is_inside_grid(idx) && !self.is_inside(idx) }
+    pub fn have_void(&self) -> bool { self.void_tiles.iter().any(|v| *v) }
+    /// The tile must be empty
+    pub fn set_void(&mut self, idx : At, void : bool)
+    {
+        if self.void_tiles.is_empty() { self.void_tiles = Arc::new(vec![false; self.tiles.len()]); }
+        let i = self.coordinate_to_idx(idx);
+        Arc::make_mut(&mut self.void_tiles)[i] = void;
+    }
+    /// Remove a square of `corner_size` tiles in each corner, like the board of the 4 players chess
+    pub fn set_void_corners(&mut self, corner_size : AtIntType)
+    {
+        for idx in self.iter_idx()
+        {
+            let near = |v : AtIntType, size : AtIntType| v < corner_size || v >= size - corner_size;
+            if near(idx.x, self.size.x) && near(idx.y, self.size.y) { self.set_void(idx, true); }
+        }
+    }
 
     fn coordinate_to_idx(&self, p : At) -> usize { p.x as usize *self.size.y as usize +p.y as usize }
 
+    /// The void tiles can be read, they are always empty
     pub fn try_get(&self, pos : At) -> Option<&Piece> 
     { 
         let p = pos.into();
-        if self.is_inside(p) { Some(&self.tiles[self.coordinate_to_idx(p)]) } else { None }
+        if self.is_inside_grid(p) { Some(&self.tiles[self.coordinate_to_idx(p)]) } else { None }
     }
     pub fn try_get_mut(&mut self, pos : At) -> Option<&mut Piece> 
     {
-        if self.is_inside(pos) { Some(&mut self.tiles[(pos.x*self.size.y+pos.y) as usize]) } else { None }
+        if self.is_inside_grid(pos) { Some(&mut self.tiles[(pos.x*self.size.y+pos.y) as usize]) } else { None }
     }
 
     /// panics if outside the grid
@@ -653,7 +835,7 @@ impl BoardGrid
 
     /// panics if outside the grid
     pub fn set(&mut self, val : Piece, p : At) { self[p] = val; }
-    pub fn try_set(&mut self, val : Piece, p : At) -> bool { let idx = p.into(); if self.is_inside(idx) { self[idx] = val; true } else { false } }
+    pub fn try_set(&mut self, val : Piece, p : At) -> bool { let idx = p.into(); if self.is_inside_grid(idx) { self[idx] = val; true } else { false } }
 }
 
 
@@ -667,6 +849,21 @@ pub struct BoardGameNotStarted
 
     pub nb_team_alive : u8,
     pub is_draw   : bool,
+    pub draw_rules : DrawRules,
+    /// Forbid the actions that leave a crown capturable, like the check rule of chess
+    pub legal_moves : bool,
+    /// Each team only see the tiles its pieces occupy or can move to
+    pub fog_of_war : bool,
+    /// Seed of a random back rank (`BackRank`), to replay the layout
+    pub layout_seed : Option<u64>,
+    /// Number of actions in a turn, without the `MoveTwiceInATurn` relic
+    pub energy_per_turn : Energy,
+    /// The abilities a chess pawn can choose when promoted, the best one first
+    pub chess_promotion : SmallVec<[PieceFlags; 4]>,
+    /// The current team must place the duck before the end of its action (`DuckButDifferent` relic)
+    pub duck_to_place : bool,
+    /// Movements of the `PieceFlags::AB_CUSTOM` abilities, in this order
+    pub custom_abilities : CustomAbilities,
 
     /// Score for the current team
     // current_team_score : Score,
@@ -675,6 +872,9 @@ pub struct BoardGameNotStarted
     /// when energy reach zero, change the team turn
     pub current_nb_energy : Energy,
     pub current_nb_action_this_turn : Turn,
+
+    /// Only up to date inside a `BoardGameFixedTime`
+    pub zobrist : ZobristHash,
 }
 impl Deref for BoardGameNotStarted { type Target=BoardGrid; fn deref(&self) -> &Self::Target { &self.board }}
 impl DerefMut for BoardGameNotStarted { fn deref_mut(&mut self) -> &mut Self::Target { &mut self.board }}
@@ -695,7 +895,16 @@ impl Default for BoardGameNotStarted
             team_data: ___(),
             nb_team_alive: 0,
             is_draw: false,
+            draw_rules: DrawRules::NONE,
+            legal_moves: false,
+            fog_of_war: false,
+            layout_seed: None,
+            energy_per_turn: 1,
+            chess_promotion: SmallVec::from_slice(&PieceFlags::CHESS_PROMOTION),
+            duck_to_place: false,
+            custom_abilities: ___(),
             captured: ___(),
+            zobrist: 0,
         }
     }
 }
@@ -703,18 +912,32 @@ impl BoardGameNotStarted
 {
     pub fn new_empty(size : At) -> Self { Self { board: BoardGrid::new_empty(size), ..___() } }
 
-    pub fn current_team_direction(&self) -> At { self.team_direction(self.current_team) }
-    pub fn team_direction(&self, t : Team) -> At
+    pub fn next_playing_team(&self) -> Team { self.next_playing_team_after(self.current_team) }
+    pub fn next_playing_team_after(&self, team : Team) -> Team
     {
-        match t
+        // Todo, handle it in a different way can be better
+        let mut futur_team = team;
+        let mut nb_iter = 0;
+        loop
         {
-            Team::White    =>  At::Y,
-            Team::Black     => -At::Y,
-            Team::Yellow  =>  At::X,
-            Team::Green   => -At::X,
+            futur_team = match futur_team
+            {
+                Team::White   => Team::Black,
+                Team::Black    => Team::Yellow,
+                Team::Yellow => Team::Green,
+                Team::Green  => Team::White,
+            };
+
+            if self.team_data[futur_team].alive { break; }
+            nb_iter += 1;
+            if nb_iter > Team::LENGHT { break; }
         }
+        futur_team
     }
 
+    pub fn current_team_direction(&self) -> At { self.team_direction(self.current_team) }
+    pub fn team_direction(&self, t : Team) -> At { self.team_data[t].direction }
+
 
     fn integrity_is_ok(&self) -> bool
     {
@@ -780,9 +1003,16 @@ pub struct BoardGameFixedTime
     pub turn : Turn,
     /// Number of actions
     pub nb_actions : Turn,
+    /// Number of actions since the last capture or pawn move (halfmove clock of the FEN notation)
+    pub half_move_clock : Turn,
 
     //pub actions_and_result : Vec<ActionAndResult>,
     pub actions : Actions,
+    /// Only calculated with `legal_moves`
+    pub check_status : CheckStatus,
+
+    /// Part of `zobrist` that is not about the tiles
+    zobrist_state : ZobristHash,
 }
 impl Deref for BoardGameFixedTime { type Target=BoardGameNotStarted; fn deref(&self) -> &Self::Target { &self.data } }
 impl DerefMut for BoardGameFixedTime { fn deref_mut(&mut self) -> &mut Self::Target { &mut self.data } }
@@ -831,13 +1061,34 @@ pub struct MinimaxResult
 
     pub stat_nb_action_evaluated : usize,
     pub stat_nb_depth_evaluated  : usize,
+
+    /// Number of position looked in the transposition table
+    pub stat_nb_tt_probe : usize,
+    /// Number of position found in the transposition table
+    pub stat_nb_tt_hit : usize,
+    /// Number of position where the transposition table score was used instead of searching
+    pub stat_nb_tt_cut : usize,
+
+    /// Algorithm used for more than 2 teams
+    pub multiplayer : MultiplayerSearch,
+    /// Score of each team with max-n
+    pub team_scores : TeamScores,
+    /// The action come from the opening book, without search
+    pub from_book : bool,
+    /// The action come from the endgame tablebase, without search
+    pub from_tablebase : bool,
 }
 impl MinimaxResult
 {
     pub fn update_stat(&mut self, sub : &Self)
     {
         self.stat_nb_action_evaluated += sub.stat_nb_action_evaluated;
+        self.stat_nb_tt_probe += sub.stat_nb_tt_probe;
+        self.stat_nb_tt_hit += sub.stat_nb_tt_hit;
+        self.stat_nb_tt_cut += sub.stat_nb_tt_cut;
     }
+
+    pub fn tt_hit_rate(&self) -> f64 { if self.stat_nb_tt_probe == 0 { 0. } else { self.stat_nb_tt_hit as f64 / self.stat_nb_tt_probe as f64 } }
 }
 
 impl BoardGameFixedTime 
@@ -847,14 +1098,15 @@ impl BoardGameFixedTime
         -self.distance_to_promoting_tile(at) as Score
     }
 
-    fn eval_team_position(&self, t : Team, current_depth : usize, maxi_depth : usize) -> MinimaxResult 
+    fn eval_team_position<E : Evaluator>(&self, t : Team, current_depth : usize, maxi_depth : usize, evaluator : &E) -> MinimaxResult 
     { 
-        MinimaxResult { score: self._eval_team_position(t, current_depth, maxi_depth), stat_nb_action_evaluated : 1, ..___() }
+        MinimaxResult { score: self._eval_team_position(t, current_depth, maxi_depth, evaluator), stat_nb_action_evaluated : 1, ..___() }
     }
 
-    fn _eval_team_position(&self, t : Team, current_depth : usize, maxi_depth : usize) -> Score 
+    fn _eval_team_position<E : Evaluator>(&self, t : Team, current_depth : usize, maxi_depth : usize, evaluator : &E) -> Score 
     {
         let rev_depth = (maxi_depth + 1) - current_depth ;
+        if self.is_draw { return 0.; }
         if !self.team_data[t].alive 
         {
             // The later your defeat arrive, the better it is
@@ -871,41 +1123,7 @@ impl BoardGameFixedTime
                 return rev_depth as Score * 10E64;
             };
         }
-
-        let mut score = 0 as Score; //self.team_data[t].alive_piece_value as Score * 2.;
-        for team in Team::iter()
-        {
-            // positive
-
-            let piece_score = 
-            if self.team_data[team].alive 
-            { 
-                self.team_data[team].alive_piece_value as Score 
-                //* if team == t { 2. } else { 1.0 }
-            } 
-            else 
-            {
-                0. as Score 
-            };
-
-            let nb_action_score = if team == self.current_team
-            {
-                self.actions.len() as Score
-            }else
-            {
-                let nb_action = self.actions_for_team_with_anticipation(team, true).len();
-                nb_action as Score
-            };
-
-            let piece_positionnal_score : Score = self.team_data[team].piece_pos
-                .iter().map(|e| self.piece_positional_value(e.pos)).sum();
-            
-            let local_score = piece_score * 128. + piece_positionnal_score * 4. + nb_action_score;
-
-            // squared for multiplayer in order to minimize each player score equaly
-            score += (local_score * local_score) * if t == team { 1. } else { -1. };
-        }
-        score
+        evaluator.eval(self, t)
     }
 
     /// maybe can do somethings to avoid allocating a new actions vector each time ?
@@ -913,15 +1131,50 @@ impl BoardGameFixedTime
     {
         self.actions.iter().map(move |e| (e.id, self.execute_action(&e, apply_anticipation)))
     }
+    /// Same as `iter_next_state`, but start with the action `first` if it exist
+    pub fn iter_next_state_first(&self, apply_anticipation : bool, first : Option<ActionID>) -> impl Iterator<Item = (ActionID, Self)> + '_
+    {
+        let first_idx = first.and_then(|id| self.actions.iter().position(|e| e.id == id));
+        first_idx.into_iter().chain((0..self.actions.len()).filter(move |i| Some(*i) != first_idx))
+            .map(move |i| (self.actions[i].id, self.execute_action(&self.actions[i], apply_anticipation)))
+    }
 
     // Thank to https://www.youtube.com/watch?v=zp3VMe0Jpf8 for the tutorial
-    fn _minimax(&self, t : Team, mut current_depth : usize, maxi_depth : usize, mut alpha : Score, mut beta : Score) -> MinimaxResult
+    /// The result is meaningless if the search was stopped
+    fn _minimax<E : Evaluator>(&self, t : Team, mut current_depth : usize, maxi_depth : usize, mut alpha : Score, mut beta : Score, search : &mut MinimaxSearch<E>) -> MinimaxResult
     {
         current_depth += 1;
-        if current_depth > maxi_depth || self.is_end_of_the_game() { return self.eval_team_position(t, current_depth, maxi_depth); }
+        // the evaluation also count the plies of the quiescence search
+        let eval_maxi_depth = maxi_depth + search.budget.quiescence_depth;
+        if self.is_end_of_the_game() { return self.eval_team_position(t, current_depth, eval_maxi_depth, search.evaluator); }
+        if current_depth > maxi_depth { return self._quiescence(t, current_depth, eval_maxi_depth, alpha, beta, search); }
 
         let mut best = MinimaxResult::default();
         best.action_id = Some(self.actions[0].id);
+        if search.visit_node() { return best; }
+
+        // the score of a position only depend on the remaining depth
+        let depth = maxi_depth + 1 - current_depth;
+        let (alpha_start, beta_start) = (alpha, beta);
+        let mut first = None;
+
+        if let Some(tt) = search.tt.as_deref()
+        {
+            best.stat_nb_tt_probe = 1;
+            if let Some(e) = tt.get(self.zobrist, t)
+            {
+                best.stat_nb_tt_hit = 1;
+                first = e.action_id;
+                // the root need to search to give an action
+                if current_depth > 1 && e.is_usable(depth, alpha, beta)
+                {
+                    best.stat_nb_tt_cut = 1;
+                    best.score = e.score;
+                    best.action_id = e.action_id.or(best.action_id);
+                    return best;
+                }
+            }
+        }
 
         let apply_anticipation = false;
 
@@ -929,9 +1182,9 @@ impl BoardGameFixedTime
         {
             // maximize player score
             best.score = Score::MIN;
-            for (action_id, next) in self.iter_next_state(apply_anticipation)
+            for (action_id, next) in self.iter_next_state_first(apply_anticipation, first)
             {
-                let cur = next._minimax(t, current_depth, maxi_depth, alpha, beta);
+                let cur = next._minimax(t, current_depth, maxi_depth, alpha, beta, search);
                 best.update_stat(&cur);
 
                 if cur.score >  best.score { best.score = cur.score; best.action_id = Some(action_id); }
@@ -939,8 +1192,7 @@ impl BoardGameFixedTime
                 if cur.score >= beta
                 { 
                     best.action_id = Some(action_id);
-                    debug_assert!(best.action_id.is_some());
-                    return best;
+                    break;
                 }
                 if cur.score >  alpha { alpha = cur.score; if next.is_end_of_the_game() { break; } }
             }
@@ -949,9 +1201,9 @@ impl BoardGameFixedTime
         {
             // minimize the player score
             best.score = Score::MAX;
-            for (action_id, next) in self.iter_next_state(apply_anticipation)
+            for (action_id, next) in self.iter_next_state_first(apply_anticipation, first)
             {
-                let cur = next._minimax(t, current_depth, maxi_depth, alpha, beta);
+                let cur = next._minimax(t, current_depth, maxi_depth, alpha, beta, search);
                 best.update_stat(&cur);
 
                 if cur.score < best.score { best.score = cur.score; best.action_id = Some(action_id); }
@@ -960,15 +1212,60 @@ impl BoardGameFixedTime
                 if cur.score <= alpha 
                 { 
                     best.action_id = Some(action_id);
-                    debug_assert!(best.action_id.is_some());
-                    return best;
+                    break;
                 }
                 if cur.score <  beta { beta = cur.score; if next.is_end_of_the_game() { break; } }
             }
         }
 
+        if search.is_stopped() { return best; }
+
+        if let Some(tt) = search.tt.as_deref_mut()
+        {
+            let bound = if best.score <= alpha_start { TranspositionBound::Upper } else if best.score >= beta_start { TranspositionBound::Lower } else { TranspositionBound::Exact };
+            tt.insert(TranspositionEntry { key: self.zobrist, team: t, depth, bound, score: best.score, action_id: best.action_id });
+        }
         debug_assert!(best.action_id.is_some());
-        return best;
+        best
+    }
+
+    /// Only search the captures and the promotions after the nominal depth, until the position is quiet.
+    /// Each team can also stop (stand pat) if it is better than all its captures
+    fn _quiescence<E : Evaluator>(&self, t : Team, current_depth : usize, eval_maxi_depth : usize, mut alpha : Score, mut beta : Score, search : &mut MinimaxSearch<E>) -> MinimaxResult
+    {
+        let mut best = self.eval_team_position(t, current_depth, eval_maxi_depth, search.evaluator);
+        if current_depth > eval_maxi_depth || self.is_end_of_the_game() || search.visit_node() { return best; }
+
+        let maximize = self.current_team == t;
+        if maximize
+        {
+            if best.score >= beta { return best; }
+            alpha = alpha.max(best.score);
+        }else
+        {
+            if best.score <= alpha { return best; }
+            beta = beta.min(best.score);
+        }
+
+        for action in self.actions.iter().filter(|a| a.is_capture_or_promote())
+        {
+            let next = self.execute_action(action, false);
+            let cur = next._quiescence(t, current_depth + 1, eval_maxi_depth, alpha, beta, search);
+            best.update_stat(&cur);
+
+            if maximize
+            {
+                if cur.score > best.score { best.score = cur.score; best.action_id = Some(action.id); }
+                if cur.score >= beta { break; }
+                alpha = alpha.max(cur.score);
+            }else
+            {
+                if cur.score < best.score { best.score = cur.score; best.action_id = Some(action.id); }
+                if cur.score <= alpha { break; }
+                beta = beta.min(cur.score);
+            }
+        }
+        best
     }
 
     pub fn ai_best_move(&self) -> ActionID { self.ai_minimax_default().action_id.unwrap() }
@@ -983,23 +1280,20 @@ impl BoardGameFixedTime
         if max_depth == 0 
         { 
             // return a pseudo random move for depth 0
-            return MinimaxResult{ score: Score::MIN, action_id: Some(self.actions[(self.turn as usize * 71) % self.actions.len()].id), stat_nb_action_evaluated: 0, stat_nb_depth_evaluated : 0 } ;
-        }
-        if self.actions.len() == 1
-        {
-            return MinimaxResult{ score: Score::MIN, action_id: Some(self.actions[0].id), stat_nb_action_evaluated: 0, stat_nb_depth_evaluated : 0 } ;
+            return MinimaxResult{ score: Score::MIN, action_id: Some(self.actions[(self.turn as usize * 71) % self.actions.len()].id), ..___() } ;
         }
-        //let mut maxi = Score::MIN;
-        let mut r = self._minimax(self.current_team, 0, max_depth, Score::MIN, Score::MAX);
-        r.stat_nb_depth_evaluated = max_depth;
-        r
+        self.minimax_budget(SearchBudget::depth(max_depth), &CancellationToken::new())
     }
 }
 
-impl Display for BoardGameFixedTime
+impl Display for BoardGameFixedTime { fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult { self.fmt_for_viewer(f, None) }}
+
+impl BoardGameFixedTime
 {
-    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult
+    /// Only the tiles visible by the `viewer` are displayed, and the actions when it is its turn
+    pub(crate) fn fmt_for_viewer(&self, f: &mut Formatter<'_>, viewer : Option<Team>) -> DisplayResult
     {
+        let visibility = viewer.map(|t| self.visibility(t));
         let s = self.size();
         writeln!(f, "Turn #{}, {} play (team stat : {})", self.turn, self.current_team, self.team_data)?;
 
@@ -1013,11 +1307,18 @@ impl Display for BoardGameFixedTime
             {
                 let idx = at(x, y);
                 let c = self.board[idx].display_char();
-                if self.turn > 0 && self.was_moved_last_turn_or_this_turn(idx) { write!(f, "{}", MAGENTA_BACKGROUND)?;} else
+                let visible = visibility.as_ref().is_none_or(|v| v.is_visible(idx));
+                if visible && self.turn > 0 && self.was_moved_last_turn_or_this_turn(idx) { write!(f, "{}", MAGENTA_BACKGROUND)?;} else
                 {
                     //write!(f, "{}", if (x+y) %2 == 0 { GREY_BACKGROUND } else { BLACK_BACKGROUND })?;
                 }
-                if c != ' '
+                if self.is_void(idx)
+                {
+                    write!(f, " ")?;
+                }else if !visible
+                {
+                    write!(f, "{}?", GREY_FOREGROUND)?;
+                }else if c != ' '
                 {
                     write!(f, "{}{}", self.board[idx].teams_flags().display_color(), c)?;
                 }else
@@ -1039,6 +1340,7 @@ impl Display for BoardGameFixedTime
         writeln!(f)?;
         writeln!(f)?;
 
+        if viewer.is_some_and(|t| t != self.current_team) { return Ok(()); }
         writeln!(f, "{} actions :", self.actions.len())?;
         for c in self.actions.iter()
         {
@@ -1118,11 +1420,13 @@ impl BoardGameNotStarted
         if !self.team_data[t].alive { self.team_data[t].alive = true; self.nb_team_alive += 1; }
 
         let value = self[at].ai_value();
+        self.zobrist ^= self[at].zobrist(at);
 
         self.team_data[t].piece_pos.push(TeamPieceInfo { pos: at });
         self.team_data[t].alive_piece_value += value as PieceValue;
        
         self[at].teams_index[t] =  MaybeTeamPieceIdx::Some(NonZeroU8::new(self.team_data[t].piece_pos.len() as u8).unwrap());
+        self.zobrist ^= self[at].zobrist(at);
         custom_assert!(self.integrity_is_ok());
     }
     pub fn piece_remove_team(&mut self, at : At, t : Team)
@@ -1133,6 +1437,7 @@ impl BoardGameNotStarted
         custom_assert!(self[at].teams_index[t].is_some());
         let value = self[at].ai_value();
         let idx = TeamPieceIdx::from(self[at].teams_index[t].unwrap()) as usize - 1;
+        self.zobrist ^= self[at].zobrist(at);
         //self.team_data[t].remove_piece(idx, value);
 
         let (board, piece_pos) = (&mut self.board, &mut self.team_data[t].piece_pos);
@@ -1151,6 +1456,7 @@ impl BoardGameNotStarted
         self.team_data[t].alive_piece_value -= value as PieceValue;
 
         self[at].teams_index[t] = None;
+        self.zobrist ^= self[at].zobrist(at);
         custom_assert!(self.integrity_is_ok());
     }
     pub fn piece_set_flags(&mut self, at : At, flags : PieceFlags)
@@ -1158,8 +1464,10 @@ impl BoardGameNotStarted
         let p = &mut self[at];
 
         let old_value = p.ai_value();
+        let old_zobrist = p.zobrist(at);
         p.flags = flags;
         let new_value = p.ai_value();
+        self.zobrist ^= old_zobrist ^ self[at].zobrist(at);
 
         if new_value != old_value
         {
@@ -1176,28 +1484,6 @@ impl BoardGameNotStarted
     }
 }
 
-struct LineOfSightStat
-{
-    //energy_lose_per_move : Energy,
-    //energy_lose_is_traversed : Energy,
-    delta : At, 
-    max_move : isize,
-    can_capture : bool,
-}
-impl Default for LineOfSightStat
-{
-    fn default() -> Self {
-        Self 
-        { 
-            //energy_lose_per_move: Default::default(),
-            //energy_lose_is_traversed: 1,
-            delta: Default::default(),
-            max_move: Default::default(),
-            can_capture : true,
-        }
-    }
-}
-
 impl BoardGameFixedTime
 {
     pub const ORTHO_DELTA : [At; 4] = 
@@ -1208,7 +1494,6 @@ impl BoardGameFixedTime
     [
         At::new(1, 1), At::new(1, -1), At::new(-1, -1), At::new(-1, 1),
     ];
-    pub const INF : int = 64;
 
     /// 2 pieces can be enemy and friend at the same time if they belong to multiple teams
     /// 
@@ -1221,7 +1506,7 @@ impl BoardGameFixedTime
     pub fn can_be_captured_by_any (&self, dest : At) -> bool 
     { 
         let dest_flag = self[dest].flags;
-        !dest_flag.is_none_flag()
+        !dest_flag.is_none_flag() && !dest_flag.is_duck()
     }
 
     /// Such as teamless empty tile
@@ -1247,26 +1532,18 @@ impl BoardGameFixedTime
         for t in self[src].iter_team() 
         {
             let team_dir = self.team_direction(t);
-            
-            if team_dir.x < 0 { dist = dist.min((0 - src.x).abs()); }
-            if team_dir.x > 0 { dist = dist.min((self.size().x - 1 - src.x).abs()); }
-            if team_dir.y < 0 { dist = dist.min((0 - src.y).abs()); }
-            if team_dir.y > 0 { dist = dist.min((self.size().y - 1 - src.y).abs()); }
+            if team_dir == At::ZERO { continue; }
+            let mut d = 0;
+            while self.is_inside(src + team_dir * (d + 1)) { d += 1; }
+            dist = dist.min(d);
         }
         dist
     }
 
     fn is_on_promoting_tile(&self, teams : TeamsFlags, dest : At) -> bool
     {
-        for t in teams.iter_team() 
-        {
-            let team_dir = self.team_direction(t);
-            if team_dir.x < 0 && dest.x == 0 { return true; }
-            if team_dir.x > 0 && dest.x == self.size().x -1 { return true; }
-            if team_dir.y < 0 && dest.y == 0 { return true; }
-            if team_dir.y > 0 && dest.y == self.size().y -1 { return true; }
-        }
-        false
+        // the last tile in the direction of the team, before the edge or a void tile
+        teams.iter_team().any(|t| self.team_direction(t) != At::ZERO && !self.is_inside(dest + self.team_direction(t)))
     }
 
     fn capture_at(&self, action : &mut Action, src : At, dest : At) -> usize
@@ -1298,7 +1575,7 @@ impl BoardGameFixedTime
 
     fn can_move_to_custom(&self, actions : &mut Actions, src : At, dest : At, can_capture : bool, energy_add : Energy) -> MoveResult
     {
-        if !self.is_inside(dest) || self.are_friend(src, dest)  { return MoveResult::cant_move(); }
+        if !self.is_inside(dest) || self.are_friend(src, dest) || self[dest].is_duck() { return MoveResult::cant_move(); }
         let mut action = Action::new(ActionID::Move(src, dest), self[src].teams_flags());
         let nb_captured = if self.are_capturable_enemy(src, dest) 
         { 
@@ -1308,7 +1585,16 @@ impl BoardGameFixedTime
         action.push(self, UnitAction::Swap(src, dest));
         if src != dest && self[src].can_be_promoted() && self.is_on_promoting_tile(self[src].teams_flags(), dest)
         {
-            action.push(self, UnitAction::Promote(dest));
+            // one action for each promotion
+            for promotion in self[src].promotion_options(&self.chess_promotion)
+            {
+                let mut promote = action.clone();
+                promote.id = ActionID::Promote(src, dest, promotion);
+                promote.push(self, UnitAction::Promote(dest, promotion));
+                if energy_add != 0 { promote.push(self, UnitAction::EnergyAdd(energy_add)); }
+                actions.push(promote);
+            }
+            return MoveResult { can_move : true, nb_captured };
         }
         if energy_add != 0 { action.push(self, UnitAction::EnergyAdd(energy_add)); }
         actions.push(action);
@@ -1318,311 +1604,95 @@ impl BoardGameFixedTime
     fn can_move_to(&self, actions : &mut Actions, src : At, dest : At) -> MoveResult
     { self.can_move_to_custom(actions, src, dest, true, -1) }
 
-    fn line_of_sight_default(&self, actions : &mut Actions, src : At, delta : At, mut max_move : isize)
+    /// A chess rook (not a queen) of the same team that never moved
+    fn is_castling_rook(&self, king_src : At, rook_src : At) -> bool
     {
-        self.line_of_sight(actions, src, LineOfSightStat{  max_move, delta, ..Default::default() }, -1)
+        let rook = self[rook_src];
+        self.are_friend(king_src, rook_src) && rook.is_also_chess_rook() && !rook.is_also_chess_bishop() && rook.nb_time_moved == Saturating(0)
     }
 
-    fn line_of_sight(&self, actions : &mut Actions, src : At, mut stat : LineOfSightStat, energy_add : Energy)
+    /// Castling of a king that never moved with the first piece on each side of its rank, if it is a rook that never moved (Chess960 rules).
+    /// The king and the rook end on the 3rd and 4th tiles from the side of the back rank of the team (c and d, or g and f for 8 tiles), whatever their start tiles.
+    /// The action is the king moving to its destination, or to its rook if it move less than 2 tiles
+    fn actions_castling(&self, actions : &mut Actions, king_src : At, apply_anticipation : bool)
     {
-        if !stat.delta.have_length() { return; }
-        let mut dest = src;
+        if self[king_src].nb_time_moved != Saturating(0) { return; }
+        let Some(team) = self[king_src].iter_team().next() else { return; };
 
-        while stat.max_move >= 1
+        // the rank is perpendicular to the direction of the team
+        let axis = if self.team_direction(team).x == 0 { At::X } else { At::Y };
+        let along = |a : At| if axis == At::X { a.x } else { a.y };
+        let tile = |v : AtIntType| if axis == At::X { king_src.with_x(v) } else { king_src.with_y(v) };
+        let (start, len) = self.team_data[team].back_rank.unwrap_or_else(||
         {
-            stat.max_move -= 1;
-            dest += stat.delta;
-            let m = self.can_move_to_custom(actions, src, dest, stat.can_capture, energy_add);
-            if !m.can_move || m.nb_captured > 0 { return; }
-        }
-    }
+            let mut start = along(king_src);
+            while self.is_inside(tile(start - 1)) { start -= 1; }
+            let mut end = along(king_src);
+            while self.is_inside(tile(end + 1)) { end += 1; }
+            (start, end - start + 1)
+        });
+        let end = start + len;
 
-    fn actions_piece_chess_king(&self, actions : &mut Actions, king_src : At, apply_anticipation : bool)
-    {
-        for d in Self::ORTHO_DELTA { self.line_of_sight_default(actions, king_src, d, 1); }
-        for d in Self::DIAG_DELTA  { self.line_of_sight_default(actions, king_src, d, 1); }
-
-        // castle
-        if self[king_src].nb_time_moved == Saturating(0)
+        for (side, king_dest, rook_dest) in [(-1, tile(start + 2), tile(start + 3)), (1, tile(end - 2), tile(end - 3))]
         {
-            for (king_dest, castle_src, castle_dest) in [(king_src.with_x(king_src.x-2), king_src.with_x(0), king_src.with_x(king_src.x-1)), (king_src.with_x(king_src.x+2), king_src.with_x(self.size().x()-1), king_src.with_x(king_src.x+1))]
-            {
-                if !self.is_inside(king_dest) || !self.is_inside(castle_dest) { continue; }
-                // queen can't be here with 0 turn (she have the chess_rook ability)
-                if !self[castle_src].is_also_chess_rook() || !self[castle_src].nb_time_moved == Saturating(0) { continue;} 
-
-                let mut can_castle = true;
-
-                let inc = (king_dest-king_src).x.signum();
-                let mut pos = king_src;
-                pos.x += inc;
-                while pos != castle_src
-                {
-                    // already a piece on the way
-                    if !self[pos].is_empty_ability() { can_castle = false; break; }
-                    pos.x += inc;
-                }
+            if !self.is_inside(king_dest) || !self.is_inside(rook_dest) { continue; }
 
-                if !can_castle { continue;}
+            let mut rook_src = king_src + axis * side;
+            while self.is_inside(rook_src) && self[rook_src].is_empty_ability() && !self[rook_src].is_duck() { rook_src += axis * side; }
+            if !self.is_inside(rook_src) || !self.is_castling_rook(king_src, rook_src) { continue; }
 
+            // every tile crossed by the king or the rook must be empty
+            let crossed = [king_src, rook_src, king_dest, rook_dest].map(along);
+            let (min, max) = (*crossed.iter().min().unwrap(), *crossed.iter().max().unwrap());
+            if (min..=max).map(tile).any(|pos| pos != king_src && pos != rook_src && (!self.is_inside(pos) || !self[pos].is_empty_ability() || self[pos].is_duck())) { continue; }
 
-                let n = self.next_playing_team_after(self[king_src].iter_team().next().unwrap());
-                
-                //if n == self.current_team { continue; }
-
-                if apply_anticipation
-                {
-                    // calculate the opponent move it at the last second to check if a tile is under attack
-                    let mut opponent_move = self.actions_for_team_with_anticipation(n, false);
-
-                    pos = king_src;
-                    while pos != king_dest
-                    {
-                        pos.x += inc;
-
-                        if opponent_move.iter().any(|a| a.iter().any(|s| match s
-                            {
-                                UnitAction::Swap(_src, dest) => *dest == pos,
-                                _ => false
-                            }))
-                        {
-                            // under attack
-                            can_castle = false; break;
-                        }
-                    }
-                }
-                
-                if !can_castle { continue; }
-
-                let mut action = Action::new(ActionID::Move(king_src, king_dest), self[king_src].teams_flags());
-                action.push(self, UnitAction::Swap(king_src, king_dest));
-                action.push(self, UnitAction::Swap(castle_src, castle_dest));
-                action.push(self, UnitAction::EnergyAdd(-1));
-                actions.push(action);
-            }
-        }
-    }
-    fn actions_piece_chess_bishop(&self, actions : &mut Actions, src : At) { for d in Self::DIAG_DELTA  { self.line_of_sight_default(actions, src, d, Self::INF); } }
-    fn actions_piece_chess_rook  (&self, actions : &mut Actions, src : At) 
-    { 
-        for d in Self::ORTHO_DELTA  
-        { self.line_of_sight_default(actions, src, d, Self::INF); } 
-    }
-    fn actions_piece_chess_knight(&self, actions : &mut Actions, src : At) 
-    {
-        // Rotation tricks
-        let mut delta = At::new(1, 2);
-        for _ in 0..4 { self.can_move_to(actions, src, src + delta); delta = delta.y_rx(); }
-        delta.y = -delta.y;
-        for _ in 0..4 { self.can_move_to(actions, src, src + delta); delta = delta.y_rx(); }
-    }
-
-    fn actions_piece_chess_pawn_dir(&self, actions : &mut Actions, src : At, dir : At)
-    {
-        let right = dir.y_x();
-        let left  = dir.ry_rx();
-
-        let right_dest = src + right + dir;
-        let left_dest  = src + left + dir;
-
-        if self.is_inside(right_dest) && self.are_capturable_enemy(src, right_dest) { self.can_move_to(actions, src, right_dest); }
-        if self.is_inside(left_dest) && self.are_capturable_enemy(src, left_dest ) { self.can_move_to(actions, src, left_dest ); }
-
-        if self.can_move_to_custom(actions, src, src + dir, false, -1).can_move && !self[src].already_move()
-        {
-            self.can_move_to_custom(actions, src, src + dir * 2, false, -1);
-        }
-
-        // *En passant*
-        { 
-            let all_en_passant_relative = [left, right];
-            for en_passant_rel in all_en_passant_relative
+            let king_distance = along(king_dest) - along(king_src);
+            if apply_anticipation
             {
-                let en_passant_attack = src + en_passant_rel;
-                let en_passant_dest  = en_passant_attack + dir;
-                if !self.is_inside(en_passant_attack) || !self.is_inside(en_passant_dest) { continue; }
-
-                if self.are_capturable_enemy(src, en_passant_attack)
+                // can't castle out of an attack or through an attacked tile. The destination is checked by the anticipation
+                let attacked = (0..king_distance.abs().max(1)).map(|i| king_src + axis * (king_distance.signum() * i)).any(|pos|
                 {
-                    let pawn = &self[en_passant_attack];
-                    if
-                         pawn.is_also_chess_pawn() &&
-                         self.was_moved_last_turn_or_this_turn(en_passant_attack) &&
-                         pawn.distance_travel_total == Saturating(2) && 
-                         pawn.nb_time_moved == Saturating(1)
-                    { 
-                        self.can_move_to(actions, src, en_passant_dest);
-                        actions.last_mut().unwrap().push(self, UnitAction::Capture(None, en_passant_attack));
-                    }
-                }
+                    let mut s = self.clone();
+                    if pos != king_src { s.piece_swap(king_src, pos); }
+                    s.is_crown_attacked(team)
+                });
+                if attacked { continue; }
             }
-        }
-    }
 
-    fn actions_piece_chess_pawn(&self, actions : &mut Actions, src : At)
-    {
-        for t in Team::iter()
-        {
-            if !self[src].is_also_team(t) { continue; }
-            self.actions_piece_chess_pawn_dir(actions, src, self.team_direction(t));
+            let id_dest = if king_distance.abs() >= 2 { king_dest } else { rook_src };
+            let mut action = Action::new(ActionID::Move(king_src, id_dest), self[king_src].teams_flags());
+            action.push(self, UnitAction::Swap(king_src, king_dest));
+            // the rook was swapped with the king if the king go on its tile
+            action.push(self, UnitAction::Swap(if king_dest == rook_src { king_src } else { rook_src }, rook_dest));
+            action.push(self, UnitAction::EnergyAdd(-1));
+            actions.push(action);
         }
     }
 
-    fn actions_piece_dame_pawn_for_team(&self, actions : &mut Actions, src : At, dir : At, can_move_backward : bool, mut only_attack : bool)
+    /// Calculate the action for a given piece regardeless of the turn. Anticipation is not applied
+    pub fn actions_piece(&self, actions : &mut Actions, src : At, apply_anticipation : bool)
     {
-        if self.current_nb_action_this_turn > 0
+        self.actions_piece_unfiltered(actions, src, apply_anticipation || self.legal_moves);
+        if self.legal_moves
         {
-            let this_turn = self[src].last_turn_moved;
-            if  this_turn != self.turn { return; }
-            only_attack = true;
-        }
-
-        let right = dir.y_x();
-        let right_up = right + dir;
-        let right_right_up_up = right_up * 2;
-        let right_up_dest = src + right_up;
-        let right_right_up_up_dest = src + right_right_up_up;
-        let can_attack_right_up = self.is_inside(right_up_dest) && self.are_capturable_enemy(src, right_up_dest)  && self.is_inside(right_right_up_up_dest) && self[right_right_up_up_dest].is_none_flag();
-
-        let left = dir.ry_rx();
-        let left_up = left + dir;
-        let left_left_up_up = left_up * 2;
-        let left_up_dest = src + left_up;
-        let left_left_up_up_dest = src + left_left_up_up;
-        let can_attack_left_up = self.is_inside(left_up_dest) && self.are_capturable_enemy(src, left_up_dest)  && self.is_inside(left_left_up_up_dest) && self[left_left_up_up_dest].is_none_flag();
-
-        let right_down = right - dir;
-        let right_right_down_down = right_down * 2;
-        let right_down_dest = src + right_down;
-        let right_right_down_down_dest = src + right_right_down_down;
-        let can_attack_right_down = self.is_inside(right_down_dest) && self.are_capturable_enemy(src, right_down_dest)  && self.is_inside(right_right_down_down_dest) && self[right_right_down_down_dest].is_none_flag();
-
-        let left_down = left - dir;
-        let left_left_down_down = left_down * 2;
-        let left_down_dest = src + left_down;
-        let left_left_down_down_dest = src + left_left_down_down;
-        let can_attack_left_down = self.is_inside(left_down_dest) && self.are_capturable_enemy(src, left_down_dest)  && self.is_inside(left_left_down_down_dest) && self[left_left_down_down_dest].is_none_flag();
-
-        if can_attack_left_up
-        {
-            let _ok = self.can_move_to_custom(actions, src , left_left_up_up_dest, false, 0).can_move;
-            custom_assert!(_ok);
-            actions.last_mut().unwrap().push(self, UnitAction::Capture(None, left_up_dest));
-        } else if !only_attack
-        {
-            self.can_move_to_custom(actions, src, left_up_dest, false, -1);
-        }
-
-        if can_attack_right_up
+            self.legal_moves_apply_on(actions)
+        }else if apply_anticipation
         {
-            let _ok = self.can_move_to_custom(actions, src , right_right_up_up_dest, false, 0).can_move;
-            custom_assert!(_ok);
-            actions.last_mut().unwrap().push(self, UnitAction::Capture(None, right_up_dest));
-        }else if !only_attack
-        {
-            self.can_move_to_custom(actions, src, right_up_dest, false, -1);
-        }
-
-        if can_move_backward
-        {
-            if can_attack_left_down 
-            {
-                let _ok = self.can_move_to_custom(actions, src , left_left_down_down_dest, false, 0).can_move;
-                custom_assert!(_ok);
-                actions.last_mut().unwrap().push(self, UnitAction::Capture(None, left_down_dest));
-            }else if !only_attack
-            {
-                self.can_move_to_custom(actions, src, left_down_dest, false, -1);
-            }
-
-            if can_attack_right_down
-            {
-                let _ok = self.can_move_to_custom(actions, src , right_right_down_down_dest, false, 0).can_move;
-                custom_assert!(_ok);
-                actions.last_mut().unwrap().push(self, UnitAction::Capture(None, right_down_dest));
-            }else if !only_attack
-            {
-                self.can_move_to_custom(actions, src, right_down_dest, false, -1);
-            }
-        }
-
-        /* 
-        if can_attack_left_up || can_attack_right_up || can_attack_left_down || can_attack_right_down
-        {
-            if can_attack_left_up
-            { 
-                if self.can_move_to_custom(actions, src , left_left_up_up_dest, false, 0).can_move
-                {
-                    actions.last_mut().unwrap().push(self, UnitAction::Capture(None, left_up_dest));
-                }
-            }
-            if can_attack_right_up
-            { 
-                self.can_move_to(actions, src , right_right_up_up_dest);
-                actions.last_mut().unwrap().push(self, UnitAction::Capture(None, right_up_dest));
-            }
-            if can_attack_left_down
-            { 
-                self.can_move_to(actions, src , left_left_down_down_dest);
-                actions.last_mut().unwrap().push(self, UnitAction::Capture(None, left_down_dest));
-            }
-            if can_attack_right_down
-            { 
-                self.can_move_to(actions, src , right_right_down_down_dest);
-                actions.last_mut().unwrap().push(self, UnitAction::Capture(None, right_down_dest));
-            }
-        }//else
-        */
-        {
-            /* 
-            if can_move_backward
-            {
-                self.can_move_to_custom(actions, src, left_down_dest, false, -1);
-                self.can_move_to_custom(actions, src, right_down_dest, false, -1);
-            }
-            */
-
-            /* 
-            if self.is_inside(left_up_dest) && self[left_up_dest].is_none_flag() { self.can_move_to(actions, src , left_up_dest); }
-            if self.is_inside(right_up_dest) && self[right_up_dest].is_none_flag() { self.can_move_to(actions, src , right_up_dest); }
-            if can_move_backward
-            {
-                if self.is_inside(left_down_dest) && self[left_down_dest].is_none_flag() { self.can_move_to(actions, src , left_down_dest); }
-                if self.is_inside(right_down_dest) && self[right_down_dest].is_none_flag() { self.can_move_to(actions, src , right_down_dest); }
-            }*/
+            self.relic_anticipation_apply_on(actions)
         }
     }
 
-
-    fn actions_piece_dame_pawn_custom(&self, actions : &mut Actions, src : At, can_move_backward : bool)
+    /// The castling still check the attacked tiles if `apply_anticipation`, but the actions are not filtered
+    pub(crate) fn actions_piece_unfiltered(&self, actions : &mut Actions, src : At, apply_anticipation : bool)
     {
-        for t in Team::iter()
+        let ability = self[src].ability().0 >> PieceFlags::ABILITY_OFFSET;
+        for idx in 0..PieceFlags::ABILITY_COUNT as usize
         {
-            if !self[src].is_also_team(t) { continue; }
-            self.actions_piece_dame_pawn_for_team(actions, src, self.team_direction(t), can_move_backward, false);
+            if ability & (1 << idx) == 0 { continue; }
+            if let Some(moves) = self.ability_moves(idx) { self.actions_piece_moves(actions, src, moves, apply_anticipation); }
         }
     }
 
-    fn actions_piece_dame_pawn(&self, actions : &mut Actions, src : At) 
-    { self.actions_piece_dame_pawn_custom(actions, src, false); }
-
-    fn actions_piece_dame_king(&self, actions : &mut Actions, src : At) 
-    { self.actions_piece_dame_pawn_custom(actions, src, true); }
-
-    /// Calculate the action for a given piece regardeless of the turn. Anticipation is not applied
-    pub fn actions_piece(&self, actions : &mut Actions, src : At, apply_anticipation : bool)
-    {
-        if self[src].have_flag(PieceFlags::AB_CHESS_KING  ) { self.actions_piece_chess_king  (actions, src, apply_anticipation); } 
-        if self[src].have_flag(PieceFlags::AB_CHESS_KNIGHT) { self.actions_piece_chess_knight(actions, src); } 
-        if self[src].have_flag(PieceFlags::AB_CHESS_BISHOP) { self.actions_piece_chess_bishop(actions, src); } 
-        if self[src].have_flag(PieceFlags::AB_CHESS_ROOK  ) { self.actions_piece_chess_rook  (actions, src); } 
-        if self[src].have_flag(PieceFlags::AB_CHESS_PAWN  ) { self.actions_piece_chess_pawn  (actions, src); } 
-        if self[src].have_flag(PieceFlags::AB_DAME_PAWN   ) { self.actions_piece_dame_pawn   (actions, src); } 
-        if self[src].have_flag(PieceFlags::AB_DAME_KING   ) { self.actions_piece_dame_king   (actions, src); } 
-
-        if apply_anticipation { self.relic_anticipation_apply_on(actions) }
-    }
-
 
     pub fn iter_team_data(&self) -> impl Iterator<Item=(Team,&TeamData)> { self.team_data.iter() }
     pub fn current_team_data(&self) -> &TeamData { &self.team_data[self.current_team] }
@@ -1632,9 +1702,22 @@ impl BoardGameFixedTime
     pub fn actions(&self) -> Actions { self.actions_for_team(self.current_team) }
     pub fn update_actions(&mut self, apply_anticipation : bool) 
     { 
-        self.actions = self.actions();
+        let apply_anticipation = apply_anticipation && self.current_team_data().relics.flag_have(Relic::Anticipation);
+        let mut actions = Actions::new();
+        if self.duck_to_place
+        {
+            self.actions_duck(&mut actions);
+        }else
+        {
+            for at in self.iter_idx_team(self.current_team) { self.actions_piece_unfiltered(&mut actions, at, apply_anticipation || self.legal_moves); }
+        }
+        actions.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        self.actions = actions;
         
-        if apply_anticipation && self.current_team_data().relics.flag_have(Relic::Anticipation)
+        if self.legal_moves
+        {
+            self.legal_moves_apply();
+        }else if apply_anticipation
         {
             self.relic_anticipation_apply();
         }
@@ -1647,6 +1730,11 @@ impl BoardGameFixedTime
         if !action.team.is_also_team(self.current_team) { return true; }
 
         let after = self.execute_action(action, false);
+        if after.duck_to_place && !after.is_end_of_the_game()
+        {
+            // the adversary play after the duck
+            return after.iter_next_state(false).any(|(_, duck)| duck.iter_next_state(false).all(|(_, state)| state.team_data[self.current_team].alive));
+        }
 
         for (_, state) in after.iter_next_state(false)
         {
@@ -1683,7 +1771,10 @@ impl BoardGameFixedTime
     }
 
     /// Also calculate the pin
-    pub fn relic_anticipation_apply(&mut self) 
+    pub fn relic_anticipation_apply(&mut self) { self.actions_filter_and_pin(Self::relic_anticipation_judge_if_action_is_legal, false) }
+
+    /// Only keep the legal actions, and mark the pieces that lost some actions with `PARTIAL_PIN` or all of them with `TOTAL_PIN`
+    pub(crate) fn actions_filter_and_pin(&mut self, is_legal : impl Fn(&Self, &Action) -> bool, castling_check : bool)
     {
         self.clear_pin_current_team();
         let mut idx : Vec<(At,usize)> = self.iter_idx_team_current().map(|e| (e, 0)).collect();
@@ -1693,18 +1784,15 @@ impl BoardGameFixedTime
 
         for action in tmp.into_iter()
         {
-            if self.relic_anticipation_judge_if_action_is_legal(&action) 
+            if is_legal(self, &action) 
             {
                 self.actions.push(action);
                 continue;
             }
-            match action.id
+            // the duck placements don't pin any piece
+            if let Some(i) = self[action.id.src()].teams_index.get_idx(self.current_team)
             {
-                ActionID::Move(src, _) => 
-                { 
-                    let i = self[src].teams_index.get_idx(self.current_team).unwrap();
-                    idx[i].1 += 1;
-                },
+                idx[i].1 += 1;
             }
         }
 
@@ -1713,7 +1801,7 @@ impl BoardGameFixedTime
         for (at, _nb_pinned_move) in idx
         {
             actions.clear();
-            self.actions_piece(&mut actions, at, false);
+            self.actions_piece_unfiltered(&mut actions, at, castling_check);
             if _nb_pinned_move != 0 
             {
                 let mut f = self[at].flags;
@@ -1735,7 +1823,10 @@ impl BoardGameFixedTime
     pub fn calculate_actions_for_team(&self, t : Team, actions : &mut Actions) { self.calculate_actions_for_team_with_anticipation(t, actions, false)}
     pub fn calculate_actions_for_team_with_anticipation(&self, t : Team, actions : &mut Actions, apply_anticipation : bool) 
     { 
-        for at in self.iter_idx_team(t) { self.actions_piece(actions, at, apply_anticipation); }
+        for at in self.iter_idx_team(t) 
+        { 
+            if apply_anticipation { self.actions_piece(actions, at, true); } else { self.actions_piece_unfiltered(actions, at, false); }
+        }
         
         // sort by 'best' action to speed up alpha beta pruning
         actions.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
@@ -1775,6 +1866,9 @@ impl BoardGameFixedTime
     {
         let delta = b - a;
         let distance_travel_total = delta.length_manhattan() as TileTravelType;
+        // a piece can stay on its tile (Chess960 castling)
+        let tiles : &[At] = if a == b { &[a] } else { &[a, b] };
+        for p in tiles { self.data.zobrist ^= self[*p].zobrist(*p); }
 
         for (t, idx) in self.data.board[a].teams_index.iter() 
         { 
@@ -1789,7 +1883,7 @@ impl BoardGameFixedTime
         self[b] = tmp;
         self[b].old_pos = a;
 
-        for p in [a, b]
+        for p in tiles.iter().copied()
         {
             self[p].last_turn_moved = self.turn;
             self[p].last_action_moved = self.nb_actions;
@@ -1797,8 +1891,10 @@ impl BoardGameFixedTime
             self[p].nb_time_moved += 1;
             self[p].distance_travel_total += distance_travel_total;
         }
+        for p in tiles { self.data.zobrist ^= self[*p].zobrist(*p); }
 
         custom_assert!(self.integrity_is_ok());
+        custom_assert!(self.zobrist_tiles_is_ok());
     }
 
     fn have_relic_at(&self, at : At, relic : Relic) -> bool
@@ -1815,6 +1911,8 @@ impl BoardGameFixedTime
             data : self.data.clone(),
             turn : self.turn,
             nb_actions : self.nb_actions+1,
+            half_move_clock : self.half_move_clock+1,
+            zobrist_state : self.zobrist_state,
             ..___()
         };
 
@@ -1840,20 +1938,38 @@ impl BoardGameFixedTime
                     }
                     s.captured.push(self[dest]);
                     s.set_empty_piece(dest);
+                    s.half_move_clock = 0;
                 },
                 UnitAction::Swap(a, b) => 
                 {
+                    if s[a].is_also_chess_pawn() { s.half_move_clock = 0; }
                     s.piece_swap(a, b);
                 },
                 UnitAction::EnergyAdd(how_many) => s.current_nb_energy += how_many,
-                UnitAction::Promote(at) => 
+                UnitAction::Promote(at, promotion) => 
                 {
-                    let f = s[at].promote(self.have_relic_at(at, Relic::Absorb));
+                    let f = s[at].promote(promotion, self.have_relic_at(at, Relic::Absorb));
                     s.piece_set_flags(at, f);
                 },
+                UnitAction::DuckPlace(old, dest) =>
+                {
+                    match old
+                    {
+                        Some(old) => s.piece_swap(old, dest),
+                        None => s.piece_set_flags(dest, PieceFlags::DUCK),
+                    }
+                    s.duck_to_place = false;
+                    // the duck is not a move for the halfmove clock
+                    s.half_move_clock = self.half_move_clock;
+                },
             }
         }
 
+        if !matches!(action.id, ActionID::Duck(_)) && self.current_team_data().relics.flag_have(Relic::DuckButDifferent)
+        {
+            s.duck_to_place = true;
+        }
+
         //s.turn += 1;
 
         s
@@ -1864,39 +1980,16 @@ impl BoardGameFixedTime
 {
     pub fn init_new_turn(&mut self)
     {
-        self.current_nb_energy = 1 + if self.current_team_data().relics.flag_have(Relic::MoveTwiceInATurn) { 1 } else { 0 };
+        self.current_nb_energy = self.energy_per_turn + if self.current_team_data().relics.flag_have(Relic::MoveTwiceInATurn) { 1 } else { 0 };
         self.current_nb_action_this_turn = 0;
         self.turn += 1;
     }
 
-    pub fn next_playing_team(&self) -> Team { self.next_playing_team_after(self.current_team) }
-    pub fn next_playing_team_after(&self, team : Team) -> Team
-    {
-        // Todo, handle it in a different way can be better
-        let mut futur_team = team;
-        let mut nb_iter = 0;
-        loop
-        {
-            futur_team = match futur_team
-            {
-                Team::White   => Team::Black,
-                Team::Black    => Team::Yellow,
-                Team::Yellow => Team::Green,
-                Team::Green  => Team::White,
-            };
-
-            if self.team_data[futur_team].alive { break; }
-            nb_iter += 1;
-            if nb_iter > Team::LENGHT { break; }
-        }
-        futur_team
-    }
-
     pub fn end_of_action(&mut self, apply_anticipation : bool)
     {
         self.nb_actions +=1;
 
-        if self.current_nb_energy <= 0 
+        if self.current_nb_energy <= 0 && !self.duck_to_place
         {
             self.current_team = self.next_playing_team();
             self.init_new_turn();
@@ -1906,6 +1999,7 @@ impl BoardGameFixedTime
         }
 
         self.actions.clear();
+        self.apply_draw_rules();
         if !self.is_end_of_the_game()
         {
             self.update_actions(apply_anticipation);
@@ -1913,44 +2007,20 @@ impl BoardGameFixedTime
             { 
                 if self.current_nb_action_this_turn == 0
                 {
-                    let current_team = self.current_team;
-
-                    // I don't want any draw. It don't make sens because the kind can't do anythings, and it make ai harder to code
-                    /* 
-                    
-                    // check if the last piece is checked
-                    if self.nb_team_alive <= 2
-                    {
-                        let mut next = self.clone();
-                        next.init_new_turn();
-                        next.current_team = self.next_playing_team();
-                        next.actions.clear();
-                        next.update_actions(true);
-                        if !next.actions.iter().any(|e| e.iter().any(|s| match &s
-                        {
-                            UnitAction::Capture(_, dest) => self[*dest].have_flag(PieceFlags::CROWN),
-                            _ => false,
-                        }))
-                        {
-                            // stale mate
-                            self.is_draw = true;
-                            for t in Team::iter()
-                            {
-                                self.execute_team_lose(t);
-                            }
-                        }
-                    }
-                    */
-                    self.execute_team_lose(current_team);
+                    self.execute_no_action_left();
                 }else
                 {
                     self.current_nb_energy = 0;
+                    self.duck_to_place = false;
                     self.end_of_action(apply_anticipation);
                 }
                 // self._is_end_of_the_game = true;
             }
         }
 
+        self.zobrist_update_state();
+        custom_assert!(self.zobrist_is_ok());
+
         // Todo : change it to the 'best' move by default
         // self.ai_current_actions_and_result_idx = 0;
         // self.update_team_score();
@@ -1999,6 +2069,8 @@ impl BoardGameFixedTime
             }
         }
 
+        self.zobrist_init();
+
     }
 }
 
@@ -2006,11 +2078,21 @@ impl BoardGameFixedTime
 pub struct BoardGame
 {
     time_line : Vec<BoardGameFixedTime>,
+    /// `action_line[i]` was played to go from `time_line[i]` to `time_line[i+1]`
+    action_line : Vec<ActionID>,
     time_line_idx : usize,
 }
 impl BoardGame
 {
-    pub fn new(data : BoardGameNotStarted) -> Self { Self { time_line: vec![BoardGameFixedTime::new(data)], time_line_idx: 0 }}
+    pub fn new(data : BoardGameNotStarted) -> Self { Self::new_from_fixed_time(BoardGameFixedTime::new(data)) }
+    pub fn new_from_fixed_time(start : BoardGameFixedTime) -> Self { Self { time_line: vec![start], action_line: vec![], time_line_idx: 0 }}
+
+    /// The position before the first action
+    pub fn start(&self) -> &BoardGameFixedTime { &self.time_line[0] }
+    /// Actions played from the start to the current position
+    pub fn played_actions(&self) -> &[ActionID] { &self.action_line[..self.time_line_idx] }
+    /// Positions from the start to the current position (included)
+    pub fn played_positions(&self) -> &[BoardGameFixedTime] { &self.time_line[..=self.time_line_idx] }
 
     pub fn current(&self) -> &BoardGameFixedTime { &self.time_line[self.time_line_idx] }
     pub fn current_mut(&mut self) -> &mut BoardGameFixedTime { &mut self.time_line[self.time_line_idx] }
@@ -2028,12 +2110,17 @@ impl BoardGame
 {
     pub fn console_display(&self) 
     {
+        let viewer = self.fog_viewer();
         println!();
-        println!("{:?}", self.team_data);
+        // the position of the pieces is hidden by the fog
+        if viewer.is_none() { println!("{:?}", self.team_data); }
         println!();
 
         println!("Turn {}, playing {}, energy {}", self.time_line.len(), self.current_team, self.current_nb_energy);
-        println!("{}", self.current());
+        if let Some(seed) = self.layout_seed { println!("Layout seed {}", seed); }
+        if self.check_status != CheckStatus::None { println!("{}", self.check_status); }
+        if self.duck_to_place { println!("{} must place the duck", self.current_team); }
+        println!("{}", self.current().display_for(viewer));
     }
     
     fn console_input_line(&self) -> String
@@ -2041,7 +2128,7 @@ impl BoardGame
         let mut input = String::new();
         match std::io::stdin().read_line(&mut input) 
         {
-            Ok(_) => { input = input.to_lowercase(); },
+            Ok(_) => {},
             Err(e) => { println!("error when reading : {}", e); },
         }
         input
@@ -2116,28 +2203,73 @@ impl BoardGame
 
     pub fn console_ai_best_move(&self) -> ActionID
     {
+        let seed = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or_default();
+        if self.fog_of_war
+        {
+            // the opening book and the tablebases need the real position
+            let cancel = CancellationToken::new();
+            let r = self.determinized_search(SearchBudget::time(1.), &cancel, seed, |s, budget| s.minimax_budget(budget, &cancel));
+            println!("Ai : {} in the fog at Turn {:>2}, {} actions evaluated on {} sampled positions", self.action_id_to_san(r.action_id.unwrap()).unwrap_or_default(), self.turn+1, r.stat_nb_action_evaluated, BoardGameFixedTime::DETERMINIZED_NB_SAMPLE);
+            return r.action_id.unwrap();
+        }
+        if let Some(id) = OpeningBook::chess_default().choose(self, &mut Random::new(self.zobrist ^ seed))
+        {
+            println!("Ai : {} from the opening book at Turn {:>2}", self.action_id_to_san(id).unwrap_or_default(), self.turn+1);
+            return id;
+        }
+        if let Some((id, value)) = Tablebases::default_tables().iter().find_map(|tb| tb.best_action(self))
+        {
+            println!("Ai : {} from the tablebase at Turn {:>2} ({})", self.action_id_to_san(id).unwrap_or_default(), self.turn+1, value);
+            return id;
+        }
+
         let start = Instant::now();
         let r = self.ai_minimax_default();
         let elapsed_s = start.elapsed().as_secs_f64();
 
-        println!("Ai : {:>9} actions evaluated in {:>7.3}s with depth {} at Turn {:>2} before choosing to {}. ({:>9} actions/s)", r.stat_nb_action_evaluated, elapsed_s, r.stat_nb_depth_evaluated, self.turn+1, r.action_id.unwrap(), (r.stat_nb_action_evaluated as f64 / elapsed_s) as usize);
+        let san = self.action_id_to_san(r.action_id.unwrap()).unwrap_or_default();
+        println!("Ai : {:>9} actions evaluated in {:>7.3}s with depth {} at Turn {:>2} before choosing to {} ({}). ({:>9} actions/s, {:>5.1}% TT hit)", r.stat_nb_action_evaluated, elapsed_s, r.stat_nb_depth_evaluated, self.turn+1, r.action_id.unwrap(), san, (r.stat_nb_action_evaluated as f64 / elapsed_s) as usize, r.tt_hit_rate() * 100.);
         r.action_id.unwrap()
     }
-    pub fn console_input_from_str(&mut self, line : &str) -> Result<ActionID,String>
+    /// The promotion is choosen with a letter (`e7e8n`), the queen by default
+    fn console_action_id(&self, src : At, dest : At, promotion : Option<char>) -> ActionID
     {
-        let chars = line.chars();
+        let promotion = promotion.and_then(PieceFlags::from_fen_char).map(|(_, f)| f.ability());
+        self.actions.iter().map(|a| a.id).find(|id| id.src() == src && id.dest() == dest && match (id.promotion(), promotion)
+        {
+            (Some(p), Some(wanted)) => p.without_flag(PieceFlags::AB_DAME_KING) == wanted,
+            _ => true,
+        }).unwrap_or(ActionID::Move(src, dest))
+    }
 
+    /// Accept the coordinates of the move (`e2e4`) or the SAN (`e4`, `Nf3`, `O-O`...), or the tile of the duck (`d4`)
+    pub fn console_input_from_str(&mut self, line : &str) -> Result<ActionID,String>
+    {
         if line.len() <= 2 
         { 
             return Ok(self.console_ai_best_move());
         }
 
-        let mut it = chars.into_iter();
+        let lower = line.to_lowercase();
+        let mut it = lower.chars();
 
-        let src =  self.console_input_at(&mut it).map_err(|e| e + " in move source")?;
-        let dest =  self.console_input_at(&mut it).map_err(|e| e + " in move destination")?;
+        if self.duck_to_place
+        {
+            // the tile of the duck (`d4` or `@d4`)
+            return self.console_input_at(&mut lower.trim().trim_start_matches('@').chars()).map(ActionID::Duck).map_err(|e| e + " in duck tile");
+        }
+
+        let coordinate = self.console_input_at(&mut it).map_err(|e| e + " in move source").and_then(|src|
+        {
+            let dest = self.console_input_at(&mut it).map_err(|e| e + " in move destination")?;
+            Ok(self.console_action_id(src, dest, it.next().filter(|c| c.is_ascii_alphabetic())))
+        });
 
-        Ok(ActionID::Move(src, dest))
+        match coordinate
+        {
+            Ok(action_id) => Ok(action_id),
+            Err(err) => self.san_to_action_id(line).map_err(|san_err| format!("{} (or {} in SAN)", err, san_err)),
+        }
     }
 
     /// panic if don't exist
@@ -2149,106 +2281,30 @@ impl BoardGame
     pub fn execute(&mut self, action_id : ActionID)
     {
         self.time_line.drain(self.time_line_idx+1..);
+        self.action_line.drain(self.time_line_idx..);
         //let cur = self.current_mut();
         //let action_idx = cur.actions_and_result.iter().position(|e| e.action.id == action_id).expect("illegal input");
         
         let result = self.execute_action(self.get_action_from_action_id(action_id), true);
 
         self.time_line.push(result);
+        self.action_line.push(action_id);
         self.time_line_idx += 1;
+        self.apply_threefold_repetition();
     }
 }
 
 impl BoardGame
 {
-    pub fn new_default() -> Self
-    {
-        // Self::new_chess_custom(4, true)
-        // Self::new_chess_custom(2, true)
-        //Self::new_checker()
-        Self::new_chess()
-        
-        //Self::new_checker_custom_size(at(8, 8), 3)
-
-        //Self::new_checker_custom_size(at(8, 8), 3)
-
-        /* 
-        let mut c = Self::new_checker_custom_size(at(8, 8), 3);
-        let lines = include_str!("./contre_kt.txt").lines();
-        for v in lines
-        {
-            let action_id = c.console_input_from_str(v).unwrap();
-            c.execute(action_id);
-        };
-        c*/
-    }
-
-    pub fn new_chess() -> Self { Self::new_chess_custom(2, true, Relics::ZERO.with_flag_add(Relic::Anticipation)) }
-
-
-    const CHESS_BACK_VALUE : [PieceFlags; 8] = [PieceFlags::AB_CHESS_ROOK, PieceFlags::AB_CHESS_KNIGHT, PieceFlags::AB_CHESS_BISHOP, PieceFlags::AB_CHESS_QUEEN, PieceFlags::AB_CHESS_KING.with_flag(PieceFlags::CROWN), PieceFlags::AB_CHESS_BISHOP, PieceFlags::AB_CHESS_KNIGHT, PieceFlags::AB_CHESS_ROOK];
-
-    pub fn new_chess_custom(mut nb_player : usize, with_pawn : bool, relics : Relics) -> Self
-    {
-        //return Self::new_checker();
-        //nb_player = 4;
-        let margin = if nb_player > 2 { 3 } else { 0 };
-        let s = At::splat(8 + 2 * margin);
-        let mut board = BoardGameNotStarted::new_empty(s);
-        custom_assert!(board.integrity_is_ok());
+    pub fn new_default() -> Self { Self::from_config(&GameConfig::default()).unwrap() }
 
-        for (idx, p) in Self::CHESS_BACK_VALUE.into_iter().enumerate()
-        {
-            let i = idx as AtIntType + margin;
+    pub fn new_chess() -> Self { Self::from_config(&GameConfig::chess()).unwrap() }
 
-            if nb_player >= 1
-            {
-                board.piece_add_team_and_set_flags(at(i, s.y-1), Team::Black, p);
-                if with_pawn { board.piece_add_team_and_set_flags(at(i, s.y-2), Team::Black, PieceFlags::AB_CHESS_PAWN); }
-            }
 
-            if nb_player >= 2
-            {
-                if with_pawn { board.piece_add_team_and_set_flags(at(i, 1), Team::White, PieceFlags::AB_CHESS_PAWN); }
-                board.piece_add_team_and_set_flags(at(i, 0), Team::White, p);
-            }
+    pub const CHESS_BACK_VALUE : [PieceFlags; 8] = [PieceFlags::AB_CHESS_ROOK, PieceFlags::AB_CHESS_KNIGHT, PieceFlags::AB_CHESS_BISHOP, PieceFlags::AB_CHESS_QUEEN, PieceFlags::AB_CHESS_KING.with_flag(PieceFlags::CROWN), PieceFlags::AB_CHESS_BISHOP, PieceFlags::AB_CHESS_KNIGHT, PieceFlags::AB_CHESS_ROOK];
 
-            if nb_player >= 3
-            {
-                board.piece_add_team_and_set_flags(at(0, i), Team::Yellow, p);
-                if with_pawn { board.piece_add_team_and_set_flags(at(1, i), Team::Yellow, PieceFlags::AB_CHESS_PAWN); }
-            }
+    pub fn new_chess_custom(nb_player : usize, with_pawn : bool, relics : Relics) -> Self { Self::from_config(&GameConfig::chess_custom(nb_player, with_pawn, relics)).unwrap() }
 
-            if nb_player >= 4
-            {
-                board.piece_add_team_and_set_flags(at(s.x-1, i), Team::Green, p);
-                if with_pawn { board.piece_add_team_and_set_flags(at(s.x-2, i), Team::Green, PieceFlags::AB_CHESS_PAWN); }
-            }
-        }
-        for p in board.team_data.val.iter_mut()
-        {
-            p.relics = relics;
-        }
-        let mut b = BoardGame::new(board);
-        b
-    }
-
-    pub fn new_checker() -> Self { Self::new_checker_custom_size(at(10, 10), 4) }
-    pub fn new_checker_custom_size(s : At, nb_line_of_pawn : AtIntType) -> Self
-    {
-        let mut board = BoardGameNotStarted::new_empty(s);
-        for y in 0..nb_line_of_pawn
-        {
-            for _x in 0..s.x/2
-            {
-                let x = 2 * _x;
-
-                board.piece_add_team_and_set_flags(at(x + (y+1) % 2, s.y - 1 - y), Team::Black, PieceFlags::AB_DAME_PAWN);
-
-                board.piece_add_team_and_set_flags(at(x + y % 2, y), Team::White, PieceFlags::AB_DAME_PAWN);
-            }
-        }
-        let mut b = BoardGame::new(board);
-        b
-    }
+    pub fn new_checker() -> Self { Self::from_config(&GameConfig::checker()).unwrap() }
+    pub fn new_checker_custom_size(s : At, nb_line_of_pawn : AtIntType) -> Self { Self::from_config(&GameConfig::checker_custom_size(s, nb_line_of_pawn)).unwrap() }
 }
@@ -0,0 +1,300 @@
+//! Monte Carlo Tree Search (UCT), an alternative to the minimax search that don't need an evaluation function.
+//!
+//! Each iteration select a leaf of the tree with UCT, add one of its child, play a playout until the end of the game
+//! (or until `MctsConfig::max_playout_len` actions, scored by the share of alive piece value), and propagate the reward of each team to the root.
+//! It work for any board size and any number of team, and is less sensitive to the branching factor of the relics than alpha-beta.
+//!
+//! The search is limited by the time, the number of nodes (iterations) and the cancellation token of the `SearchBudget`. The depth is ignored.
+
+use super::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum MctsPlayout
+{
+    /// Uniformly random actions
+    Random,
+    /// The best scored action (captures, promotions...) of a few random actions
+    #[default]
+    Heuristic,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MctsConfig
+{
+    /// Exploration constant of UCT. Higher explore more, lower exploit more
+    pub exploration : Score,
+    pub playout : MctsPlayout,
+    /// Maximum number of actions of a playout
+    pub max_playout_len : usize,
+    pub seed : u64,
+}
+impl Default for MctsConfig { fn default() -> Self { Self { exploration: std::f64::consts::SQRT_2, playout: ___(), max_playout_len: 200, seed: 42 } } }
+impl MctsConfig
+{
+    /// Number of iterations when the budget have no time and no node limit
+    pub const DEFAULT_NB_ITERATION : usize = 10_000;
+    /// Number of random actions compared by the heuristic playout
+    pub const HEURISTIC_NB_SAMPLE : usize = 3;
+
+    pub fn with_exploration(mut self, exploration : Score) -> Self { self.exploration = exploration; self }
+    pub fn with_playout(mut self, playout : MctsPlayout) -> Self { self.playout = playout; self }
+    pub fn with_max_playout_len(mut self, max_playout_len : usize) -> Self { self.max_playout_len = max_playout_len; self }
+    pub fn with_seed(mut self, seed : u64) -> Self { self.seed = seed; self }
+}
+
+#[derive(Clone, Debug)]
+struct MctsNode
+{
+    state : BoardGameFixedTime,
+    parent : Option<usize>,
+    /// Action of the parent that lead to this node
+    action_id : Option<ActionID>,
+    /// Team that played the action
+    team : Team,
+    children : Vec<usize>,
+    /// The children are created in the order of `state.actions`
+    nb_expanded : usize,
+    nb_visit : usize,
+    /// Sum of the reward of `team`, between 0 and 1 for each visit
+    reward : Score,
+}
+impl MctsNode
+{
+    fn new(state : BoardGameFixedTime, parent : Option<usize>, action_id : Option<ActionID>, team : Team) -> Self
+    {
+        Self { state, parent, action_id, team, children: vec![], nb_expanded: 0, nb_visit: 0, reward: 0. }
+    }
+    fn is_fully_expanded(&self) -> bool { self.nb_expanded >= self.state.actions.len() }
+}
+
+/// MCTS that can be done in several steps, like `MinimaxIterativeSearch`
+#[derive(Clone, Debug)]
+pub struct MctsSearch
+{
+    pub budget : SearchBudget,
+    pub cancel : CancellationToken,
+    pub config : MctsConfig,
+
+    nodes : Vec<MctsNode>,
+    rng : Random,
+    start : f64,
+    nb_iteration : usize,
+    max_depth : usize,
+    done : bool,
+}
+impl MctsSearch
+{
+    /// The clock is only checked every `CLOCK_CHECK_NB_ITERATION` iterations
+    const CLOCK_CHECK_NB_ITERATION : usize = 16;
+
+    pub fn new(state : BoardGameFixedTime, budget : SearchBudget, cancel : CancellationToken, config : MctsConfig) -> Self
+    {
+        let done = state.actions.len() <= 1 || state.is_end_of_the_game();
+        let team = state.current_team;
+        Self { budget, cancel, config, nodes: vec![MctsNode::new(state, None, None, team)], rng: Random::new(config.seed), start: (budget.clock)(), nb_iteration: 0, max_depth: 0, done }
+    }
+
+    pub fn is_done(&self) -> bool { self.done }
+    pub fn nb_iteration(&self) -> usize { self.nb_iteration }
+
+    /// The most visited action of the root. The score is its average reward between 0 and 1
+    pub fn result(&self) -> MinimaxResult
+    {
+        let root = &self.nodes[0];
+        let best = root.children.iter().map(|c| &self.nodes[*c]).max_by_key(|c| c.nb_visit);
+        MinimaxResult
+        {
+            score: best.map(|c| c.reward / c.nb_visit.max(1) as Score).unwrap_or(0.),
+            action_id: best.and_then(|c| c.action_id).or(root.state.actions.first().map(|a| a.id)),
+            stat_nb_action_evaluated: self.nb_iteration,
+            stat_nb_depth_evaluated: self.max_depth,
+            ..___()
+        }
+    }
+
+    /// Search until the end
+    pub fn run(mut self) -> MinimaxResult
+    {
+        self.step(None);
+        self.result()
+    }
+
+    /// Search during `slice` second at most, and return true when the search is done
+    pub fn step(&mut self, slice : Option<f64>) -> bool
+    {
+        if self.done { return true; }
+        let step_start = (self.budget.clock)();
+        let nb_iteration_max = match (self.budget.nodes, self.budget.time) { (Some(n), _) => n, (None, Some(_)) => usize::MAX, (None, None) => MctsConfig::DEFAULT_NB_ITERATION };
+
+        loop
+        {
+            if self.nb_iteration >= nb_iteration_max || self.cancel.is_cancelled() { self.done = true; break; }
+            if self.nb_iteration.is_multiple_of(Self::CLOCK_CHECK_NB_ITERATION)
+            {
+                let now = (self.budget.clock)();
+                if self.budget.time.is_some_and(|t| now - self.start >= t) { self.done = true; break; }
+                if slice.is_some_and(|s| now - step_start >= s) { break; }
+            }
+            self.iteration();
+        }
+        self.done
+    }
+
+    fn iteration(&mut self)
+    {
+        self.nb_iteration += 1;
+
+        // selection
+        let mut idx = 0;
+        let mut depth = 0;
+        while self.nodes[idx].is_fully_expanded() && !self.nodes[idx].children.is_empty()
+        {
+            idx = self.select_child(idx);
+            depth += 1;
+        }
+
+        // expansion
+        let node = &self.nodes[idx];
+        if !node.is_fully_expanded() && !node.state.is_end_of_the_game()
+        {
+            let action = &node.state.actions[node.nb_expanded];
+            let child = MctsNode::new(node.state.execute_action(action, false), Some(idx), Some(action.id), node.state.current_team);
+            self.nodes[idx].nb_expanded += 1;
+            self.nodes.push(child);
+            let child_idx = self.nodes.len() - 1;
+            self.nodes[idx].children.push(child_idx);
+            idx = child_idx;
+            depth += 1;
+        }
+        self.max_depth = self.max_depth.max(depth);
+
+        // simulation
+        let reward = self.playout(self.nodes[idx].state.clone());
+
+        // backpropagation
+        let mut cur = Some(idx);
+        while let Some(i) = cur
+        {
+            let node = &mut self.nodes[i];
+            node.nb_visit += 1;
+            node.reward += reward[node.team as usize];
+            cur = node.parent;
+        }
+    }
+
+    /// UCT
+    fn select_child(&self, idx : usize) -> usize
+    {
+        let ln_visit = (self.nodes[idx].nb_visit.max(1) as Score).ln();
+        let uct = |c : &MctsNode| c.reward / c.nb_visit as Score + self.config.exploration * (ln_visit / c.nb_visit as Score).sqrt();
+        *self.nodes[idx].children.iter().max_by(|a, b| uct(&self.nodes[**a]).total_cmp(&uct(&self.nodes[**b]))).unwrap()
+    }
+
+    fn playout(&mut self, mut state : BoardGameFixedTime) -> TeamScores
+    {
+        for _ in 0..self.config.max_playout_len
+        {
+            if state.is_end_of_the_game() || state.actions.is_empty() { break; }
+            let idx = match self.config.playout
+            {
+                MctsPlayout::Random => self.rng.gen_range(0..state.actions.len()),
+                MctsPlayout::Heuristic => (0..MctsConfig::HEURISTIC_NB_SAMPLE).map(|_| self.rng.gen_range(0..state.actions.len()))
+                    .max_by(|a, b| state.actions[*a].score.total_cmp(&state.actions[*b].score)).unwrap(),
+            };
+            state = state.execute_action(&state.actions[idx], false);
+        }
+        state.mcts_reward()
+    }
+}
+
+impl BoardGameFixedTime
+{
+    /// Reward of each team between 0 and 1 at the end of a playout.
+    /// The winner get 1, a draw is shared, and an unfinished game is shared by alive piece value
+    fn mcts_reward(&self) -> TeamScores
+    {
+        let mut reward = TeamScores::default();
+        match self.end_game_result()
+        {
+            Some(BoardResult::WinnerIs(t)) => reward[t as usize] = 1.,
+            Some(BoardResult::Draw) =>
+            {
+                let present : Vec<Team> = Team::iter().filter(|t| self.team_data[*t].is_present).collect();
+                for t in present.iter() { reward[*t as usize] = 1. / present.len() as Score; }
+            },
+            None =>
+            {
+                let total : Score = Team::iter().filter(|t| self.team_data[*t].alive).map(|t| self.team_data[t].alive_piece_value as Score).sum();
+                for t in Team::iter().filter(|t| self.team_data[*t].alive)
+                {
+                    reward[t as usize] = self.team_data[t].alive_piece_value as Score / total.max(1.);
+                }
+            },
+        }
+        reward
+    }
+
+    pub fn mcts(&self, budget : SearchBudget, cancel : &CancellationToken, config : MctsConfig) -> MinimaxResult
+    {
+        MctsSearch::new(self.clone(), budget, cancel.clone(), config).run()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn mcts_capture_and_deterministic()
+    {
+        let b = BoardGame::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", Relics::ZERO).unwrap();
+        let r = b.mcts(SearchBudget::nodes(2000), &CancellationToken::new(), ___());
+        assert_eq!(b.action_id_to_san(r.action_id.unwrap()).unwrap(), "Rxd5");
+        assert_eq!(r.stat_nb_action_evaluated, 2000);
+        assert!(r.score > 0.5);
+
+        // same seed, same result
+        let config = MctsConfig::default().with_playout(MctsPlayout::Random).with_seed(7);
+        let a = b.mcts(SearchBudget::nodes(500), &CancellationToken::new(), config);
+        let c = b.mcts(SearchBudget::nodes(500), &CancellationToken::new(), config);
+        assert_eq!((a.action_id, a.score), (c.action_id, c.score));
+    }
+
+    #[test]
+    fn mcts_edge_cases()
+    {
+        // cancelled before the first iteration : still a valid action
+        let b = BoardGame::new_chess();
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let r = b.mcts(SearchBudget::nodes(100), &cancel, ___());
+        assert_eq!(r.stat_nb_action_evaluated, 0);
+        assert!(r.action_id.is_some_and(|id| b.action_id_is_valid(id)));
+
+        // a single action
+        let b = BoardGame::from_fen("4k3/8/8/8/8/8/r7/3rK3 w - - 0 1", Relics::ZERO).unwrap().with_legal_moves(true);
+        assert_eq!(b.actions.len(), 1);
+        assert_eq!(b.mcts(SearchBudget::nodes(50), &CancellationToken::new(), ___()).action_id, Some(b.actions[0].id));
+
+        // the end of the game : no action and no iteration
+        let mut b = BoardGame::from_fen("4k3/8/8/8/8/8/8/q3K3 b - - 0 1", Relics::ZERO).unwrap();
+        b.execute(b.san_to_action_id("Qxe1").unwrap());
+        assert!(b.is_end_of_the_game());
+        let r = b.mcts(SearchBudget::nodes(50), &CancellationToken::new(), ___());
+        assert_eq!((r.action_id, r.stat_nb_action_evaluated), (None, 0));
+    }
+
+    #[test]
+    fn mcts_any_board()
+    {
+        for b in [BoardGame::new_chess_custom(4, true, Relics::ZERO), BoardGame::new_checker(), BoardGame::new_chess_custom(2, true, Relics::ZERO.with_flag_add(Relic::MoveTwiceInATurn))]
+        {
+            let mut search = MctsSearch::new(b.current().clone(), SearchBudget::nodes(200), CancellationToken::new(), ___());
+            while !search.step(Some(0.001)) {}
+            let r = search.result();
+            assert!(r.action_id.is_some_and(|id| b.action_id_is_valid(id)));
+            assert_eq!(search.nb_iteration(), 200);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Search algorithms for more than 2 teams, selected with `SearchBudget::multiplayer`.
+//!
+//! - Paranoid : every other team is a single minimizer (`_minimax`). Alpha-beta and the transposition table can be used
+//! - Max-n : each team maximize its own score in a vector of scores. No pruning
+//! - Best-reply : the root team play, then only the best action of all the other teams is played, and the root team play again.
+//!   Alpha-beta can be used, and the root team can search deeper than with paranoid
+//!
+//! With 2 teams, best-reply is the same as paranoid.
+
+use super::*;
+
+/// Score of each team, seen by the team
+pub type TeamScores = [Score; Team::LENGHT];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum MultiplayerSearch
+{
+    #[default]
+    Paranoid,
+    MaxN,
+    BestReply,
+}
+impl MultiplayerSearch
+{
+    pub const ALL : [Self; 3] = [Self::Paranoid, Self::MaxN, Self::BestReply];
+    pub const NAMES : [&'static str; 3] = ["paranoid", "maxn", "bestreply"];
+
+    pub fn name(self) -> &'static str { Self::NAMES[self as usize] }
+    pub fn from_name(name : &str) -> Option<Self> { Self::NAMES.iter().position(|n| n.eq_ignore_ascii_case(name)).map(|i| Self::ALL[i]) }
+}
+impl Display for MultiplayerSearch { fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult { f.pad(self.name()) }}
+
+impl BoardGameFixedTime
+{
+    /// Search the root with the `MultiplayerSearch` of the budget
+    pub(crate) fn _search_root<E : Evaluator>(&self, depth : usize, search : &mut MinimaxSearch<E>) -> MinimaxResult
+    {
+        let t = self.current_team;
+        let mut r = match search.budget.multiplayer
+        {
+            MultiplayerSearch::MaxN => self._maxn(t, 0, depth, search),
+            MultiplayerSearch::BestReply if self.nb_team_alive > 2 => self._best_reply(t, 0, depth, Score::MIN, Score::MAX, search),
+            _ => self._minimax(t, 0, depth, Score::MIN, Score::MAX, search),
+        };
+        r.multiplayer = search.budget.multiplayer;
+        r
+    }
+
+    /// Each team maximize its own score. `t` is the root team, the `score` of the result is its score
+    fn _maxn<E : Evaluator>(&self, t : Team, mut current_depth : usize, maxi_depth : usize, search : &mut MinimaxSearch<E>) -> MinimaxResult
+    {
+        current_depth += 1;
+        if current_depth > maxi_depth || self.is_end_of_the_game() || self.actions.is_empty()
+        {
+            let mut r = MinimaxResult { stat_nb_action_evaluated: 1, ..___() };
+            for team in Team::iter().filter(|team| self.team_data[*team].is_present)
+            {
+                r.team_scores[team as usize] = self._eval_team_position(team, current_depth, maxi_depth, search.evaluator);
+            }
+            r.score = r.team_scores[t as usize];
+            return r;
+        }
+
+        let mut best = MinimaxResult { score: Score::MIN, action_id: Some(self.actions[0].id), ..___() };
+        if search.visit_node() { return best; }
+
+        let c = self.current_team as usize;
+        let mut best_score = Score::MIN;
+        for (action_id, next) in self.iter_next_state(false)
+        {
+            let cur = next._maxn(t, current_depth, maxi_depth, search);
+            best.update_stat(&cur);
+            if search.is_stopped() { break; }
+
+            if cur.team_scores[c] > best_score
+            {
+                best_score = cur.team_scores[c];
+                best.score = cur.score;
+                best.team_scores = cur.team_scores;
+                best.action_id = Some(action_id);
+            }
+        }
+        best
+    }
+
+    /// The turn of `team`, only used by the best-reply search
+    fn with_turn_of(&self, team : Team) -> Self
+    {
+        let mut s = self.clone();
+        s.current_team = team;
+        s.init_new_turn();
+        s.update_actions(false);
+        s.zobrist_update_state();
+        s
+    }
+
+    /// `t` maximize, then the best action of all the other teams minimize
+    fn _best_reply<E : Evaluator>(&self, t : Team, mut current_depth : usize, maxi_depth : usize, mut alpha : Score, mut beta : Score, search : &mut MinimaxSearch<E>) -> MinimaxResult
+    {
+        current_depth += 1;
+        let eval_maxi_depth = maxi_depth + search.budget.quiescence_depth;
+        if self.is_end_of_the_game() || self.actions.is_empty() { return self.eval_team_position(t, current_depth, eval_maxi_depth, search.evaluator); }
+        if current_depth > maxi_depth { return self._quiescence(t, current_depth, eval_maxi_depth, alpha, beta, search); }
+
+        let mut best = MinimaxResult { action_id: Some(self.actions[0].id), ..___() };
+        if search.visit_node() { return best; }
+
+        if self.current_team == t
+        {
+            best.score = Score::MIN;
+            for (action_id, next) in self.iter_next_state(false)
+            {
+                let cur = next._best_reply(t, current_depth, maxi_depth, alpha, beta, search);
+                best.update_stat(&cur);
+
+                if cur.score > best.score { best.score = cur.score; best.action_id = Some(action_id); }
+                if cur.score >= beta { break; }
+                alpha = alpha.max(cur.score);
+            }
+        }else
+        {
+            best.score = Score::MAX;
+            'opponent: for o in Team::iter().filter(|o| *o != t && self.team_data[*o].alive)
+            {
+                let state = if o == self.current_team { self.clone() } else { self.with_turn_of(o) };
+                for (action_id, next) in state.iter_next_state(false)
+                {
+                    // the root team play after any reply
+                    let next = if next.current_team != t && !next.is_end_of_the_game() { next.with_turn_of(t) } else { next };
+                    let cur = next._best_reply(t, current_depth, maxi_depth, alpha, beta, search);
+                    best.update_stat(&cur);
+
+                    if cur.score < best.score { best.score = cur.score; best.action_id = Some(action_id); }
+                    if cur.score <= alpha { break 'opponent; }
+                    beta = beta.min(cur.score);
+                }
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn search(b : &BoardGame, depth : usize, multiplayer : MultiplayerSearch) -> MinimaxResult
+    {
+        b.minimax_budget(SearchBudget::depth(depth).with_multiplayer(multiplayer), &CancellationToken::new())
+    }
+
+    #[test]
+    fn multiplayer_two_teams()
+    {
+        let mut b = BoardGame::new_chess();
+        for san in ["e4", "e5", "Nf3", "Nc6"] { b.execute(b.san_to_action_id(san).unwrap()); }
+
+        for depth in 1..=3
+        {
+            let paranoid = search(&b, depth, MultiplayerSearch::Paranoid);
+            let best_reply = search(&b, depth, MultiplayerSearch::BestReply);
+            assert_eq!(paranoid.score, best_reply.score);
+            assert_eq!(paranoid.action_id, best_reply.action_id);
+            assert_eq!(best_reply.multiplayer, MultiplayerSearch::BestReply);
+
+            let maxn = search(&b, depth, MultiplayerSearch::MaxN);
+            assert!(maxn.action_id.is_some_and(|id| b.action_id_is_valid(id)));
+            assert_eq!(maxn.score, maxn.team_scores[b.current_team as usize]);
+        }
+    }
+
+    #[test]
+    fn multiplayer_four_teams()
+    {
+        let mut b = BoardGame::new_chess_custom(4, true, Relics::ZERO);
+        let mut rng = Random::new(2);
+        for _ in 0..8
+        {
+            let id = b.actions[rng.gen_range(0..b.actions.len())].id;
+            b.execute(id);
+        }
+
+        for multiplayer in MultiplayerSearch::ALL
+        {
+            let r = search(&b, 2, multiplayer);
+            assert!(r.action_id.is_some_and(|id| b.action_id_is_valid(id)), "{}", multiplayer);
+            assert_eq!(r.stat_nb_depth_evaluated, 2);
+            assert!(r.stat_nb_action_evaluated > 0);
+            assert_eq!(MultiplayerSearch::from_name(multiplayer.name()), Some(multiplayer));
+        }
+
+        // every team is evaluated by max-n
+        let r = search(&b, 2, MultiplayerSearch::MaxN);
+        assert!(Team::iter().all(|t| r.team_scores[t as usize] != 0.));
+    }
+
+    #[test]
+    fn multiplayer_edge_cases()
+    {
+        assert_eq!(MultiplayerSearch::from_name("max_n2"), None);
+        assert_eq!(MultiplayerSearch::from_name(""), None);
+
+        // the game is over : no action to search
+        let mut b = BoardGame::from_fen("4k3/8/8/8/8/8/8/q3K3 b - - 0 1", Relics::ZERO).unwrap();
+        b.execute(b.san_to_action_id("Qxe1").unwrap());
+        for multiplayer in MultiplayerSearch::ALL
+        {
+            let r = search(&b, 2, multiplayer);
+            assert_eq!((r.action_id, r.stat_nb_action_evaluated, r.multiplayer), (None, 0, multiplayer));
+        }
+    }
+}
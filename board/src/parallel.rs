@@ -0,0 +1,162 @@
+//! Multi-threaded minimax search, by splitting the actions of the root between the threads (feature `parallel`).
+//!
+//! At each depth, the best action of the previous depth is searched first by the current thread to get a good alpha,
+//! then the other actions are shared between the threads, which share the best score (alpha) found so far.
+//! Each thread have its own transposition table.
+//! The score is the same as the single-threaded search, but the choosen action between 2 actions with the same score can depend on the scheduling of the threads.
+//!
+//! Measured with `examples/parallel_bench.rs` (4 players board, release build) on a machine with a single core,
+//! so it only show the cost of the threads. The speedup on several cores is not measured :
+//!
+//! | depth | threads | time    | actions evaluated | actions/s |
+//! |-------|---------|---------|-------------------|-----------|
+//! | 3     | 1       | 0.309s  | 5 729             | 18 558    |
+//! | 3     | 2       | 0.304s  | 5 900             | 19 389    |
+//! | 4     | 1       | 15.063s | 354 498           | 23 535    |
+//! | 4     | 2       | 17.559s | 408 549           | 23 267    |
+//! | 4     | 4       | 22.236s | 529 398           | 23 808    |
+//!
+//! The threads don't slow down the evaluation (same actions/s), but they evaluate 15% (2 threads) to 49% (4 threads) more actions,
+//! because an action searched in parallel don't benefit from the alpha of the actions searched at the same time.
+
+use std::thread;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use super::*;
+
+/// Result of one thread for one depth
+#[derive(Clone, Copy, Default, Debug)]
+struct ParallelRootResult
+{
+    score : Score,
+    /// Index in the ordered actions
+    idx : Option<usize>,
+    stat : MinimaxResult,
+    stopped : bool,
+}
+
+/// State shared by the threads for one depth
+struct ParallelRoot<'a>
+{
+    order : &'a [ActionID],
+    depth : usize,
+    budget : SearchBudget,
+    cancel : &'a CancellationToken,
+    /// Best score found so far (`f64` bits)
+    alpha : AtomicU64,
+    /// Next action to search
+    next_idx : AtomicUsize,
+}
+
+impl BoardGameFixedTime
+{
+    /// Same as `minimax_budget`, but the actions of the root are searched by `nb_thread` threads.
+    /// With 1 thread, or with another multiplayer search than paranoid, this is exactly `minimax_budget`
+    pub fn minimax_parallel(&self, budget : SearchBudget, cancel : &CancellationToken, nb_thread : usize) -> MinimaxResult
+    {
+        let paranoid = budget.multiplayer == MultiplayerSearch::Paranoid || self.nb_team_alive <= 2;
+        if nb_thread <= 1 || !paranoid || self.actions.len() <= 1 || self.is_end_of_the_game() { return self.minimax_budget(budget, cancel); }
+        let nb_thread = nb_thread.min(self.actions.len() - 1);
+
+        let start = (budget.clock)();
+        let mut tts : Vec<TranspositionTable> = (0..nb_thread).map(|_| ___()).collect();
+
+        let mut order : Vec<ActionID> = self.actions.iter().map(|a| a.id).collect();
+        let mut best = MinimaxResult { score: Score::MIN, action_id: Some(order[0]), ..___() };
+        let mut stat = MinimaxResult::default();
+
+        for depth in 1..=budget.max_depth
+        {
+            let elapsed = (budget.clock)() - start;
+            let nb_node = stat.stat_nb_action_evaluated;
+            let remaining = SearchBudget { time: budget.time.map(|t| t - elapsed), nodes: budget.nodes.map(|n| n.saturating_sub(nb_node) / nb_thread), ..budget };
+
+            let root = ParallelRoot { order: &order, depth, budget: remaining, cancel, alpha: AtomicU64::new(Score::MIN.to_bits()), next_idx: AtomicUsize::new(0) };
+
+            // the first action alone
+            let first = self.minimax_parallel_root(&root, &mut tts[0], 1);
+            stat.update_stat(&first.stat);
+            if first.stopped { break; }
+
+            let results : Vec<ParallelRootResult> = thread::scope(|s|
+            {
+                let root = &root;
+                let handles : Vec<_> = tts.iter_mut().map(|tt| s.spawn(move || self.minimax_parallel_root(root, tt, usize::MAX))).collect();
+                handles.into_iter().map(|h| h.join().unwrap()).collect()
+            });
+
+            for r in results.iter() { stat.update_stat(&r.stat); }
+            if results.iter().any(|r| r.stopped) { break; }
+
+            // the best score, then the first action
+            let r = results.iter().chain([&first]).filter(|r| r.idx.is_some())
+                .max_by(|a, b| a.score.partial_cmp(&b.score).unwrap().then(b.idx.cmp(&a.idx))).unwrap();
+            let idx = r.idx.unwrap();
+            best = MinimaxResult { score: r.score, action_id: Some(order[idx]), stat_nb_depth_evaluated: depth, ..___() };
+
+            // search the best action first at the next depth
+            order[..=idx].rotate_right(1);
+
+            if budget.time.is_some_and(|t| (budget.clock)() - start >= t) { break; }
+        }
+        MinimaxResult { score: best.score, action_id: best.action_id, stat_nb_depth_evaluated: best.stat_nb_depth_evaluated, multiplayer: budget.multiplayer, ..stat }
+    }
+
+    /// Search at most `nb_action` of the remaining actions of the root
+    fn minimax_parallel_root(&self, root : &ParallelRoot, tt : &mut TranspositionTable, nb_action : usize) -> ParallelRootResult
+    {
+        let t = self.current_team;
+        let mut search = MinimaxSearch::new(root.budget, Some(tt), Some(root.cancel));
+        let mut r = ParallelRootResult { score: Score::MIN, ..___() };
+
+        for _ in 0..nb_action
+        {
+            let idx = root.next_idx.fetch_add(1, Ordering::Relaxed);
+            if idx >= root.order.len() { break; }
+
+            let alpha = Score::from_bits(root.alpha.load(Ordering::Relaxed));
+            let next = self.execute_action(self.action_id_to_action(root.order[idx]).unwrap(), false);
+            // the root is at the depth 1
+            let cur = next._minimax(t, 1, root.depth, alpha, Score::MAX, &mut search);
+            r.stat.update_stat(&cur);
+            if search.is_stopped() { r.stopped = true; break; }
+
+            // otherwise the score is only an upper bound
+            if cur.score > alpha || alpha == Score::MIN
+            {
+                if r.idx.is_none() || cur.score > r.score { r.score = cur.score; r.idx = Some(idx); }
+                let _ = root.alpha.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |a| (cur.score > Score::from_bits(a)).then_some(cur.score.to_bits()));
+            }
+        }
+        r
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn parallel_same_result()
+    {
+        let mut b = BoardGame::new_chess();
+        for san in ["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6"] { b.execute(b.san_to_action_id(san).unwrap()); }
+
+        for depth in 1..=3
+        {
+            let plain = b.current()._minimax(b.current_team, 0, depth, Score::MIN, Score::MAX, &mut MinimaxSearch::unlimited());
+            for nb_thread in [1, 2, 3]
+            {
+                let r = b.minimax_parallel(SearchBudget::depth(depth), &CancellationToken::new(), nb_thread);
+                assert_eq!(plain.score, r.score, "depth {} with {} threads", depth, nb_thread);
+            }
+        }
+
+        // deterministic with one thread
+        let a = b.minimax_parallel(SearchBudget::depth(3), &CancellationToken::new(), 1);
+        let c = b.minimax_budget(SearchBudget::depth(3), &CancellationToken::new());
+        assert_eq!(a.action_id, c.action_id);
+        assert_eq!(a.stat_nb_action_evaluated, c.stat_nb_action_evaluated);
+    }
+}
@@ -0,0 +1,108 @@
+//! Perft : count the leaf positions of the action tree at a given depth, to test the actions generation.
+//!
+//! The actions are executed like `BoardGame::execute()` (the `Anticipation` relic remove the actions that lose the crown),
+//! so the counts of `BoardGame::new_chess()` and the FEN positions with `Anticipation` are the legal chess moves counts.
+//! An action that end the game is a leaf.
+
+use super::*;
+
+impl BoardGameFixedTime
+{
+    /// The actions of the root are not always filtered by `Anticipation` (position from a FEN...)
+    fn perft_root(&self) -> Self
+    {
+        let mut s = self.clone();
+        if !s.is_end_of_the_game() { s.update_actions(true); }
+        s
+    }
+
+    fn _perft(&self, depth : usize) -> u64
+    {
+        if depth == 0 || self.is_end_of_the_game() { return 1; }
+        if depth == 1 { return self.actions.len() as u64; }
+        self.iter_next_state(true).map(|(_, next)| next._perft(depth - 1)).sum()
+    }
+
+    /// Number of leaf positions `depth` actions later
+    pub fn perft(&self, depth : usize) -> u64 { self.perft_root()._perft(depth) }
+
+    /// `perft(depth - 1)` after each action
+    pub fn perft_divide(&self, depth : usize) -> Vec<(ActionID, u64)>
+    {
+        if depth == 0 || self.is_end_of_the_game() { return vec![]; }
+        self.perft_root().iter_next_state(true).map(|(id, next)| (id, next._perft(depth - 1))).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn chess(fen : &str) -> BoardGame { BoardGame::from_fen(fen, Relics::ZERO.with_flag_add(Relic::Anticipation)).unwrap() }
+
+    fn assert_perft(b : &BoardGameFixedTime, counts : &[u64])
+    {
+        for (depth, count) in counts.iter().enumerate()
+        {
+            assert_eq!(b.perft(depth + 1), *count, "depth {}", depth + 1);
+        }
+    }
+
+    #[test]
+    fn perft_chess()
+    {
+        assert_perft(&BoardGame::new_chess(), &[20, 400]);
+        // Kiwipete
+        assert_perft(&chess("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"), &[48]);
+        assert_perft(&chess("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"), &[14, 191, 2812]);
+        assert_perft(&chess("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"), &[6, 264]);
+        assert_perft(&chess("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8"), &[44]);
+        assert_perft(&chess("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10"), &[46]);
+    }
+
+    /// Deep enough to reach the castling through check, the en passant and the promotions interactions.
+    /// About 3 minutes in release, too slow for the debug build : `cargo test --release -p board -- --ignored perft`
+    #[test]
+    #[ignore]
+    fn perft_chess_deep()
+    {
+        assert_perft(&BoardGame::new_chess(), &[20, 400, 8902, 197281]);
+        // Kiwipete
+        assert_perft(&chess("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"), &[48, 2039, 97862]);
+        assert_perft(&chess("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"), &[6, 264, 9467]);
+        assert_perft(&chess("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8"), &[44, 1486, 62379]);
+        assert_perft(&chess("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10"), &[46, 2079, 89890]);
+    }
+
+    #[test]
+    fn perft_divide()
+    {
+        let b = BoardGame::new_chess();
+        let divide = b.perft_divide(2);
+        assert_eq!(divide.len(), 20);
+        assert!(divide.iter().all(|(_, n)| *n == 20));
+        assert_eq!(divide.iter().map(|(_, n)| n).sum::<u64>(), b.perft(2));
+    }
+
+    /// Recorded with this implementation
+    #[test]
+    fn perft_golden_variants()
+    {
+        assert_perft(&BoardGame::new_checker(), &[9, 81, 793]);
+        // the rooks and the knights can't go in the void corners
+        assert_perft(&BoardGame::new_chess_custom(4, true, Relics::ZERO), &[20, 399, 7960]);
+        // custom Betza abilities
+        assert_perft(&GameConfig::from_toml(include_str!("../config/fairy_pieces.toml")).unwrap().build().unwrap(), &[26, 676]);
+    }
+
+    /// Recorded with this implementation : `cargo test --release -p board -- --ignored perft`
+    #[test]
+    #[ignore]
+    fn perft_golden_variants_deep()
+    {
+        assert_perft(&BoardGame::new_checker(), &[9, 81, 793, 7654]);
+        assert_perft(&BoardGame::new_chess_custom(4, true, Relics::ZERO), &[20, 399, 7960, 158402]);
+        assert_perft(&GameConfig::from_toml(include_str!("../config/fairy_pieces.toml")).unwrap().build().unwrap(), &[26, 676, 20318]);
+    }
+}
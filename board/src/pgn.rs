@@ -0,0 +1,336 @@
+//! PGN game record of a 2 teams game (white and black).
+//!
+//! The Seven Tag Roster is always written, followed by :
+//! - `BoardSize` : `<width>x<height>`
+//! - `WhiteRelics` / `BlackRelics` : the name of the relics separated by `,`. The chess rules (`Anticipation`) if missing, as in a PGN from another software
+//! - `DrawRules` : the name of the draw rules separated by `,` (`DrawRules::NAMES`). The chess rules if missing with a FEN, none with a Chessito FEN
+//! - `SetUp` / `FEN` if the game don't start from the initial chess position,
+//!   or `ChessitoFEN` if the initial position can't be written in FEN (the relics tags are then ignored when reading)
+//!
+//! Additional tags, like the kind of players, can be given with `BoardGame::to_pgn_with_tags`.
+
+use super::*;
+
+/// Error when writing or reading a game in the PGN notation
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PgnError
+{
+    /// The game can't be written in PGN (more than 2 teams...)
+    UnsupportedGame(String),
+    InvalidTag(String),
+    Fen(FenError),
+    San { move_number : Turn, reason : SanError },
+    IllegalMove { move_number : Turn, san : String, reason : SanError },
+    /// A move was played after the end of the game
+    GameOver { move_number : Turn, san : String },
+}
+impl Display for PgnError
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult
+    {
+        match self
+        {
+            PgnError::UnsupportedGame(s) => write!(f, "unsupported game : {}", s),
+            PgnError::InvalidTag(s) => write!(f, "invalid tag \"{}\"", s),
+            PgnError::Fen(e) => write!(f, "invalid position : {}", e),
+            PgnError::San { move_number, reason } => write!(f, "move {} : {}", move_number, reason),
+            PgnError::IllegalMove { move_number, san, reason } => write!(f, "move {} \"{}\" : {}", move_number, san, reason),
+            PgnError::GameOver { move_number, san } => write!(f, "move {} \"{}\" : the game is already over", move_number, san),
+        }
+    }
+}
+impl std::error::Error for PgnError {}
+impl From<FenError> for PgnError { fn from(value: FenError) -> Self { PgnError::Fen(value) } }
+
+fn relics_to_pgn(relics : Relics) -> String
+{
+    Relic::iter().filter(|r| relics.flag_have(*r)).map(|r| format!("{:?}", r)).collect::<Vec<_>>().join(",")
+}
+fn relics_from_pgn(s : &str) -> Result<Relics, PgnError>
+{
+    let mut relics = Relics::ZERO;
+    for name in s.split(',').map(|e| e.trim()).filter(|e| !e.is_empty())
+    {
+        let r = Relic::iter().find(|r| format!("{:?}", r) == name).ok_or_else(|| PgnError::InvalidTag(s.to_owned()))?;
+        relics.flag_add(r);
+    }
+    Ok(relics)
+}
+
+fn parse_pgn_tag(line : &str) -> Result<(String, String), PgnError>
+{
+    let invalid = || PgnError::InvalidTag(line.to_owned());
+    let inside = line.strip_prefix('[').and_then(|e| e.strip_suffix(']')).ok_or_else(invalid)?;
+    let (name, value) = inside.split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let value = value.trim().strip_prefix('"').and_then(|e| e.strip_suffix('"')).ok_or_else(invalid)?;
+    Ok((name.to_owned(), value.replace("\\\"", "\"").replace("\\\\", "\\")))
+}
+
+/// Remove the comments, variations, NAGs, move numbers and result from the movetext
+fn pgn_movetext_sans(movetext : &str) -> Vec<String>
+{
+    let mut sans = vec![];
+    let mut token = String::new();
+    let mut variation_depth = 0;
+    let mut in_comment = false;
+    let mut in_line_comment = false;
+
+    let mut push_token = |token : &mut String|
+    {
+        let san = token.trim_start_matches(|c : char| c.is_ascii_digit()).trim_start_matches('.');
+        // move number alone ("12." or "12...") or result
+        let is_move_number = san.len() != token.len() && (san.is_empty() || token.contains('.'));
+        let san = if is_move_number { san } else { token.as_str() };
+
+        if !san.is_empty() && !san.starts_with('$') && !matches!(san, "1-0" | "0-1" | "1/2-1/2" | "*")
+        {
+            sans.push(san.to_owned());
+        }
+        token.clear();
+    };
+
+    for c in movetext.chars()
+    {
+        if in_line_comment { if c == '\n' { in_line_comment = false; } continue; }
+        if in_comment { if c == '}' { in_comment = false; } continue; }
+
+        match c
+        {
+            '{' => { push_token(&mut token); in_comment = true; },
+            ';' => { push_token(&mut token); in_line_comment = true; },
+            '(' => { push_token(&mut token); variation_depth += 1; },
+            ')' => { token.clear(); variation_depth -= 1; },
+            _ if variation_depth > 0 => {},
+            _ if c.is_whitespace() => push_token(&mut token),
+            _ => token.push(c),
+        }
+    }
+    push_token(&mut token);
+    sans
+}
+
+impl BoardGame
+{
+    fn pgn_move_number(b : &BoardGameFixedTime) -> Turn { (b.turn - 1) / 2 + 1 }
+
+    pub fn to_pgn(&self) -> Result<String, PgnError> { self.to_pgn_with_tags(&[]) }
+
+    /// The tags override the default one with the same name
+    pub fn to_pgn_with_tags(&self, tags : &[(&str, &str)]) -> Result<String, PgnError>
+    {
+        let start = self.start();
+        if let Some((t, _)) = start.iter_team_data().find(|(t, data)| data.is_present && !matches!(t, Team::White | Team::Black))
+        {
+            return Err(PgnError::UnsupportedGame(format!("team {} can't be written in PGN", t)));
+        }
+
+        let result = match self.current().end_game_result()
+        {
+            Some(BoardResult::WinnerIs(Team::White)) => "1-0",
+            Some(BoardResult::WinnerIs(Team::Black)) => "0-1",
+            Some(BoardResult::Draw) => "1/2-1/2",
+            _ => "*",
+        };
+
+        let mut all_tags : Vec<(String, String)> = [("Event", "?"), ("Site", "?"), ("Date", "????.??.??"), ("Round", "?"), ("White", "?"), ("Black", "?"), ("Result", result)]
+            .iter().map(|(n, v)| (n.to_string(), v.to_string())).collect();
+
+        all_tags.push(("BoardSize".to_owned(), format!("{}x{}", start.size().x, start.size().y)));
+        all_tags.push(("WhiteRelics".to_owned(), relics_to_pgn(start.team_data[Team::White].relics)));
+        all_tags.push(("BlackRelics".to_owned(), relics_to_pgn(start.team_data[Team::Black].relics)));
+        all_tags.push(("DrawRules".to_owned(), start.draw_rules.to_names()));
+        if start.legal_moves { all_tags.push(("LegalMoves".to_owned(), "1".to_owned())); }
+
+        match start.to_fen()
+        {
+            Ok(fen) => if fen != Self::FEN_CHESS
+            {
+                all_tags.push(("SetUp".to_owned(), "1".to_owned()));
+                all_tags.push(("FEN".to_owned(), fen));
+            },
+            Err(_) => all_tags.push(("ChessitoFEN".to_owned(), start.to_chessito_fen())),
+        }
+
+        for (name, value) in tags.iter()
+        {
+            match all_tags.iter_mut().find(|(n, _)| n == name)
+            {
+                Some(tag) => tag.1 = value.to_string(),
+                None => all_tags.push((name.to_string(), value.to_string())),
+            }
+        }
+
+        let mut pgn = String::new();
+        for (name, value) in all_tags.iter()
+        {
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\"")));
+        }
+        pgn.push('\n');
+
+        let mut tokens = vec![];
+        let mut last_team = None;
+        for (b, action_id) in self.played_positions().iter().zip(self.played_actions().iter())
+        {
+            let move_number = Self::pgn_move_number(b);
+            match (b.current_team, last_team)
+            {
+                (Team::White, Some(Team::White)) => {},
+                (Team::White, _) => tokens.push(format!("{}.", move_number)),
+                (Team::Black, None) => tokens.push(format!("{}...", move_number)),
+                _ => {},
+            }
+            tokens.push(b.action_id_to_san(*action_id).map_err(|reason| PgnError::San { move_number, reason })?);
+            last_team = Some(b.current_team);
+        }
+        tokens.push(result.to_owned());
+
+        let mut line_len = 0;
+        for (idx, token) in tokens.iter().enumerate()
+        {
+            if idx != 0
+            {
+                if line_len + 1 + token.len() > 80 { pgn.push('\n'); line_len = 0; }
+                else { pgn.push(' '); line_len += 1; }
+            }
+            pgn.push_str(token);
+            line_len += token.len();
+        }
+        pgn.push('\n');
+        Ok(pgn)
+    }
+
+    /// Replay the first game of the PGN. The relics are read from the `WhiteRelics` and `BlackRelics` tags (`Anticipation` if missing)
+    pub fn from_pgn(pgn : &str) -> Result<Self, PgnError>
+    {
+        let mut tags = vec![];
+        let mut movetext = String::new();
+
+        for line in pgn.lines()
+        {
+            let line = line.trim();
+            if line.starts_with('%') { continue; }
+            if line.starts_with('[') && movetext.trim().is_empty()
+            {
+                tags.push(parse_pgn_tag(line)?);
+            }else
+            {
+                movetext.push_str(line);
+                movetext.push('\n');
+            }
+        }
+
+        let tag = |name : &str| tags.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str());
+
+        let mut start = if let Some(fen) = tag("ChessitoFEN")
+        {
+            BoardGameFixedTime::from_chessito_fen(fen)?
+        }else
+        {
+            // the legal moves of the chess
+            let chess = Relics::ZERO.with_flag_add(Relic::Anticipation);
+            let mut relics = [Relics::ZERO; Team::LENGHT];
+            relics[Team::White as usize] = tag("WhiteRelics").map_or(Ok(chess), relics_from_pgn)?;
+            relics[Team::Black as usize] = tag("BlackRelics").map_or(Ok(chess), relics_from_pgn)?;
+            BoardGameFixedTime::from_fen_with_teams_relics(tag("FEN").unwrap_or(Self::FEN_CHESS), relics)?
+        };
+
+        if let Some(size) = tag("BoardSize")
+        {
+            if size != format!("{}x{}", start.size().x, start.size().y) { return Err(PgnError::InvalidTag(format!("BoardSize \"{}\"", size))); }
+        }
+
+        if let Some(rules) = tag("DrawRules")
+        {
+            start.draw_rules = DrawRules::from_names(rules).ok_or_else(|| PgnError::InvalidTag(format!("DrawRules \"{}\"", rules)))?;
+        }
+        match tag("LegalMoves")
+        {
+            Some("1") => start.set_legal_moves(true),
+            Some("0") | None => {},
+            Some(v) => return Err(PgnError::InvalidTag(format!("LegalMoves \"{}\"", v))),
+        }
+
+        let mut game = Self::new_from_fixed_time(start);
+        for san in pgn_movetext_sans(&movetext)
+        {
+            let move_number = Self::pgn_move_number(game.current());
+            if game.is_end_of_the_game() { return Err(PgnError::GameOver { move_number, san }); }
+
+            match game.san_to_action_id(&san)
+            {
+                Ok(action_id) => game.execute(action_id),
+                Err(reason) => return Err(PgnError::IllegalMove { move_number, san, reason }),
+            }
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn pgn_round_trip()
+    {
+        let mut b = BoardGame::new_chess();
+        for san in ["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"]
+        {
+            b.execute(b.san_to_action_id(san).unwrap());
+        }
+        assert!(b.is_end_of_the_game());
+
+        let pgn = b.to_pgn_with_tags(&[("White", "Cpu Hard"), ("WhiteKind", "Cpu")]).unwrap();
+        assert!(pgn.contains("[White \"Cpu Hard\"]"));
+        assert!(pgn.contains("[WhiteRelics \"Anticipation\"]"));
+        assert!(pgn.contains("[Result \"1-0\"]"));
+        assert!(pgn.contains("[DrawRules \"Stalemate,ThreefoldRepetition,FiftyMove,InsufficientMaterial\"]"));
+        assert!(pgn.ends_with("1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n"));
+
+        let replay = BoardGame::from_pgn(&pgn).unwrap();
+        assert_eq!(replay.played_actions(), b.played_actions());
+        assert_eq!(replay.end_game_result(), Some(BoardResult::WinnerIs(Team::White)));
+    }
+
+    #[test]
+    fn pgn_without_relics_tags()
+    {
+        // a PGN of another software is replayed with the chess rules : the knight of c3 is pinned, and the checkmate end the game
+        let b = BoardGame::from_pgn("1. d4 e6 2. e4 Bb4+ 3. Nc3 a6 4. Ne2").unwrap();
+        assert_eq!(b.played_actions().len(), 7);
+        assert_eq!(b.current().team_data[Team::Black].relics, Relics::ZERO.with_flag_add(Relic::Anticipation));
+        assert_eq!(BoardGame::from_pgn("1. f3 e5 2. g4 Qh4# 3. a3"), Err(PgnError::GameOver { move_number: 3, san: "a3".to_owned() }));
+    }
+
+    #[test]
+    fn pgn_replay_from_position_with_comments()
+    {
+        let pgn = "[FEN \"4k3/8/8/8/8/8/8/R3K2R b KQ - 0 1\"]\n\n1... Kd7 {comment} 2. O-O-O+ (2. Ra7+ Kc6) Kc6 $1 ; line comment\n3. Rd6+ *";
+        let b = BoardGame::from_pgn(pgn).unwrap();
+        assert_eq!(b.played_actions().len(), 4);
+
+        let pgn = b.to_pgn().unwrap();
+        assert!(pgn.contains("[SetUp \"1\"]"));
+        assert!(pgn.ends_with("1... Kd7 2. O-O-O+ Kc6 3. Rd6+ *\n"));
+
+        assert_eq!(BoardGame::from_pgn("1. e4 e5 2. Ke3"), Err(PgnError::IllegalMove { move_number: 2, san: "Ke3".to_owned(), reason: SanError::IllegalMove("Ke3".to_owned()) }));
+    }
+
+    #[test]
+    fn pgn_error()
+    {
+        assert_eq!(BoardGame::from_pgn("[White \"Cpu\"\n1. e4"), Err(PgnError::InvalidTag("[White \"Cpu\"".to_owned())));
+        assert_eq!(BoardGame::from_pgn("[WhiteRelics \"Wings\"]\n1. e4"), Err(PgnError::InvalidTag("Wings".to_owned())));
+        assert_eq!(BoardGame::from_pgn("[BoardSize \"10x10\"]\n1. e4"), Err(PgnError::InvalidTag("BoardSize \"10x10\"".to_owned())));
+        assert!(matches!(BoardGame::from_pgn("[FEN \"8/8 z\"]\n1. e4"), Err(PgnError::Fen(FenError::InvalidTeam(_)))));
+
+        // the knights of c3 and g1 can both go to e2
+        assert_eq!(BoardGame::from_pgn("1. Nc3 a6 2. e3 a5 3. Ne2"), Err(PgnError::IllegalMove { move_number: 3, san: "Ne2".to_owned(), reason: SanError::Ambiguous("Ne2".to_owned()) }));
+        assert_eq!(BoardGame::from_pgn("1. e4 e5 2. e5"), Err(PgnError::IllegalMove { move_number: 2, san: "e5".to_owned(), reason: SanError::IllegalMove("e5".to_owned()) }));
+        // without relic, the king must be captured
+        assert_eq!(BoardGame::from_pgn("[WhiteRelics \"\"]\n[BlackRelics \"\"]\n1. f3 e5 2. g4 Qh4 3. a3 Qxe1 4. a4"), Err(PgnError::GameOver { move_number: 4, san: "a4".to_owned() }));
+
+        assert!(matches!(BoardGame::new_chess_custom(4, true, Relics::ZERO).to_pgn(), Err(PgnError::UnsupportedGame(_))));
+    }
+}
@@ -0,0 +1,374 @@
+//! Standard Algebraic Notation (SAN) of the actions, computed from the actions of the current team.
+//!
+//! A piece with exactly the abilities of a chess piece use its usual letter (`K`, `Q`, `R`, `B`, `N`, nothing for the pawn).
+//! Any other piece use its Chessito FEN abilities between brackets, in the Chessito FEN order :
+//! `[NR]` for a knight + rook, `[PB]` for a pawn + bishop, `[x]` for a checkers pawn, `[X]` for a checkers king...
+//!
+//! ex : `e4`, `exd5`, `Nbd7`, `R1e2`, `Qh4xe1`, `e8=Q`, `O-O`, `O-O-O`, `[NR]c3`, `[x]b4xd6`, `Bb5+`, `Qxf7#`
+//!
+//! The duck placement of the `DuckButDifferent` relic is `@` followed by the tile, ex : `@d4`
+
+use super::*;
+
+/// Error when writing or reading an action in the Standard Algebraic Notation (SAN)
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SanError
+{
+    Empty,
+    InvalidSyntax(String),
+    /// The piece can't be written in SAN (no ability)
+    UnsupportedPiece(At),
+    /// No action of the current team match
+    IllegalMove(String),
+    /// Multiple actions of the current team match
+    Ambiguous(String),
+}
+impl Display for SanError
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult
+    {
+        match self
+        {
+            SanError::Empty => write!(f, "empty move"),
+            SanError::InvalidSyntax(s) => write!(f, "invalid syntax \"{}\"", s),
+            SanError::UnsupportedPiece(a) => { write!(f, "piece at ")?; display_at(*a, f)?; write!(f, " can't be written in SAN") },
+            SanError::IllegalMove(s) => write!(f, "illegal move \"{}\"", s),
+            SanError::Ambiguous(s) => write!(f, "ambiguous move \"{}\"", s),
+        }
+    }
+}
+impl std::error::Error for SanError {}
+
+impl PieceFlags
+{
+    /// Letter of the piece in SAN : empty for the pawn, `[NR]` for a knight + rook...
+    pub fn san_letter(self) -> Option<String>
+    {
+        match self.ability()
+        {
+            Self::AB_CHESS_PAWN => Some(String::new()),
+            Self::AB_CHESS_KNIGHT | Self::AB_CHESS_BISHOP | Self::AB_CHESS_ROOK | Self::AB_CHESS_QUEEN | Self::AB_CHESS_KING => self.fen_char(Team::White).map(|c| c.to_string()),
+            ability if ability.is_none_flag() => None,
+            ability => Some(format!("[{}]", Self::CHESSITO_FEN_ABILITY.iter().filter(|(f, _)| ability.have_flag(*f)).map(|(_, c)| *c).collect::<String>())),
+        }
+    }
+
+    /// Read the letter of a piece at the start of `s` (a pawn if there is none). Return the abilities and the rest of `s`
+    pub fn from_san_letter(s : &str) -> Option<(PieceFlags, &str)>
+    {
+        match s.chars().next()
+        {
+            Some('[') =>
+            {
+                let (abilities, rest) = s[1..].split_once(']')?;
+                let mut ability = Self::ZERO;
+                for c in abilities.chars()
+                {
+                    ability |= if c == 'Q' { Self::AB_CHESS_QUEEN } else { Self::CHESSITO_FEN_ABILITY.iter().find(|(_, l)| *l == c)?.0 };
+                }
+                if ability.is_none_flag() { return None; }
+                Some((ability, rest))
+            },
+            Some(c) if c.is_ascii_uppercase() => Some((Self::from_fen_char(c)?.1.ability(), &s[1..])),
+            _ => Some((Self::AB_CHESS_PAWN, s)),
+        }
+    }
+}
+
+impl Action
+{
+    pub fn is_castling(&self) -> bool { self.iter().filter(|e| e.is_swap()).count() >= 2 }
+}
+
+impl BoardGameFixedTime
+{
+    /// The action must be playable by the current team
+    pub fn action_id_to_san(&self, action_id : ActionID) -> Result<String, SanError>
+    {
+        let action = self.action_id_to_action(action_id).ok_or_else(|| SanError::IllegalMove(action_id.to_string()))?;
+
+        let mut san = match action_id
+        {
+            ActionID::Move(src, dest) => self.san_move(action, src, dest)?,
+            ActionID::Promote(src, dest, promotion) => 
+            {
+                let letter = promotion.san_letter().ok_or(SanError::UnsupportedPiece(src))?;
+                format!("{}={}", self.san_move(action, src, dest)?, letter)
+            },
+            ActionID::Duck(dest) => format!("@{}", at_to_string(dest)),
+        };
+        san.push_str(self.san_check_suffix(action));
+        Ok(san)
+    }
+
+    fn san_move(&self, action : &Action, src : At, dest : At) -> Result<String, SanError>
+    {
+        let piece = self[src].flags;
+        let mut san = piece.san_letter().ok_or(SanError::UnsupportedPiece(src))?;
+
+        if piece.is_also_chess_king() && action.is_castling()
+        {
+            // the king side is toward the higher tiles, whatever the axis of the rank (Chess960 castling can move the king by 1 or 0 tile)
+            return Ok(if dest.x + dest.y > src.x + src.y { "O-O" } else { "O-O-O" }.to_owned());
+        }
+
+        let is_capture = action.iter().any(|e| e.is_capture());
+        let file = |x : AtIntType| char::from_u32('a' as u32 + x as u32).unwrap();
+
+        // other pieces that could be written the same way without disambiguation
+        let others : Vec<At> = self.actions.iter().map(|a| (a.id.src(), a))
+            .filter(|(s, a)| *s != src && a.id.dest() == dest && self[*s].ability() == piece.ability() && a.iter().any(|e| e.is_capture()) == is_capture)
+            .map(|(s, _)| s).collect();
+
+        if piece.ability() == PieceFlags::AB_CHESS_PAWN && is_capture
+        {
+            // the file is always written, the rank is only needed when the pawns don't move vertically (4 players)
+            san.push(file(src.x));
+            if others.iter().any(|o| o.x == src.x) { san.push_str(&(src.y + 1).to_string()); }
+        }else if !others.is_empty()
+        {
+            if others.iter().all(|o| o.x != src.x)
+            {
+                san.push(file(src.x));
+            }else if others.iter().all(|o| o.y != src.y)
+            {
+                san.push_str(&(src.y + 1).to_string());
+            }else
+            {
+                san.push_str(&at_to_string(src));
+            }
+        }
+
+        if is_capture { san.push('x'); }
+        san.push_str(&at_to_string(dest));
+        Ok(san)
+    }
+
+    /// `#` if the current team win with this action, `+` if it can capture an enemy crown after it
+    fn san_check_suffix(&self, action : &Action) -> &'static str
+    {
+        let team = self.current_team;
+        let next = self.execute_action(action, true);
+
+        if next.is_end_of_the_game()
+        {
+            return if next.end_game_result() == Some(BoardResult::WinnerIs(team)) { "#" } else { "" };
+        }
+
+        let check = next.actions_for_team(team).iter().any(|a| a.iter().any(|e| match e
+        {
+            UnitAction::Capture(_, dest) => next[*dest].have_flag(PieceFlags::CROWN) && !next[*dest].is_also_team(team),
+            _ => false,
+        }));
+        if check { "+" } else { "" }
+    }
+
+    /// Accept `e4`, `exd5`, `Nbd7`, `R1e2`, `Qh4xe1`, `e8=Q`, `O-O`, `0-0-0`, `[NR]c3`, `@d4` (duck)... The `+`, `#`, `!` and `?` suffixes are ignored
+    pub fn san_to_action_id(&self, san : &str) -> Result<ActionID, SanError>
+    {
+        let body = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        if body.is_empty() { return Err(SanError::Empty); }
+
+        let candidates : Vec<ActionID> = match body
+        {
+            "O-O" | "0-0" | "O-O-O" | "0-0-0" =>
+            {
+                let king_side = body.len() == 3;
+                self.actions.iter().filter(|a| self[a.id.src()].is_also_chess_king() && a.is_castling() && (a.id.dest().x + a.id.dest().y > a.id.src().x + a.id.src().y) == king_side).map(|a| a.id).collect()
+            },
+            _ if body.starts_with('@') =>
+            {
+                let dest = at_from_str(&body[1..]).ok_or_else(|| SanError::InvalidSyntax(body.to_owned()))?;
+                self.actions.iter().map(|a| a.id).filter(|id| *id == ActionID::Duck(dest)).collect()
+            },
+            _ => self.san_move_candidates(body)?,
+        };
+
+        // a multi ability piece can have the same action multiple time
+        let mut unique : Vec<ActionID> = vec![];
+        for id in candidates { if !unique.contains(&id) { unique.push(id); } }
+
+        match unique.len()
+        {
+            0 => Err(SanError::IllegalMove(san.to_owned())),
+            1 => Ok(unique[0]),
+            _ => Err(SanError::Ambiguous(san.to_owned())),
+        }
+    }
+
+    fn san_move_candidates(&self, body : &str) -> Result<Vec<ActionID>, SanError>
+    {
+        let invalid = || SanError::InvalidSyntax(body.to_owned());
+
+        let (ability, rest) = PieceFlags::from_san_letter(body).ok_or_else(invalid)?;
+
+        let (rest, promotion) = match rest.split_once('=')
+        {
+            Some((rest, promotion)) => match PieceFlags::from_san_letter(promotion)
+            {
+                Some((p, "")) if p != PieceFlags::AB_CHESS_PAWN => (rest, Some(p)),
+                _ => return Err(invalid()),
+            },
+            None => (rest, None),
+        };
+
+        // the destination is the last file letter followed by the rank
+        let rank_idx = rest.trim_end_matches(|c : char| c.is_ascii_digit()).len();
+        let dest_idx = rank_idx.checked_sub(1).ok_or_else(invalid)?;
+        let dest = at_from_str(&rest[dest_idx..]).ok_or_else(invalid)?;
+
+        let mut prefix = &rest[..dest_idx];
+        let is_capture = prefix.ends_with('x');
+        if is_capture { prefix = &prefix[..prefix.len() - 1]; }
+
+        let mut file = None;
+        if let Some(c) = prefix.chars().next().filter(|c| c.is_ascii_lowercase())
+        {
+            file = Some(c as AtIntType - 'a' as AtIntType);
+            prefix = &prefix[1..];
+        }
+        let rank = if prefix.is_empty() { None } else { Some(prefix.parse::<AtIntType>().map_err(|_| invalid())? - 1) };
+
+        Ok(self.actions.iter().filter(|a|
+        {
+            let src = a.id.src();
+            a.id.dest() == dest &&
+            self[src].ability() == ability &&
+            !(self[src].is_also_chess_king() && a.is_castling()) &&
+            file.is_none_or(|x| x == src.x) &&
+            rank.is_none_or(|y| y == src.y) &&
+            a.iter().any(|e| e.is_capture()) == is_capture &&
+            a.id.promotion() == promotion
+        }).map(|a| a.id).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn san_round_trip()
+    {
+        let mut b = BoardGame::new_chess();
+        for san in ["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6", "O-O", "Bc5", "d3", "d6", "Bg5", "h6", "Bxf6", "Qxf6"]
+        {
+            let action_id = b.san_to_action_id(san).unwrap();
+            assert_eq!(b.action_id_to_san(action_id).unwrap(), san);
+            b.execute(action_id);
+        }
+
+        for action in b.actions.iter()
+        {
+            let san = b.action_id_to_san(action.id).unwrap();
+            assert_eq!(b.san_to_action_id(&san).unwrap(), action.id);
+        }
+
+        assert_eq!(b.console_input_from_str("Nbd2\n"), Ok(ActionID::Move(at(1, 0), at(3, 1))));
+        assert_eq!(b.console_input_from_str("B1D2\n"), Ok(ActionID::Move(at(1, 0), at(3, 1))));
+    }
+
+    #[test]
+    fn san_disambiguation_and_check()
+    {
+        let relics = Relics::ZERO.with_flag_add(Relic::Anticipation);
+
+        let b = BoardGame::from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1", relics).unwrap();
+        assert_eq!(b.action_id_to_san(ActionID::Move(at(0, 0), at(3, 0))).unwrap(), "Rad1");
+        assert_eq!(b.san_to_action_id("Rhd1").unwrap(), ActionID::Move(at(7, 0), at(3, 0)));
+        assert_eq!(b.san_to_action_id("Rd1"), Err(SanError::Ambiguous("Rd1".to_owned())));
+        assert_eq!(b.action_id_to_san(ActionID::Move(at(0, 0), at(0, 7))).unwrap(), "Ra8+");
+
+        let b = BoardGame::from_fen("R7/8/8/7k/8/8/4K3/R7 w - - 0 1", relics).unwrap();
+        assert_eq!(b.action_id_to_san(ActionID::Move(at(0, 0), at(0, 3))).unwrap(), "R1a4");
+        assert_eq!(b.san_to_action_id("R8a4").unwrap(), ActionID::Move(at(0, 7), at(0, 3)));
+
+        let b = BoardGame::from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", relics).unwrap();
+        assert_eq!(b.san_to_action_id("O-O-O").unwrap(), ActionID::Move(at(4, 0), at(2, 0)));
+        assert_eq!(b.action_id_to_san(ActionID::Move(at(4, 0), at(6, 0))).unwrap(), "O-O");
+        assert_eq!(b.san_to_action_id("Nf3"), Err(SanError::IllegalMove("Nf3".to_owned())));
+    }
+
+    #[test]
+    fn san_error()
+    {
+        let b = BoardGame::new_chess();
+        assert_eq!(b.san_to_action_id(" +"), Err(SanError::Empty));
+        for san in ["e", "xx", "Nf3=", "Zf3"] { assert_eq!(b.san_to_action_id(san), Err(SanError::InvalidSyntax(san.to_owned()))); }
+        // no capture, no castling and no queen move at the start, and outside of the board
+        for san in ["Nxf3", "exd3", "O-O", "O-O-O", "Qd3", "e5", "e9"] { assert_eq!(b.san_to_action_id(san), Err(SanError::IllegalMove(san.to_owned()))); }
+        assert_eq!(b.action_id_to_san(ActionID::Move(at(4, 1), at(4, 4))), Err(SanError::IllegalMove(ActionID::Move(at(4, 1), at(4, 4)).to_string())));
+
+        // the promotion must be written, and only for a pawn that promote
+        let b = BoardGame::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", Relics::ZERO).unwrap();
+        assert!(b.san_to_action_id("a8").is_err());
+        assert!(b.san_to_action_id("a8=Q").is_ok());
+        assert!(b.san_to_action_id("Ke2=Q").is_err());
+
+        // 2 pawns can capture on d5, 2 knights can go to d2
+        let b = BoardGame::from_fen("4k3/8/8/3p4/2P1P3/8/8/1N2KN2 w - - 0 1", Relics::ZERO).unwrap();
+        assert_eq!(b.san_to_action_id("xd5"), Err(SanError::Ambiguous("xd5".to_owned())));
+        assert_eq!(b.san_to_action_id("Nd2"), Err(SanError::Ambiguous("Nd2".to_owned())));
+        assert_eq!(b.san_to_action_id("Nbd2").unwrap(), ActionID::Move(at(1, 0), at(3, 1)));
+        assert_eq!(b.san_to_action_id("Ngd2"), Err(SanError::IllegalMove("Ngd2".to_owned())));
+    }
+
+    #[test]
+    fn san_multi_ability_piece()
+    {
+        let b = BoardGame::from_chessito_fen("8x8 4k3/8/8/8/8/8/8/{w:NR}3K3 w 1 0 - 1 1 0 w,b").unwrap();
+        assert_eq!(b.action_id_to_san(ActionID::Move(at(0, 0), at(2, 1))).unwrap(), "[NR]c2");
+        assert_eq!(b.action_id_to_san(ActionID::Move(at(0, 0), at(0, 7))).unwrap(), "[NR]a8+");
+        assert_eq!(b.san_to_action_id("[RN]c2").unwrap(), ActionID::Move(at(0, 0), at(2, 1)));
+        assert_eq!(b.san_to_action_id("Nc2"), Err(SanError::IllegalMove("Nc2".to_owned())));
+        assert_eq!(b.san_to_action_id("[NR"), Err(SanError::InvalidSyntax("[NR".to_owned())));
+
+        let b = BoardGame::new_checker();
+        assert_eq!(b.action_id_to_san(ActionID::Move(at(1, 3), at(0, 4))).unwrap(), "[x]a5");
+    }
+
+    /// Play random games and check that every action round-trips
+    #[test]
+    fn san_round_trip_reachable_position()
+    {
+        let mut rng = Random::new(7);
+
+        let setups =
+        [
+            BoardGame::new_chess_custom(2, true, Relics::ZERO.with_flag_add(Relic::Explosive).with_flag_add(Relic::Absorb)),
+            BoardGame::new_chess_custom(4, true, Relics::ZERO.with_flag_add(Relic::Absorb)),
+            BoardGame::new_checker(),
+        ];
+
+        for mut b in setups
+        {
+            for _ in 0..40
+            {
+                if b.is_end_of_the_game() { break; }
+                for action in b.actions.iter()
+                {
+                    let san = b.action_id_to_san(action.id).unwrap();
+                    assert_eq!(b.san_to_action_id(&san), Ok(action.id), "{}", san);
+                }
+                let action_id = b.actions[rng.gen_range(0..b.actions.len())].id;
+                b.execute(action_id);
+            }
+        }
+    }
+
+    #[test]
+    fn san_under_promotion()
+    {
+        let mut b = BoardGame::from_fen("1r6/P3k3/8/8/8/8/8/4K3 w - - 0 1", Relics::ZERO.with_flag_add(Relic::Anticipation)).unwrap();
+        assert_eq!(b.actions.iter().filter(|a| a.id.promotion().is_some()).count(), 8);
+        assert_eq!(b.action_id_to_san(ActionID::Promote(at(0, 6), at(0, 7), PieceFlags::AB_CHESS_QUEEN)).unwrap(), "a8=Q");
+        assert_eq!(b.action_id_to_san(ActionID::Promote(at(0, 6), at(1, 7), PieceFlags::AB_CHESS_KNIGHT)).unwrap(), "axb8=N");
+        assert_eq!(b.san_to_action_id("a8"), Err(SanError::IllegalMove("a8".to_owned())));
+
+        let action_id = b.san_to_action_id("axb8=R").unwrap();
+        assert_eq!(action_id, ActionID::Promote(at(0, 6), at(1, 7), PieceFlags::AB_CHESS_ROOK));
+        b.execute(action_id);
+        assert_eq!(b[at(1, 7)].ability(), PieceFlags::AB_CHESS_ROOK);
+    }
+}
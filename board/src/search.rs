@@ -0,0 +1,300 @@
+//! Limit the minimax search with a depth, a wall-clock time, a number of node, or a cancellation token.
+//!
+//! The search use iterative deepening : when a limit is reached in the middle of a depth,
+//! the best action of the deepest completed depth is returned.
+
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}, OnceLock};
+
+use super::*;
+
+/// Time in second since an arbitrary point. `Instant` is not available on every platform (wasm), so it can be replaced
+pub type SearchClock = fn() -> f64;
+
+/// Use `Instant`
+pub fn search_clock_default() -> f64
+{
+    static START : OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_secs_f64()
+}
+
+/// Shared flag to stop a search from another place (another thread, or the Undo button...)
+#[derive(Clone, Default, Debug)]
+pub struct CancellationToken(Arc<AtomicBool>);
+impl PartialEq for CancellationToken { fn eq(&self, other: &Self) -> bool { Arc::ptr_eq(&self.0, &other.0) }}
+impl CancellationToken
+{
+    pub fn new() -> Self { ___() }
+    pub fn cancel(&self) { self.0.store(true, Ordering::Relaxed); }
+    pub fn is_cancelled(&self) -> bool { self.0.load(Ordering::Relaxed) }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SearchBudget
+{
+    pub max_depth : usize,
+    /// Wall-clock time in second
+    pub time : Option<f64>,
+    /// Number of position searched
+    pub nodes : Option<usize>,
+    /// Maximum number of capture and promotion searched after `max_depth`. 0 to disable the quiescence search
+    pub quiescence_depth : usize,
+    /// Algorithm used for more than 2 teams
+    pub multiplayer : MultiplayerSearch,
+    pub clock : SearchClock,
+}
+impl Default for SearchBudget { fn default() -> Self { Self { max_depth: Self::MAX_DEPTH, time: None, nodes: None, quiescence_depth: Self::QUIESCENCE_DEPTH, multiplayer: ___(), clock: search_clock_default } } }
+impl SearchBudget
+{
+    pub const MAX_DEPTH : usize = 64;
+    pub const QUIESCENCE_DEPTH : usize = 6;
+
+    pub fn depth(max_depth : usize) -> Self { Self { max_depth, ..___() } }
+    pub fn time(second : f64) -> Self { Self { time: Some(second), ..___() } }
+    pub fn nodes(nodes : usize) -> Self { Self { nodes: Some(nodes), ..___() } }
+
+    pub fn with_max_depth(mut self, max_depth : usize) -> Self { self.max_depth = max_depth; self }
+    pub fn with_time(mut self, second : f64) -> Self { self.time = Some(second); self }
+    pub fn with_nodes(mut self, nodes : usize) -> Self { self.nodes = Some(nodes); self }
+    pub fn with_quiescence_depth(mut self, quiescence_depth : usize) -> Self { self.quiescence_depth = quiescence_depth; self }
+    pub fn with_multiplayer(mut self, multiplayer : MultiplayerSearch) -> Self { self.multiplayer = multiplayer; self }
+    pub fn with_clock(mut self, clock : SearchClock) -> Self { self.clock = clock; self }
+
+    /// Budget of each of the `nb` searches done one after the other, with the same total time and number of node
+    pub fn split(self, nb : usize) -> Self
+    {
+        let nb = nb.max(1);
+        Self { time: self.time.map(|t| t / nb as f64), nodes: self.nodes.map(|n| (n / nb).max(1)), ..self }
+    }
+}
+
+/// State shared by all the position of a search
+pub struct MinimaxSearch<'a, E : Evaluator = DefaultEvaluator>
+{
+    pub tt : Option<&'a mut TranspositionTable>,
+    pub budget : SearchBudget,
+    pub cancel : Option<&'a CancellationToken>,
+    pub evaluator : &'a E,
+    start : f64,
+    nb_node : usize,
+    stopped : bool,
+}
+impl<'a> MinimaxSearch<'a>
+{
+    pub fn new(budget : SearchBudget, tt : Option<&'a mut TranspositionTable>, cancel : Option<&'a CancellationToken>) -> Self
+    {
+        Self { tt, budget, cancel, evaluator: &DefaultEvaluator, start: (budget.clock)(), nb_node: 0, stopped: false }
+    }
+    /// No limit and no transposition table
+    pub fn unlimited() -> Self { Self::new(___(), None, None) }
+}
+impl<'a, E : Evaluator> MinimaxSearch<'a, E>
+{
+    /// The clock is only checked every `CLOCK_CHECK_NB_NODE` node
+    const CLOCK_CHECK_NB_NODE : usize = 256;
+
+    pub fn with_evaluator<E2 : Evaluator>(self, evaluator : &'a E2) -> MinimaxSearch<'a, E2>
+    {
+        MinimaxSearch { tt: self.tt, budget: self.budget, cancel: self.cancel, evaluator, start: self.start, nb_node: self.nb_node, stopped: self.stopped }
+    }
+
+    pub fn is_stopped(&self) -> bool { self.stopped }
+    pub fn nb_node(&self) -> usize { self.nb_node }
+
+    /// Count one more node, and return true if the search must stop
+    pub(crate) fn visit_node(&mut self) -> bool
+    {
+        if self.stopped { return true; }
+        self.nb_node += 1;
+
+        self.stopped = self.cancel.is_some_and(|c| c.is_cancelled())
+            || self.budget.nodes.is_some_and(|n| self.nb_node > n)
+            || (self.nb_node.is_multiple_of(Self::CLOCK_CHECK_NB_NODE) && self.is_time_over());
+        self.stopped
+    }
+
+    pub fn is_time_over(&self) -> bool { self.budget.time.is_some_and(|t| (self.budget.clock)() - self.start >= t) }
+}
+
+impl BoardGameFixedTime
+{
+    /// Search until one of the limit of the budget is reached, or until the search is cancelled.
+    /// Return the best action of the deepest completed depth, or the first action if no depth was completed
+    pub fn minimax_budget(&self, budget : SearchBudget, cancel : &CancellationToken) -> MinimaxResult
+    {
+        MinimaxIterativeSearch::new(self.clone(), budget, cancel.clone()).run()
+    }
+
+    /// Same as `minimax_budget`, with another evaluation of the positions
+    pub fn minimax_evaluator<E : Evaluator>(&self, budget : SearchBudget, cancel : &CancellationToken, evaluator : &E) -> MinimaxResult
+    {
+        MinimaxIterativeSearch::new(self.clone(), budget, cancel.clone()).with_evaluator(evaluator.clone()).run()
+    }
+}
+
+/// Iterative deepening search (depth 1, 2, ... `max_depth`) that can be done in several steps, for cooperative time-slicing when there is no thread.
+/// The best actions of the previous depth are searched first thanks to the transposition table
+#[derive(Clone, Debug)]
+pub struct MinimaxIterativeSearch<E : Evaluator = DefaultEvaluator>
+{
+    pub state : BoardGameFixedTime,
+    pub budget : SearchBudget,
+    pub cancel : CancellationToken,
+    pub evaluator : E,
+
+    tt : TranspositionTable,
+    start : f64,
+    nb_node : usize,
+    /// Next depth to search
+    depth : usize,
+    best : MinimaxResult,
+    /// Include the unfinished depth
+    stat : MinimaxResult,
+    done : bool,
+}
+impl MinimaxIterativeSearch
+{
+    pub fn new(state : BoardGameFixedTime, budget : SearchBudget, cancel : CancellationToken) -> Self
+    {
+        let best = MinimaxResult { score: Score::MIN, action_id: state.actions.first().map(|a| a.id), multiplayer: budget.multiplayer, ..___() };
+        // nothing to search
+        let done = state.actions.len() <= 1 || state.is_end_of_the_game();
+        Self { state, budget, cancel, evaluator: DefaultEvaluator, tt: ___(), start: (budget.clock)(), nb_node: 0, depth: 1, best, stat: ___(), done }
+    }
+}
+impl<E : Evaluator> MinimaxIterativeSearch<E>
+{
+    pub fn with_evaluator<E2 : Evaluator>(self, evaluator : E2) -> MinimaxIterativeSearch<E2>
+    {
+        let Self { state, budget, cancel, evaluator: _, tt, start, nb_node, depth, best, stat, done } = self;
+        MinimaxIterativeSearch { state, budget, cancel, evaluator, tt, start, nb_node, depth, best, stat, done }
+    }
+
+    pub fn is_done(&self) -> bool { self.done }
+
+    /// Best action of the deepest completed depth
+    pub fn result(&self) -> MinimaxResult
+    {
+        MinimaxResult { score: self.best.score, action_id: self.best.action_id, stat_nb_depth_evaluated: self.best.stat_nb_depth_evaluated, multiplayer: self.best.multiplayer, team_scores: self.best.team_scores, ..self.stat }
+    }
+
+    /// Search until the end
+    pub fn run(mut self) -> MinimaxResult
+    {
+        self.step(None);
+        self.result()
+    }
+
+    /// Search during `slice` second at most, and return true when the search is done.
+    /// An unfinished depth is searched again at the next step, faster thanks to the transposition table
+    pub fn step(&mut self, slice : Option<f64>) -> bool
+    {
+        if self.done { return true; }
+
+        let elapsed = (self.budget.clock)() - self.start;
+        let time = match (self.budget.time.map(|t| t - elapsed), slice)
+        {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        let budget = SearchBudget { time, nodes: self.budget.nodes.map(|n| n.saturating_sub(self.nb_node)), ..self.budget };
+
+        let mut search = MinimaxSearch::new(budget, Some(&mut self.tt), Some(&self.cancel)).with_evaluator(&self.evaluator);
+        while self.depth <= self.budget.max_depth
+        {
+            let cur = self.state._search_root(self.depth, &mut search);
+            self.stat.update_stat(&cur);
+            if search.is_stopped() { break; }
+
+            self.best = MinimaxResult { stat_nb_depth_evaluated: self.depth, ..cur };
+            self.depth += 1;
+            if search.is_time_over() { break; }
+        }
+        self.nb_node += search.nb_node();
+
+        self.done = self.depth > self.budget.max_depth
+            || self.cancel.is_cancelled()
+            || self.budget.nodes.is_some_and(|n| self.nb_node >= n)
+            || self.budget.time.is_some_and(|t| (self.budget.clock)() - self.start >= t);
+        self.done
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn italian() -> BoardGame
+    {
+        let mut b = BoardGame::new_chess();
+        for san in ["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6"] { b.execute(b.san_to_action_id(san).unwrap()); }
+        b
+    }
+
+    #[test]
+    fn search_node_budget()
+    {
+        let b = italian();
+        let r = b.minimax_budget(SearchBudget::nodes(2000), &CancellationToken::new());
+        assert!(r.action_id.is_some_and(|id| b.action_id_is_valid(id)));
+        assert!(r.stat_nb_depth_evaluated >= 1 && r.stat_nb_depth_evaluated < SearchBudget::MAX_DEPTH);
+
+        // same result as the depth of the deepest completed iteration
+        let d = b.minimax_budget(SearchBudget::depth(r.stat_nb_depth_evaluated), &CancellationToken::new());
+        assert_eq!(r.action_id, d.action_id);
+        assert_eq!(r.score, d.score);
+    }
+
+    #[test]
+    fn search_time_budget_and_cancel()
+    {
+        let b = italian();
+        let start = Instant::now();
+        let r = b.minimax_budget(SearchBudget::time(0.2), &CancellationToken::new());
+        assert!(start.elapsed().as_secs_f64() < 2.);
+        assert!(r.action_id.is_some_and(|id| b.action_id_is_valid(id)));
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let r = b.minimax_budget(SearchBudget::depth(5), &cancel);
+        assert_eq!(r.stat_nb_depth_evaluated, 0);
+        assert!(r.action_id.is_some_and(|id| b.action_id_is_valid(id)));
+    }
+
+    #[test]
+    fn search_in_several_steps()
+    {
+        let b = italian();
+        let mut search = MinimaxIterativeSearch::new(b.current().clone(), SearchBudget::depth(3), CancellationToken::new());
+        let mut nb_step = 0;
+        while !search.step(Some(0.001)) { nb_step += 1; }
+        assert!(nb_step > 0);
+
+        let r = b.minimax_custom(3);
+        assert_eq!(search.result().action_id, r.action_id);
+        assert_eq!(search.result().stat_nb_depth_evaluated, 3);
+    }
+
+    #[test]
+    fn quiescence_dont_hang_material()
+    {
+        // the knight e3 fork the queen and the bishop : it must be captured
+        let fork = BoardGame::from_fen("rn2kb1r/2pb2pp/pp1p2q1/2N1ppN1/P2P2P1/4nP2/1PP1P2P/R1BQKB1R w K - 4 14", Relics::ZERO).unwrap();
+        // the pawn e4 is free
+        let free_pawn = BoardGame::from_fen("r1bqkbr1/pp1pppp1/n4n1p/2p5/4P3/N5PP/PPPP1P2/R1BQKBNR b Kq - 0 6", Relics::ZERO).unwrap();
+
+        for (b, san, depths_without_quiescence) in [(fork, "Bxe3", [3, 4]), (free_pawn, "Nxe4", [2, 4])]
+        {
+            let best = |budget : SearchBudget| b.action_id_to_san(b.minimax_budget(budget, &CancellationToken::new()).action_id.unwrap()).unwrap();
+            for depth in 2..=4
+            {
+                assert_eq!(best(SearchBudget::depth(depth)), san, "depth {}", depth);
+            }
+            // the horizon effect without the quiescence search
+            for depth in depths_without_quiescence
+            {
+                assert_ne!(best(SearchBudget::depth(depth).with_quiescence_depth(0)), san, "depth {}", depth);
+            }
+        }
+    }
+}
@@ -0,0 +1,829 @@
+//! Endgame tablebase : the exact result of every position with few pieces, generated by retrograde analysis.
+//!
+//! A table is generated for a board size and a material (the abilities of the pieces of the white and the black team, up to 4 pieces).
+//! The actions are the ones of the engine, so any ability and board size work, but only without relic.
+//! The rules that change the actions (`TablebaseRules`) are saved with the table, and a table is only used for a game with the same rules.
+//! A position don't know the energy left in the turn, so the tables are only used with 1 action per turn (`energy_per_turn`).
+//!
+//! The positions are resolved from the end of the game : a position is won in `d` plies if an action lead to a position lost in `d-1` plies,
+//! and lost in `d` plies if every action lead to a position won in at most `d-1` plies. The remaining positions are a draw.
+//! The captures and promotions lead to other tables, generated before.
+//! The actions of every position are kept in memory during the generation, so a table is limited to `Tablebase::MAX_POSITION` positions.
+//!
+//! The distance to mate (DTM) is the number of plies before the capture of the crown, or before the team without action lose.
+//! Each table is saved in a `.dtm` file of 1 byte per position, after a header with the board size, the rules and the material.
+//! The pieces are considered already moved (no castling, no en passant), except the pawns on their start tile.
+
+use std::{cmp::Reverse, collections::HashMap};
+
+use super::*;
+
+/// Value of a position for the team to play
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TablebaseValue
+{
+    /// Number of plies before the enemy lose
+    Win(u8),
+    /// Number of plies before the team lose
+    Loss(u8),
+    Draw,
+}
+impl TablebaseValue
+{
+    fn from_dtm(dtm : u8) -> Option<Self>
+    {
+        match dtm
+        {
+            Tablebase::INVALID => None,
+            Tablebase::DRAW => Some(Self::Draw),
+            d if d % 2 == 1 => Some(Self::Win(d)),
+            d => Some(Self::Loss(d)),
+        }
+    }
+
+    /// Value of the previous position for the team that played
+    fn before(self) -> Self
+    {
+        match self
+        {
+            Self::Win(d) => Self::Loss(d + 1),
+            Self::Loss(d) => Self::Win(d + 1),
+            Self::Draw => Self::Draw,
+        }
+    }
+
+    /// Same scale as the victory and defeat of the minimax search : the faster win is the best
+    pub fn score(self) -> Score
+    {
+        match self
+        {
+            Self::Win(d) => (Tablebase::MAX_DTM + 1 - d) as Score * 10E64,
+            Self::Loss(d) => (Tablebase::MAX_DTM + 1 - d) as Score * -10E64,
+            Self::Draw => 0.,
+        }
+    }
+
+    fn rank(self) -> i32
+    {
+        match self
+        {
+            Self::Win(d) => 1000 - d as i32,
+            Self::Loss(d) => -1000 + d as i32,
+            Self::Draw => 0,
+        }
+    }
+}
+impl Display for TablebaseValue
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult
+    {
+        match self
+        {
+            Self::Win(d) => write!(f, "win in {} plies", d),
+            Self::Loss(d) => write!(f, "loss in {} plies", d),
+            Self::Draw => write!(f, "draw"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TablebaseError
+{
+    Io(String, String),
+    InvalidFile(String),
+    /// The material and the board size have more than `Tablebase::MAX_POSITION` positions
+    TooLarge(String, usize),
+}
+impl Display for TablebaseError
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult
+    {
+        match self
+        {
+            TablebaseError::Io(path, e) => write!(f, "can't access {} : {}", path, e),
+            TablebaseError::InvalidFile(s) => write!(f, "invalid tablebase file : {}", s),
+            TablebaseError::TooLarge(table, nb_position) => write!(f, "{} have {} positions, the limit is {}", table, nb_position, Tablebase::MAX_POSITION),
+        }
+    }
+}
+impl std::error::Error for TablebaseError {}
+
+/// The pieces of a table, white first, and sorted by value
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct TablebaseMaterial(Vec<(Team, PieceFlags)>);
+impl TablebaseMaterial
+{
+    pub const MAX_PIECES : usize = 4;
+    /// The pin flags are ignored
+    const FLAGS_MASK : PieceFlags = PieceFlags(PieceFlags::ABILITY_MASK.0 | PieceFlags::CROWN.0);
+
+    fn piece_order(t : Team, f : PieceFlags) -> (u8, Reverse<PieceValue>, PieceFlagsType) { (t as u8, Reverse(f.ai_value() as PieceValue), f.0) }
+
+    /// None if there is more than 4 pieces, a team without piece, or a team other than white and black
+    pub fn new(mut pieces : Vec<(Team, PieceFlags)>) -> Option<Self>
+    {
+        if pieces.len() > Self::MAX_PIECES || pieces.iter().any(|(t, _)| !matches!(t, Team::White | Team::Black)) { return None; }
+        if [Team::White, Team::Black].iter().any(|team| !pieces.iter().any(|(t, _)| t == team)) { return None; }
+
+        for (_, f) in pieces.iter_mut() { *f &= Self::FLAGS_MASK; }
+        pieces.sort_by_key(|(t, f)| Self::piece_order(*t, *f));
+        Some(Self(pieces))
+    }
+
+    /// The chess pieces of each team separated by `v` : `KQvK`, `KPvKR`...
+    pub fn from_name(name : &str) -> Option<Self>
+    {
+        let (white, black) = name.split_once('v')?;
+        let mut pieces = vec![];
+        for (t, s) in [(Team::White, white), (Team::Black, black)]
+        {
+            for c in s.chars() { pieces.push((t, PieceFlags::from_fen_char(c.to_ascii_uppercase())?.1)); }
+        }
+        Self::new(pieces)
+    }
+
+    pub fn pieces(&self) -> &[(Team, PieceFlags)] { &self.0 }
+    pub fn len(&self) -> usize { self.0.len() }
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+}
+impl Display for TablebaseMaterial
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult
+    {
+        for (i, team) in [Team::White, Team::Black].into_iter().enumerate()
+        {
+            if i != 0 { write!(f, "v")?; }
+            for (_, flags) in self.0.iter().filter(|(t, _)| *t == team)
+            {
+                match flags.fen_char(Team::White)
+                {
+                    Some(c) => write!(f, "{}", c)?,
+                    None => write!(f, "[{}]", flags.0)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The rules of the game that change the actions, besides the board size and the draw rules
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TablebaseRules
+{
+    pub legal_moves : bool,
+    /// The void tiles of a non rectangular board
+    pub void_tiles : Vec<At>,
+    pub chess_promotion : Vec<PieceFlags>,
+    /// The movements of the `PieceFlags::AB_CUSTOM` abilities, their names are ignored
+    pub custom_abilities : Vec<PieceMoves>,
+}
+impl Default for TablebaseRules
+{
+    fn default() -> Self { Self::of(&___()) }
+}
+impl TablebaseRules
+{
+    /// The rules of a game
+    pub fn of(data : &BoardGameNotStarted) -> Self
+    {
+        Self { legal_moves: data.legal_moves, void_tiles: data.iter_idx().filter(|a| data.is_void(*a)).collect(), chess_promotion: data.chess_promotion.to_vec(),
+            custom_abilities: data.custom_abilities.iter().map(|a| a.moves.clone()).collect() }
+    }
+
+    /// Set the rules on an empty board
+    fn apply(&self, data : &mut BoardGameNotStarted)
+    {
+        data.legal_moves = self.legal_moves;
+        for a in self.void_tiles.iter() { data.set_void(*a, true); }
+        data.chess_promotion = SmallVec::from_slice(&self.chess_promotion);
+        data.custom_abilities = Arc::new(self.custom_abilities.iter().map(|m| CustomAbility { name: m.to_string(), moves: m.clone() }).collect());
+    }
+
+    fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut b = vec![self.legal_moves as u8];
+        b.extend((self.void_tiles.len() as u16).to_be_bytes());
+        for a in self.void_tiles.iter() { b.extend([a.x as u8, a.y as u8]); }
+        b.push(self.chess_promotion.len() as u8);
+        for f in self.chess_promotion.iter() { b.extend(f.0.to_be_bytes()); }
+        b.push(self.custom_abilities.len() as u8);
+        for m in self.custom_abilities.iter()
+        {
+            let betza = m.to_string();
+            b.push(betza.len() as u8);
+            b.extend(betza.as_bytes());
+        }
+        b
+    }
+
+    fn read(r : &mut TablebaseReader) -> Result<Self, TablebaseError>
+    {
+        let legal_moves = r.u8()? != 0;
+        let nb_void = r.take(2)?;
+        let void_tiles = r.take(2 * u16::from_be_bytes([nb_void[0], nb_void[1]]) as usize)?.chunks_exact(2).map(|a| at(a[0] as AtIntType, a[1] as AtIntType)).collect();
+        let nb_promotion = r.u8()? as usize;
+        let chess_promotion = r.take(2 * nb_promotion)?.chunks_exact(2).map(|f| PieceFlags(PieceFlagsType::from_be_bytes([f[0], f[1]]))).collect();
+        let mut custom_abilities = vec![];
+        for _ in 0..r.u8()?
+        {
+            let len = r.u8()? as usize;
+            let betza = std::str::from_utf8(r.take(len)?).ok().and_then(|b| PieceMoves::from_betza(b).ok());
+            custom_abilities.push(betza.ok_or_else(|| TablebaseError::InvalidFile("invalid custom ability".to_owned()))?);
+        }
+        Ok(Self { legal_moves, void_tiles, chess_promotion, custom_abilities })
+    }
+
+    /// Empty for the default rules, otherwise a hash (FNV-1a) of the rules, to save the tables of different rules in the same directory
+    fn file_suffix(&self) -> String
+    {
+        if *self == Self::default() { return String::new(); }
+        let hash = self.to_bytes().iter().fold(0x811C_9DC5u32, |h, b| (h ^ *b as u32).wrapping_mul(0x0100_0193));
+        format!("_{:08x}", hash)
+    }
+}
+
+/// Read the header of a table file
+struct TablebaseReader<'a>
+{
+    bytes : &'a [u8],
+    pos : usize,
+}
+impl<'a> TablebaseReader<'a>
+{
+    fn take(&mut self, len : usize) -> Result<&'a [u8], TablebaseError>
+    {
+        let b = self.bytes.get(self.pos..self.pos + len).ok_or_else(|| TablebaseError::InvalidFile("truncated header".to_owned()))?;
+        self.pos += len;
+        Ok(b)
+    }
+    fn u8(&mut self) -> Result<u8, TablebaseError> { Ok(self.take(1)?[0]) }
+}
+
+/// Action of a position during the generation
+#[derive(Clone, Copy, Debug)]
+enum TablebaseChild
+{
+    /// Position of the same table
+    Same(u32),
+    /// Position of another table, and the index of the table
+    Sub(u16, u32),
+    /// Already known DTM
+    Known(u8),
+}
+
+/// The DTM of every position of a material, for the team to play
+#[derive(Clone, PartialEq, Debug)]
+pub struct Tablebase
+{
+    pub material : TablebaseMaterial,
+    pub size : At,
+    pub draw_rules : DrawRules,
+    pub rules : TablebaseRules,
+    dtm : Vec<u8>,
+}
+impl Tablebase
+{
+    pub const MAX_DTM : u8 = 253;
+    /// Up to 4 pieces on 6x5 and 3 pieces on 10x10 (about 1 GB for the actions of the positions during the generation)
+    pub const MAX_POSITION : usize = 1 << 21;
+    const INVALID : u8 = 254;
+    const DRAW : u8 = 255;
+
+    const MAGIC : &'static [u8; 4] = b"TBDM";
+    const VERSION : u8 = 2;
+
+    fn nb_tile(size : At) -> usize { (size.x * size.y) as usize }
+    fn nb_position(size : At, nb_piece : usize) -> usize { Self::nb_tile(size).pow(nb_piece as u32) * 2 }
+
+    /// The tile of each piece, then the team to play
+    fn index(size : At, tiles : &[At], team : Team) -> usize
+    {
+        let nb_tile = Self::nb_tile(size);
+        tiles.iter().fold(0, |idx, a| idx * nb_tile + (a.y * size.x + a.x) as usize) * 2 + if team == Team::White { 0 } else { 1 }
+    }
+    fn decode(size : At, nb_piece : usize, mut idx : usize) -> (Vec<At>, Team)
+    {
+        let nb_tile = Self::nb_tile(size);
+        let team = if idx.is_multiple_of(2) { Team::White } else { Team::Black };
+        idx /= 2;
+        let mut tiles = vec![At::ZERO; nb_piece];
+        for tile in tiles.iter_mut().rev()
+        {
+            let i = (idx % nb_tile) as AtIntType;
+            *tile = at(i % size.x, i / size.x);
+            idx /= nb_tile;
+        }
+        (tiles, team)
+    }
+
+    pub fn nb_position_total(&self) -> usize { self.dtm.len() }
+    pub fn value(&self, idx : usize) -> Option<TablebaseValue> { TablebaseValue::from_dtm(*self.dtm.get(idx)?) }
+    /// The longest win
+    pub fn max_dtm(&self) -> u8 { self.dtm.iter().copied().filter(|d| *d <= Self::MAX_DTM).max().unwrap_or(0) }
+
+    /// `KQvK_8x8.dtm`, followed by a hash of the rules if they are not the default ones : `KQvK_8x8_1a2b3c4d.dtm`
+    pub fn file_name(&self) -> String { format!("{}_{}x{}{}.dtm", self.material, self.size.x, self.size.y, self.rules.file_suffix()) }
+
+    fn draw_rules_bits(r : DrawRules) -> u8 { r.stalemate as u8 | (r.insufficient_material as u8) << 1 }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut b = Self::MAGIC.to_vec();
+        b.extend([Self::VERSION, self.size.x as u8, self.size.y as u8, Self::draw_rules_bits(self.draw_rules)]);
+        b.extend(self.rules.to_bytes());
+        b.push(self.material.len() as u8);
+        for (t, f) in self.material.pieces()
+        {
+            b.push(*t as u8);
+            b.extend(f.0.to_be_bytes());
+        }
+        b.extend(&self.dtm);
+        b
+    }
+
+    pub fn from_bytes(b : &[u8]) -> Result<Self, TablebaseError>
+    {
+        let err = |s : &str| TablebaseError::InvalidFile(s.to_owned());
+        if b.len() < 5 || &b[0..4] != Self::MAGIC { return Err(err("not a tablebase")); }
+        if b[4] != Self::VERSION { return Err(err("unknown version")); }
+
+        let mut r = TablebaseReader { bytes: b, pos: 5 };
+        let size = r.take(2)?;
+        let size = at(size[0] as AtIntType, size[1] as AtIntType);
+        let draw_bits = r.u8()?;
+        let draw_rules = DrawRules { stalemate: draw_bits & 1 != 0, insufficient_material: draw_bits & 2 != 0, ..DrawRules::NONE };
+        let rules = TablebaseRules::read(&mut r)?;
+        let nb_piece = r.u8()? as usize;
+
+        let mut pieces = vec![];
+        for p in r.take(3 * nb_piece)?.chunks_exact(3)
+        {
+            if p[0] as usize >= Team::LENGHT { return Err(err("invalid team")); }
+            pieces.push((Team::from_usize(p[0] as usize), PieceFlags(PieceFlagsType::from_be_bytes([p[1], p[2]]))));
+        }
+        let material = TablebaseMaterial::new(pieces).ok_or_else(|| err("invalid material"))?;
+
+        let dtm = b[r.pos..].to_vec();
+        if dtm.len() != Self::nb_position(size, nb_piece) { return Err(err("the number of positions don't match the material")); }
+        Ok(Self { material, size, draw_rules, rules, dtm })
+    }
+}
+
+/// The tables of a board size, draw rules and rules
+#[derive(Clone, PartialEq, Debug)]
+pub struct Tablebases
+{
+    pub size : At,
+    pub draw_rules : DrawRules,
+    pub rules : TablebaseRules,
+    tables : HashMap<TablebaseMaterial, Tablebase>,
+}
+impl Tablebases
+{
+    /// Directory used by the console
+    pub const DEFAULT_DIR : &'static str = "tablebase";
+
+    /// Only the stalemate and insufficient material rules are used, the other ones depend on the previous positions
+    pub fn new(size : At, draw_rules : DrawRules) -> Self
+    {
+        Self { size, draw_rules: DrawRules { stalemate: draw_rules.stalemate, insufficient_material: draw_rules.insufficient_material, ..DrawRules::NONE }, rules: ___(), tables: HashMap::new() }
+    }
+
+    /// Empty tables, with the rules of the game
+    pub fn for_game(state : &BoardGameNotStarted) -> Self { Self::new(state.size(), state.draw_rules).with_rules(TablebaseRules::of(state)) }
+
+    /// Only before the generation of the first table
+    pub fn with_rules(mut self, rules : TablebaseRules) -> Self { self.rules = rules; self }
+
+    pub fn get(&self, material : &TablebaseMaterial) -> Option<&Tablebase> { self.tables.get(material) }
+    pub fn iter(&self) -> impl Iterator<Item = &Tablebase> { self.tables.values() }
+    pub fn nb_table(&self) -> usize { self.tables.len() }
+
+    fn is_applicable(&self, state : &BoardGameFixedTime) -> bool
+    {
+        state.size() == self.size && Self::new(self.size, state.draw_rules).draw_rules == self.draw_rules
+            && Team::iter().all(|t| state.team_data[t].relics == Relics::ZERO) && state.energy_per_turn == 1 && TablebaseRules::of(state) == self.rules
+    }
+
+    /// The material of the position and its index
+    fn position_index(&self, state : &BoardGameFixedTime) -> Option<(TablebaseMaterial, usize)>
+    {
+        let mut pieces = vec![];
+        for t in Team::iter()
+        {
+            for a in state.iter_idx_team(t)
+            {
+                if state[a].teams_flags().count() != 1 { return None; }
+                pieces.push((t, state[a].flags & TablebaseMaterial::FLAGS_MASK, a));
+            }
+        }
+        pieces.sort_by_key(|(t, f, a)| (TablebaseMaterial::piece_order(*t, *f), a.y, a.x));
+
+        let tiles : Vec<At> = pieces.iter().map(|(_, _, a)| *a).collect();
+        let material = TablebaseMaterial::new(pieces.into_iter().map(|(t, f, _)| (t, f)).collect())?;
+        Some((material, Tablebase::index(self.size, &tiles, state.current_team)))
+    }
+
+    /// None if 2 pieces are on the same tile, or a piece on a void tile
+    fn position(&self, material : &TablebaseMaterial, idx : usize) -> Option<BoardGameFixedTime>
+    {
+        let (tiles, team) = Tablebase::decode(self.size, material.len(), idx);
+        if tiles.iter().enumerate().any(|(i, a)| tiles[..i].contains(a) || self.rules.void_tiles.contains(a)) { return None; }
+
+        let mut data = BoardGameNotStarted::new_empty(self.size);
+        self.rules.apply(&mut data);
+        for ((t, f), a) in material.pieces().iter().zip(tiles)
+        {
+            data.piece_add_team_and_set_flags(a, *t, *f);
+            if f.is_also_chess_pawn() && data.is_on_chess_pawn_start_tile(*t, a) { continue; }
+            let p = &mut data[a];
+            p.nb_time_moved = Saturating(1);
+            p.distance_travel_total = Saturating(1);
+            p.last_turn_moved = 0;
+            p.last_action_moved = 0;
+        }
+        data.draw_rules = self.draw_rules;
+        // `BoardGameFixedTime::new()` give the hand to the next team
+        data.current_team = if team == Team::White { Team::Black } else { Team::White };
+        Some(BoardGameFixedTime::new(data))
+    }
+
+    fn child(&self, next : &BoardGameFixedTime, team : Team, material : &TablebaseMaterial, sub_materials : &mut Vec<TablebaseMaterial>) -> TablebaseChild
+    {
+        match next.end_game_result()
+        {
+            Some(BoardResult::WinnerIs(w)) => return TablebaseChild::Known(if w == team { 0 } else { 1 }),
+            Some(BoardResult::Draw) => return TablebaseChild::Known(Tablebase::DRAW),
+            None => {},
+        }
+        let (m, idx) = self.position_index(next).expect("the material only decrease");
+        if &m == material { return TablebaseChild::Same(idx as u32); }
+
+        let sub = sub_materials.iter().position(|e| *e == m).unwrap_or_else(|| { sub_materials.push(m); sub_materials.len() - 1 });
+        TablebaseChild::Sub(sub as u16, idx as u32)
+    }
+
+    /// Generate the table of the material, and the tables of the positions after a capture or a promotion
+    pub fn generate(&mut self, material : &TablebaseMaterial) -> Result<(), TablebaseError>
+    {
+        if self.tables.contains_key(material) { return Ok(()); }
+
+        let nb_position = Tablebase::nb_position(self.size, material.len());
+        if nb_position > Tablebase::MAX_POSITION { return Err(TablebaseError::TooLarge(format!("{} on {}x{}", material, self.size.x, self.size.y), nb_position)); }
+        let mut dtm = vec![Tablebase::DRAW; nb_position];
+        let mut resolved = vec![false; nb_position];
+        let mut children = vec![];
+        let mut children_start = Vec::with_capacity(nb_position + 1);
+        let mut sub_materials = vec![];
+
+        for idx in 0..nb_position
+        {
+            children_start.push(children.len());
+            match self.position(material, idx)
+            {
+                None => { dtm[idx] = Tablebase::INVALID; resolved[idx] = true; },
+                Some(s) if s.is_end_of_the_game() =>
+                {
+                    // the team to play have no action
+                    if matches!(s.end_game_result(), Some(BoardResult::WinnerIs(w)) if w != s.current_team) { dtm[idx] = 0; }
+                    resolved[idx] = true;
+                },
+                Some(s) => for action in s.actions.iter()
+                {
+                    children.push(self.child(&s.execute_action(action, false), s.current_team, material, &mut sub_materials));
+                },
+            }
+        }
+        children_start.push(children.len());
+
+        for m in sub_materials.iter() { self.generate(m)?; }
+        let mut max_known = 0;
+        for c in children.iter_mut()
+        {
+            if let TablebaseChild::Sub(m, idx) = *c { *c = TablebaseChild::Known(self.tables[&sub_materials[m as usize]].dtm[idx as usize]); }
+            if let TablebaseChild::Known(d) = *c { if d <= Tablebase::MAX_DTM { max_known = max_known.max(d); } }
+        }
+
+        // retrograde analysis, one ply at a time
+        let child_dtm = |c : &TablebaseChild, dtm : &[u8], resolved : &[bool]| match *c
+        {
+            TablebaseChild::Same(idx) => resolved[idx as usize].then_some(dtm[idx as usize]),
+            TablebaseChild::Known(d) => Some(d),
+            TablebaseChild::Sub(_, _) => None,
+        };
+        let mut last_resolved = 0;
+        let mut d = 1;
+        while d <= Tablebase::MAX_DTM && d <= max_known.max(last_resolved).saturating_add(2)
+        {
+            for idx in 0..nb_position
+            {
+                if resolved[idx] { continue; }
+                let mut it = children[children_start[idx]..children_start[idx + 1]].iter().map(|c| child_dtm(c, &dtm, &resolved));
+                let is_resolved = if d % 2 == 1
+                {
+                    // an action make the enemy lose in d-1
+                    it.any(|c| c == Some(d - 1))
+                }else
+                {
+                    // every action make the enemy win
+                    it.all(|c| c.is_some_and(|c| c % 2 == 1 && c < d))
+                };
+                if is_resolved { dtm[idx] = d; resolved[idx] = true; last_resolved = d; }
+            }
+            d += 1;
+        }
+
+        self.tables.insert(material.clone(), Tablebase { material: material.clone(), size: self.size, draw_rules: self.draw_rules, rules: self.rules.clone(), dtm });
+        Ok(())
+    }
+
+    /// Every material with a king for each team, and up to `max_pieces` pieces (4 at most) with the `abilities`
+    pub fn generate_abilities(&mut self, abilities : &[PieceFlags], max_pieces : usize) -> Result<(), TablebaseError>
+    {
+        let king = PieceFlags::AB_CHESS_KING.with_flag(PieceFlags::CROWN);
+        let mut extras : Vec<Vec<(Team, PieceFlags)>> = vec![vec![]];
+        for _ in 0..max_pieces.min(TablebaseMaterial::MAX_PIECES).saturating_sub(2)
+        {
+            let mut next = extras.clone();
+            for e in extras.iter().filter(|e| e.len() == extras.last().unwrap().len())
+            {
+                for t in [Team::White, Team::Black]
+                {
+                    for a in abilities { let mut e = e.clone(); e.push((t, *a)); next.push(e); }
+                }
+            }
+            extras = next;
+        }
+
+        for extra in extras
+        {
+            let mut pieces = vec![(Team::White, king), (Team::Black, king)];
+            pieces.extend(extra);
+            if let Some(m) = TablebaseMaterial::new(pieces) { self.generate(&m)?; }
+        }
+        Ok(())
+    }
+
+    /// DTM of a position of the tables, without checking if the tables can be used
+    fn value_of(&self, state : &BoardGameFixedTime) -> Option<TablebaseValue>
+    {
+        let (material, idx) = self.position_index(state)?;
+        self.tables.get(&material)?.value(idx)
+    }
+
+    /// None if the tables can't be used for this position (relic, board size, number of pieces...)
+    pub fn probe(&self, state : &BoardGameFixedTime) -> Option<TablebaseValue>
+    {
+        if !self.is_applicable(state) || state.is_end_of_the_game() { return None; }
+        self.value_of(state)
+    }
+
+    /// The fastest win, the draw, or the slowest defeat
+    pub fn best_action(&self, state : &BoardGameFixedTime) -> Option<(ActionID, TablebaseValue)>
+    {
+        self.probe(state)?;
+        let team = state.current_team;
+        let mut best : Option<(ActionID, TablebaseValue)> = None;
+        for action in state.actions.iter()
+        {
+            let next = state.execute_action(action, false);
+            let value = match next.end_game_result()
+            {
+                Some(BoardResult::WinnerIs(w)) => if w == team { TablebaseValue::Win(1) } else { TablebaseValue::Loss(0) },
+                Some(BoardResult::Draw) => TablebaseValue::Draw,
+                None => self.value_of(&next)?.before(),
+            };
+            if best.is_none_or(|(_, b)| value.rank() > b.rank()) { best = Some((action.id, value)); }
+        }
+        best
+    }
+
+    pub fn save(&self, dir : &str) -> Result<(), TablebaseError>
+    {
+        std::fs::create_dir_all(dir).map_err(|e| TablebaseError::Io(dir.to_owned(), e.to_string()))?;
+        for table in self.tables.values()
+        {
+            let path = format!("{}/{}", dir, table.file_name());
+            std::fs::write(&path, table.to_bytes()).map_err(|e| TablebaseError::Io(path, e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// The `.dtm` files of the directory, grouped by board size, draw rules and rules
+    pub fn load_all(dir : &str) -> Result<Vec<Self>, TablebaseError>
+    {
+        let mut all : Vec<Self> = vec![];
+        for entry in std::fs::read_dir(dir).map_err(|e| TablebaseError::Io(dir.to_owned(), e.to_string()))?
+        {
+            let path = entry.map_err(|e| TablebaseError::Io(dir.to_owned(), e.to_string()))?.path();
+            if path.extension().is_none_or(|e| e != "dtm") { continue; }
+
+            let bytes = std::fs::read(&path).map_err(|e| TablebaseError::Io(path.display().to_string(), e.to_string()))?;
+            let table = Tablebase::from_bytes(&bytes)?;
+            let idx = match all.iter().position(|t| t.size == table.size && t.draw_rules == table.draw_rules && t.rules == table.rules)
+            {
+                Some(idx) => idx,
+                None => { all.push(Self::new(table.size, table.draw_rules).with_rules(table.rules.clone())); all.len() - 1 },
+            };
+            all[idx].tables.insert(table.material.clone(), table);
+        }
+        Ok(all)
+    }
+
+    /// The tables of `DEFAULT_DIR`, loaded once
+    pub fn default_tables() -> &'static [Self]
+    {
+        static TABLES : std::sync::OnceLock<Vec<Tablebases>> = std::sync::OnceLock::new();
+        TABLES.get_or_init(|| Self::load_all(Self::DEFAULT_DIR).unwrap_or_default())
+    }
+}
+
+impl BoardGameFixedTime
+{
+    /// The best action of the tablebase if there is one, otherwise the search
+    pub fn minimax_with_tablebase(&self, tablebases : &[Tablebases], budget : SearchBudget, cancel : &CancellationToken) -> MinimaxResult
+    {
+        match tablebases.iter().find_map(|tb| tb.best_action(self))
+        {
+            Some((action_id, value)) => MinimaxResult { action_id: Some(action_id), score: value.score(), from_tablebase: true, ..___() },
+            None => self.minimax_budget(budget, cancel),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn material(name : &str) -> TablebaseMaterial { TablebaseMaterial::from_name(name).unwrap() }
+
+    /// Both team play the tablebase action until the end of the game
+    fn play_until_the_end(tb : &Tablebases, state : &BoardGameFixedTime) -> (usize, Option<BoardResult>)
+    {
+        let mut b = BoardGame::new_from_fixed_time(state.clone());
+        let mut nb_ply = 0;
+        while !b.is_end_of_the_game() && nb_ply < 64
+        {
+            let (id, _) = tb.best_action(b.current()).unwrap();
+            b.execute(id);
+            nb_ply += 1;
+        }
+        (nb_ply, b.end_game_result())
+    }
+
+    #[test]
+    fn tablebase_queen_and_rook()
+    {
+        let mut tb = Tablebases::new(at(4, 4), DrawRules::CHESS);
+        tb.generate(&material("KQvK")).unwrap();
+        tb.generate(&material("KRvK")).unwrap();
+        // the capture of the last piece is a draw by insufficient material
+        assert_eq!(tb.nb_table(), 2);
+        tb.generate(&material("KvK")).unwrap();
+        assert!(tb.get(&material("KvK")).unwrap().dtm.iter().all(|d| matches!(*d, Tablebase::DRAW | Tablebase::INVALID)));
+
+        let kqk = tb.get(&material("KQvK")).unwrap();
+        assert!(kqk.max_dtm() > 4);
+
+        let mut rng = Random::new(1);
+        let mut nb_checked = 0;
+        while nb_checked < 20
+        {
+            let idx = rng.gen_range(0..kqk.nb_position_total());
+            let Some(state) = tb.position(&kqk.material, idx) else { continue; };
+            let Some(value) = tb.probe(&state) else { continue; };
+
+            // the DTM is exact
+            let (nb_ply, result) = play_until_the_end(&tb, &state);
+            match value
+            {
+                TablebaseValue::Win(d) => assert_eq!((nb_ply, result), (d as usize, Some(BoardResult::WinnerIs(state.current_team)))),
+                TablebaseValue::Loss(d) => assert_eq!(nb_ply, d as usize),
+                TablebaseValue::Draw => assert_eq!(result, Some(BoardResult::Draw)),
+            }
+            if let TablebaseValue::Win(d @ 1..=3) = value
+            {
+                assert!(state.minimax_budget(SearchBudget::depth(d as usize), &CancellationToken::new()).score > 1E64);
+            }
+            nb_checked += 1;
+        }
+    }
+
+    #[test]
+    fn tablebase_promotion_and_probe()
+    {
+        let mut tb = Tablebases::new(at(3, 4), DrawRules::CHESS);
+        tb.generate_abilities(&[PieceFlags::AB_CHESS_PAWN], 3).unwrap();
+        // the pawn can be promoted
+        for name in ["KPvK", "KvKP", "KQvK", "KRvK", "KvKQ", "KvKR"] { assert!(tb.get(&material(name)).is_some(), "{}", name); }
+        assert!(tb.get(&material("KBvK")).is_none());
+
+        let b = BoardGame::from_fen("k2/3/1P1/K2 w - - 0 1", Relics::ZERO).unwrap();
+        let value = tb.probe(&b).unwrap();
+        let r = b.minimax_with_tablebase(std::slice::from_ref(&tb), SearchBudget::depth(1), &CancellationToken::new());
+        assert!(r.from_tablebase);
+        assert_eq!(r.score, value.score());
+        assert_eq!(tb.best_action(&b).unwrap().1, value);
+
+        // not used with a relic or another board size
+        assert!(tb.probe(&BoardGame::from_fen("k2/3/1P1/K2 w - - 0 1", Relics::ZERO.with_flag_add(Relic::Anticipation)).unwrap()).is_none());
+        assert!(tb.probe(&BoardGame::from_fen("k3/4/1P2/K3 w - - 0 1", Relics::ZERO).unwrap()).is_none());
+        // or other rules : 2 actions per turn, only a queen promotion
+        let with_setup = |setup : fn(&mut BoardGameNotStarted)| BoardGameFixedTime::from_fen_with_setup("k2/3/1P1/K2 w - - 0 1", [Relics::ZERO; Team::LENGHT], setup).unwrap();
+        assert!(tb.probe(&with_setup(|_| {})).is_some());
+        assert!(tb.probe(&with_setup(|d| d.energy_per_turn = 2)).is_none());
+        assert!(tb.probe(&with_setup(|d| d.chess_promotion = SmallVec::from_slice(&[PieceFlags::AB_CHESS_QUEEN]))).is_none());
+        let r = BoardGame::new_chess().minimax_with_tablebase(std::slice::from_ref(&tb), SearchBudget::depth(1), &CancellationToken::new());
+        assert!(!r.from_tablebase);
+    }
+
+    #[test]
+    fn tablebase_rules()
+    {
+        let mut tb = Tablebases::new(at(4, 3), DrawRules::CHESS);
+        tb.generate(&material("KRvK")).unwrap();
+
+        let mut legal = BoardGame::from_fen("k3/4/1K1R w - - 0 1", Relics::ZERO).unwrap().current().clone();
+        legal.set_legal_moves(true);
+        assert!(tb.probe(&legal).is_none());
+
+        let mut legal_tb = Tablebases::for_game(&legal);
+        legal_tb.generate(&material("KRvK")).unwrap();
+        let Some(TablebaseValue::Win(d)) = legal_tb.probe(&legal) else { panic!("the rook win") };
+        assert_eq!(play_until_the_end(&legal_tb, &legal), (d as usize, Some(BoardResult::WinnerIs(Team::White))));
+
+        let table = legal_tb.get(&material("KRvK")).unwrap();
+        assert_ne!(table.file_name(), tb.get(&material("KRvK")).unwrap().file_name());
+        assert_eq!(Tablebase::from_bytes(&table.to_bytes()).as_ref(), Ok(table));
+
+        // a board without corner is not a 5x5 board
+        let mut tb = Tablebases::new(at(5, 5), DrawRules::CHESS);
+        tb.generate(&material("KRvK")).unwrap();
+        let cross = BoardGameFixedTime::from_fen_with_setup("5/1k3/5/1K1R1/5 w - - 0 1", [Relics::ZERO; Team::LENGHT], |d| d.set_void_corners(1)).unwrap();
+        assert!(tb.probe(&cross).is_none());
+
+        let mut cross_tb = Tablebases::for_game(&cross);
+        cross_tb.generate(&material("KRvK")).unwrap();
+        assert_eq!(cross_tb.rules.void_tiles.len(), 4);
+        let Some(TablebaseValue::Win(d)) = cross_tb.probe(&cross) else { panic!("the rook win") };
+        assert_eq!(play_until_the_end(&cross_tb, &cross), (d as usize, Some(BoardResult::WinnerIs(Team::White))));
+        let table = cross_tb.get(&material("KRvK")).unwrap();
+        let corner = Tablebase::index(at(5, 5), &[at(1, 1), at(3, 1), at(0, 0)], Team::White);
+        assert_eq!(table.value(corner), None);
+        assert_eq!(Tablebase::from_bytes(&table.to_bytes()).as_ref(), Ok(table));
+
+        // a custom ability that move like the rook give the same values as the rook
+        let rook = BoardGame::from_fen("k3/4/1K1R w - - 0 1", Relics::ZERO).unwrap();
+        let custom = BoardGameFixedTime::from_fen_with_setup("k3/4/1K2 w - - 0 1", [Relics::ZERO; Team::LENGHT], |d|
+        {
+            d.custom_abilities = Arc::new(vec![CustomAbility::new("tower", "R").unwrap()]);
+            d.piece_add_team_and_set_flags(at(3, 0), Team::White, PieceFlags::AB_CUSTOM[0]);
+        }).unwrap();
+        let mut tb = Tablebases::new(at(4, 3), DrawRules::CHESS);
+        tb.generate(&material("KRvK")).unwrap();
+        assert!(!tb.is_applicable(&custom));
+        let mut custom_tb = Tablebases::for_game(&custom);
+        let king = PieceFlags::AB_CHESS_KING.with_flag(PieceFlags::CROWN);
+        let custom_material = TablebaseMaterial::new(vec![(Team::White, king), (Team::White, PieceFlags::AB_CUSTOM[0]), (Team::Black, king)]).unwrap();
+        custom_tb.generate(&custom_material).unwrap();
+        assert!(custom_tb.probe(&rook).is_none());
+        assert_eq!(custom_tb.probe(&custom), tb.probe(&rook));
+        assert!(custom_tb.probe(&custom).is_some());
+        let table = custom_tb.get(&custom_material).unwrap();
+        assert_eq!(Tablebase::from_bytes(&table.to_bytes()).as_ref(), Ok(table));
+    }
+
+    #[test]
+    fn tablebase_file()
+    {
+        let mut tb = Tablebases::new(at(4, 3), DrawRules::CHESS);
+        tb.generate(&material("KRvK")).unwrap();
+        let table = tb.get(&material("KRvK")).unwrap();
+        assert_eq!(table.file_name(), "KRvK_4x3.dtm");
+        assert_eq!(Tablebase::from_bytes(&table.to_bytes()).as_ref(), Ok(table));
+        assert!(Tablebase::from_bytes(&table.to_bytes()[..100]).is_err());
+        let invalid = |s : &str| Err(TablebaseError::InvalidFile(s.to_owned()));
+        let mut bytes = table.to_bytes();
+        bytes[4] = Tablebase::VERSION + 1;
+        assert_eq!(Tablebase::from_bytes(&bytes), invalid("unknown version"));
+        assert_eq!(Tablebase::from_bytes(b"KRvK_4x3"), invalid("not a tablebase"));
+        assert_eq!(Tablebase::from_bytes(&table.to_bytes()[..10]), invalid("truncated header"));
+        let mut bytes = table.to_bytes();
+        let first_team = bytes.len() - table.nb_position_total() - 3 * table.material.len();
+        bytes[first_team] = Team::LENGHT as u8;
+        assert_eq!(Tablebase::from_bytes(&bytes), invalid("invalid team"));
+
+        // too many pieces, a team without piece, a piece that is not a chess piece
+        for name in ["KQRvKR", "KQRv", "KvX"] { assert_eq!(TablebaseMaterial::from_name(name), None); }
+        assert!(matches!(Tablebases::load_all("tablebase_dir_that_dont_exist"), Err(TablebaseError::Io(..))));
+        assert_eq!(Tablebases::new(at(8, 8), DrawRules::CHESS).generate(&material("KQvKR")), Err(TablebaseError::TooLarge("KQvKR on 8x8".to_owned(), 33_554_432)));
+
+        let dir = std::env::temp_dir().join(format!("tablebase_test_{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+        tb.save(dir).unwrap();
+        let loaded = Tablebases::load_all(dir).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+        assert_eq!(loaded, [tb]);
+    }
+}
@@ -0,0 +1,125 @@
+//! Fixed size transposition table used by the minimax search.
+//!
+//! Each entry is indexed by the zobrist hash of the position, and store for which team the score was computed,
+//! the remaining depth of the search, the kind of bound of the score (alpha-beta) and the best action found.
+
+use super::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TranspositionBound
+{
+    /// The score is exact
+    Exact,
+    /// The real score is greater or equal (beta cut)
+    Lower,
+    /// The real score is lower or equal (alpha cut)
+    Upper,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TranspositionEntry
+{
+    pub key : ZobristHash,
+    /// The score is seen by this team
+    pub team : Team,
+    /// Remaining depth when the position was searched
+    pub depth : usize,
+    pub bound : TranspositionBound,
+    pub score : Score,
+    pub action_id : Option<ActionID>,
+}
+impl TranspositionEntry
+{
+    /// Can the score be used instead of searching the position with `depth` and the window `alpha`, `beta` ?
+    pub fn is_usable(&self, depth : usize, alpha : Score, beta : Score) -> bool
+    {
+        self.depth >= depth && match self.bound
+        {
+            TranspositionBound::Exact => true,
+            TranspositionBound::Lower => self.score >= beta,
+            TranspositionBound::Upper => self.score <= alpha,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TranspositionTable
+{
+    entries : Vec<Option<TranspositionEntry>>,
+}
+impl Default for TranspositionTable { fn default() -> Self { Self::new(Self::DEFAULT_SIZE_LOG2) } }
+impl TranspositionTable
+{
+    pub const DEFAULT_SIZE_LOG2 : usize = 16;
+
+    /// Table of `2^size_log2` entries
+    pub fn new(size_log2 : usize) -> Self { Self { entries: vec![None; 1 << size_log2] } }
+
+    pub fn clear(&mut self) { self.entries.iter_mut().for_each(|e| *e = None); }
+
+    fn idx(&self, key : ZobristHash) -> usize { key as usize & (self.entries.len() - 1) }
+
+    pub fn get(&self, key : ZobristHash, team : Team) -> Option<&TranspositionEntry>
+    {
+        self.entries[self.idx(key)].as_ref().filter(|e| e.key == key && e.team == team)
+    }
+
+    /// Replace the previous entry, except if it is the same position already searched deeper
+    pub fn insert(&mut self, entry : TranspositionEntry)
+    {
+        let idx = self.idx(entry.key);
+        let slot = &mut self.entries[idx];
+        if slot.is_some_and(|e| e.key == entry.key && e.team == entry.team && e.depth > entry.depth) { return; }
+        *slot = Some(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn transposition_table_replacement()
+    {
+        let mut tt = TranspositionTable::new(4);
+        let entry = TranspositionEntry { key: 3, team: Team::White, depth: 3, bound: TranspositionBound::Exact, score: 1., action_id: None };
+        tt.insert(entry);
+        assert!(tt.get(3, Team::Black).is_none());
+        assert!(tt.get(3 + 16, Team::White).is_none());
+
+        tt.insert(TranspositionEntry { depth: 2, score: 2., ..entry });
+        assert_eq!(tt.get(3, Team::White).unwrap().score, 1.);
+
+        tt.insert(TranspositionEntry { key: 3 + 16, ..entry });
+        assert!(tt.get(3, Team::White).is_none());
+        assert!(tt.get(3 + 16, Team::White).is_some());
+    }
+
+    #[test]
+    fn iterative_deepening_same_result_as_plain_search()
+    {
+        let mut b = BoardGame::new_chess();
+        for san in ["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6"] { b.execute(b.san_to_action_id(san).unwrap()); }
+
+        for depth in 1..=3
+        {
+            let plain = b.current()._minimax(b.current_team, 0, depth, Score::MIN, Score::MAX, &mut MinimaxSearch::unlimited());
+            let r = b.minimax_custom(depth);
+            assert_eq!(plain.score, r.score, "depth {}", depth);
+        }
+
+        let r = b.minimax_custom(3);
+        assert!(r.stat_nb_tt_hit > 0);
+        assert!(r.tt_hit_rate() > 0. && r.tt_hit_rate() <= 1.);
+    }
+
+    #[test]
+    fn iterative_deepening_find_mate()
+    {
+        let b = BoardGame::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", Relics::ZERO.with_flag_add(Relic::Anticipation)).unwrap();
+        // the crown is captured at the 3rd action
+        let r = b.minimax_custom(4);
+        assert_eq!(b.action_id_to_san(r.action_id.unwrap()).unwrap(), "Ra8#");
+    }
+}
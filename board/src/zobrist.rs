@@ -0,0 +1,146 @@
+//! Zobrist hashing of the positions.
+//!
+//! The hash is the xor of a key for every information of the position :
+//! - each tile : the bits of the `PieceFlags` (except the pins, deduced from the position), the teams of the piece, and if a king/rook/pawn was never moved (castling and pawn first move)
+//! - the pawns that can be captured en passant
+//! - `current_team`, `current_nb_energy`, the `Relics` of each team, and if the duck must be placed
+//!
+//! The tiles part is updated in `piece_set_flags`, `piece_add_team`, `piece_remove_team` (so `set_empty_piece`) and `piece_swap`.
+//! The other part only depend on the state of the turn, so it is recomputed in `end_of_action`.
+
+use super::*;
+
+pub type ZobristHash = u64;
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+enum ZobristKind
+{
+    PieceFlag,
+    PieceTeam,
+    PieceNeverMoved,
+    EnPassant,
+    CurrentTeam,
+    CurrentEnergy,
+    TeamRelic,
+    DuckToPlace,
+}
+
+/// Pseudo random key, always the same for the same input (splitmix64)
+fn zobrist_key(kind : ZobristKind, x : i16, y : i16, bit : u8) -> ZobristHash
+{
+    let mut z = ((kind as u64) << 48 | (x as u16 as u64) << 32 | (y as u16 as u64) << 16 | bit as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn zobrist_key_bits(kind : ZobristKind, x : i16, y : i16, bits : u16) -> ZobristHash
+{
+    (0..16).filter(|i| bits & (1 << i) != 0).fold(0, |h, i| h ^ zobrist_key(kind, x, y, i))
+}
+
+impl Piece
+{
+    /// Zobrist key of the piece on the tile `at`. Zero for an empty tile
+    pub fn zobrist(&self, at : At) -> ZobristHash
+    {
+        let flags = self.flags.without_flag(PieceFlags::PARTIAL_PIN).without_flag(PieceFlags::TOTAL_PIN);
+        let mut h = zobrist_key_bits(ZobristKind::PieceFlag, at.x, at.y, flags.0) ^ zobrist_key_bits(ZobristKind::PieceTeam, at.x, at.y, self.teams_flags().0 as u16);
+        if (self.is_also_chess_king() || self.is_also_chess_rook() || self.is_also_chess_pawn()) && self.nb_time_moved == Saturating(0)
+        {
+            h ^= zobrist_key(ZobristKind::PieceNeverMoved, at.x, at.y, 0);
+        }
+        h
+    }
+}
+
+impl BoardGameNotStarted
+{
+    /// Zobrist hash of the tiles, computed from scratch
+    pub fn zobrist_tiles_compute(&self) -> ZobristHash
+    {
+        self.iter_idx().fold(0, |h, at| h ^ self[at].zobrist(at))
+    }
+}
+
+impl BoardGameFixedTime
+{
+    /// Zobrist hash of everything that is not a tile, computed from scratch
+    pub fn zobrist_state_compute(&self) -> ZobristHash
+    {
+        let mut h = zobrist_key(ZobristKind::CurrentTeam, 0, 0, self.current_team as u8) ^ zobrist_key(ZobristKind::CurrentEnergy, 0, 0, self.current_nb_energy as u8);
+        if self.duck_to_place { h ^= zobrist_key(ZobristKind::DuckToPlace, 0, 0, 0); }
+
+        for t in Team::iter()
+        {
+            h ^= zobrist_key_bits(ZobristKind::TeamRelic, t as i16, 0, self.team_data[t].relics as u16);
+
+            for at in self.iter_idx_team(t).filter(|a| self[*a].is_also_chess_pawn() && self.was_moved_last_turn_or_this_turn(*a) && self[*a].distance_travel_total == Saturating(2) && self[*a].nb_time_moved == Saturating(1))
+            {
+                h ^= zobrist_key(ZobristKind::EnPassant, at.x, at.y, t as u8);
+            }
+        }
+        h
+    }
+
+    /// Zobrist hash of the position, computed from scratch
+    pub fn zobrist_compute(&self) -> ZobristHash { self.zobrist_tiles_compute() ^ self.zobrist_state_compute() }
+
+    /// Recompute the zobrist hash from scratch, needed after editing the position without the `piece_*` functions
+    pub fn zobrist_init(&mut self)
+    {
+        self.zobrist_state = self.zobrist_state_compute();
+        self.data.zobrist = self.zobrist_tiles_compute() ^ self.zobrist_state;
+    }
+
+    /// Replace the old non tile part of the hash by the new one
+    pub(crate) fn zobrist_update_state(&mut self)
+    {
+        let state = self.zobrist_state_compute();
+        self.data.zobrist ^= self.zobrist_state ^ state;
+        self.zobrist_state = state;
+    }
+
+    pub(crate) fn zobrist_tiles_is_ok(&self) -> bool { self.zobrist ^ self.zobrist_state == self.zobrist_tiles_compute() }
+    pub(crate) fn zobrist_is_ok(&self) -> bool { self.zobrist == self.zobrist_compute() }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn zobrist_incremental_match_recompute()
+    {
+        let mut rng = Random::new(11);
+        for mut b in [BoardGame::new_chess(), BoardGame::new_chess_custom(4, true, Relics::ZERO), BoardGame::new_checker(), BoardGame::new_chess_custom(2, true, Relics::ZERO.with_flag_add(Relic::DuckButDifferent))]
+        {
+            for _ in 0..60
+            {
+                if b.is_end_of_the_game() { break; }
+                assert_eq!(b.zobrist, b.current().zobrist_compute());
+                let id = b.actions[rng.gen_range(0..b.actions.len())].id;
+                b.execute(id);
+            }
+            assert_eq!(b.zobrist, b.current().zobrist_compute());
+        }
+    }
+
+    #[test]
+    fn zobrist_transposition()
+    {
+        let mut a = BoardGame::new_chess();
+        let mut b = BoardGame::new_chess();
+        for san in ["Nf3", "Nc6", "Nc3"] { a.execute(a.san_to_action_id(san).unwrap()); }
+        for san in ["Nc3", "Nc6", "Nf3"] { b.execute(b.san_to_action_id(san).unwrap()); }
+        assert_eq!(a.zobrist, b.zobrist);
+
+        // same tiles, different en passant
+        let c = BoardGame::from_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3", Relics::ZERO).unwrap();
+        let d = BoardGame::from_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3", Relics::ZERO).unwrap();
+        assert_ne!(c.zobrist, d.zobrist);
+        assert_eq!(c.zobrist, c.current().zobrist_compute());
+    }
+}
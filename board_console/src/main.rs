@@ -41,12 +41,55 @@ fn player_vs_player(mut b : BoardGame)
     b.console_display();*/
 }
 
-fn ai_vs_ai(board : BoardGame)
+/// Engine used by a cpu in the console
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ConsoleEngine
+{
+    Minimax,
+    Mcts,
+}
+impl ConsoleEngine
+{
+    fn from_name(name : &str) -> Option<Self>
+    {
+        match name
+        {
+            "minimax" => Some(Self::Minimax),
+            "mcts" => Some(Self::Mcts),
+            _ => { println!("unknown engine {} (expected minimax or mcts)", name); None }
+        }
+    }
+
+    fn best_move(self, b : &BoardGame) -> ActionID
+    {
+        match self
+        {
+            ConsoleEngine::Minimax => b.console_ai_best_move(),
+            // the samples of the fog of war are searched with mcts
+            ConsoleEngine::Mcts if b.fog_of_war =>
+            {
+                let cancel = CancellationToken::new();
+                let r = b.determinized_search(SearchBudget::time(1.), &cancel, b.turn as u64, |s, budget| s.mcts(budget, &cancel, MctsConfig::default().with_seed(b.turn as u64)));
+                println!("mcts in the fog : {} iterations on {} sampled positions", r.stat_nb_action_evaluated, BoardGameFixedTime::DETERMINIZED_NB_SAMPLE);
+                r.action_id.unwrap()
+            },
+            ConsoleEngine::Mcts =>
+            {
+                let r = b.mcts(SearchBudget::time(1.), &CancellationToken::new(), MctsConfig::default().with_seed(b.zobrist));
+                println!("mcts : {} iterations, depth {}, win rate {:.1}%", r.stat_nb_action_evaluated, r.stat_nb_depth_evaluated, r.score * 100.);
+                r.action_id.unwrap()
+            },
+        }
+    }
+}
+
+/// The engine of each team is `engines[team % engines.len()]`
+fn ai_vs_ai(board : BoardGame, engines : &[ConsoleEngine])
 {
     let mut b = board.clone();
 
     let nb_turn = 16;
-    println!("Ai vs Ai, {} turns", nb_turn);
+    println!("Ai vs Ai, {} turns, {:?}", nb_turn, engines);
     println!();
 
     /* 
@@ -63,7 +106,9 @@ fn ai_vs_ai(board : BoardGame)
 
     for _ in 0..nb_turn
     {
-        b.execute(b.console_ai_best_move());
+        if b.is_end_of_the_game() { break; }
+        let engine = engines[b.current_team as usize % engines.len()];
+        b.execute(engine.best_move(&b));
     }
 
     let elapsed_s = start.elapsed().as_secs_f64();
@@ -71,15 +116,283 @@ fn ai_vs_ai(board : BoardGame)
     b.console_display();
     println!("Total wait time : {:.4}s for {} turns ({:.4} s/turn)", elapsed_s, nb_turn, elapsed_s / nb_turn as f64);
 
+    match b.to_pgn_with_tags(&[("White", "Cpu"), ("Black", "Cpu"), ("WhiteKind", "Cpu"), ("BlackKind", "Cpu")])
+    {
+        Ok(pgn) => { println!(); println!("{}", pgn); },
+        Err(e) => println!("can't export the game in PGN : {}", e),
+    }
+
+}
+
+/// Replay a PGN file and display each position
+fn replay_pgn(path : &str)
+{
+    let pgn = match std::fs::read_to_string(path)
+    {
+        Ok(pgn) => pgn,
+        Err(e) => { println!("can't read {} : {}", path, e); return; }
+    };
+
+    match BoardGame::from_pgn(&pgn)
+    {
+        Ok(mut b) =>
+        {
+            while b.undo() {}
+            b.console_display();
+            while b.redo() { b.console_display(); }
+        },
+        Err(e) => println!("can't replay {} : {}", path, e),
+    }
+}
+
+/// `default` or the path of a TOML config with an `[eval]` table
+fn load_evaluator(name : &str) -> Option<WeightedEvaluator>
+{
+    if name == "default" { return Some(WeightedEvaluator::default()); }
+    match EvalWeights::load(name)
+    {
+        Ok(w) => Some(WeightedEvaluator::new(w)),
+        Err(e) => { println!("invalid evaluator {} : {}", name, e); None }
+    }
+}
+
+/// Pit 2 evaluators against each other. Each opening (a few pseudo random actions) is played twice, once with each color
+fn evaluator_vs_evaluator(a : &str, b : &str, depth : usize, nb_game : usize)
+{
+    let (Some(eval_a), Some(eval_b)) = (load_evaluator(a), load_evaluator(b)) else { return; };
+    let max_nb_action = 300;
+    let nb_random_action = 2;
+
+    let (mut win_a, mut win_b, mut draw) = (0, 0, 0);
+    for game in 0..nb_game
+    {
+        let mut board = BoardGame::new_default();
+        let mut seed = (game / 2) as u64;
+        for _ in 0..nb_random_action
+        {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            board.execute(board.actions[(seed >> 33) as usize % board.actions.len()].id);
+        }
+
+        let a_team = if game % 2 == 0 { Team::White } else { Team::Black };
+        while !board.is_end_of_the_game() && board.played_actions().len() < max_nb_action
+        {
+            let budget = SearchBudget::depth(depth);
+            let cancel = CancellationToken::new();
+            let r = if board.current_team == a_team { board.minimax_evaluator(budget, &cancel, &eval_a) } else { board.minimax_evaluator(budget, &cancel, &eval_b) };
+            board.execute(r.action_id.unwrap());
+        }
+
+        let result = match board.current().end_game_result()
+        {
+            Some(BoardResult::WinnerIs(t)) if t == a_team => { win_a += 1; "A win" },
+            Some(BoardResult::WinnerIs(_)) => { win_b += 1; "B win" },
+            _ => { draw += 1; "draw" },
+        };
+        println!("game #{} : A is {}, {} after {} actions", game + 1, a_team, result, board.played_actions().len());
+    }
+    println!();
+    println!("A ({}) : {} win, B ({}) : {} win, {} draw", a, win_a, b, win_b, draw);
+}
+
+/// 4 players games where each team use a `MultiplayerSearch`, rotated at each game, and the win rate of each algorithm
+fn multiplayer_vs_multiplayer(depth : usize, nb_game : usize)
+{
+    let max_nb_action = 400;
+    let all = MultiplayerSearch::ALL;
+    let mut nb_win = [0; MultiplayerSearch::ALL.len()];
+    let mut nb_played = [0; MultiplayerSearch::ALL.len()];
+    let mut nb_draw = 0;
+
+    for game in 0..nb_game
+    {
+        let algo_of = |t : Team| (t as usize + game) % all.len();
+        let mut board = BoardGame::new_chess_custom(4, true, 0);
+        for t in Team::iter() { nb_played[algo_of(t)] += 1; }
+
+        while !board.is_end_of_the_game() && board.played_actions().len() < max_nb_action
+        {
+            let budget = SearchBudget::depth(depth).with_multiplayer(all[algo_of(board.current_team)]);
+            board.execute(board.minimax_budget(budget, &CancellationToken::new()).action_id.unwrap());
+        }
+
+        match board.current().end_game_result()
+        {
+            Some(BoardResult::WinnerIs(t)) =>
+            {
+                nb_win[algo_of(t)] += 1;
+                println!("game #{} : {} ({}) win after {} actions", game + 1, t, all[algo_of(t)], board.played_actions().len());
+            },
+            _ => { nb_draw += 1; println!("game #{} : draw after {} actions", game + 1, board.played_actions().len()); },
+        }
+    }
+    println!();
+    for (i, algo) in all.iter().enumerate()
+    {
+        println!("{:>10} : {} win for {} team played ({:.1}%)", algo, nb_win[i], nb_played[i], 100. * nb_win[i] as f64 / nb_played[i].max(1) as f64);
+    }
+    println!("{:>10} : {}", "draw", nb_draw);
+}
+
+/// Generate the tables of the materials (`KQvK`, `KRvKP`...) for a board size (`8`, `6x5`...) with the chess draw rules, and save them in `Tablebases::DEFAULT_DIR`
+fn generate_tablebase(size : &str, materials : &[String])
+{
+    let size = match size.split_once('x').map_or_else(|| size.parse().map(|n| (n, n)), |(x, y)| x.parse().and_then(|x| y.parse().map(|y| (x, y))))
+    {
+        Ok((x, y)) => at(x, y),
+        Err(_) => { println!("invalid board size {} (expected 8 or 6x5)", size); return; },
+    };
+
+    let mut tb = Tablebases::new(size, DrawRules::CHESS);
+    for name in materials
+    {
+        let Some(material) = TablebaseMaterial::from_name(name) else { println!("invalid material {} (expected KQvK, up to {} pieces)", name, TablebaseMaterial::MAX_PIECES); return; };
+        let start = Instant::now();
+        if let Err(e) = tb.generate(&material) { println!("{}", e); return; }
+        let table = tb.get(&material).unwrap();
+        println!("{} : {} positions in {:.1}s, longest win in {} plies", material, table.nb_position_total(), start.elapsed().as_secs_f64(), table.max_dtm());
+    }
+
+    match tb.save(Tablebases::DEFAULT_DIR)
+    {
+        Ok(()) => println!("{} tables saved in {}", tb.nb_table(), Tablebases::DEFAULT_DIR),
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Count the leaf positions at `depth` from the chess start position or a FEN, and with `divide` the count after each action
+fn perft(depth : usize, divide : bool, fen : Option<&str>)
+{
+    let b = match fen
+    {
+        Some(fen) => match BoardGame::from_fen(fen, 1 << Relic::Anticipation as u8)
+        {
+            Ok(b) => b,
+            Err(e) => { println!("{}", e); return; },
+        },
+        None => BoardGame::new_chess(),
+    };
+
+    let start = Instant::now();
+    let total = if divide
+    {
+        let mut total = 0;
+        for (id, n) in b.perft_divide(depth)
+        {
+            println!("{:>8} : {}", b.action_id_to_san(id).unwrap_or_else(|_| id.to_string()), n);
+            total += n;
+        }
+        total
+    }else
+    {
+        b.perft(depth)
+    };
+    println!("perft({}) = {} in {:.2}s", depth, total, start.elapsed().as_secs_f64());
+}
+
+/// A Polyglot `.bin` book, with the official keys in a text file (the `Random64` C array of the specification)
+fn load_polyglot_book(book : &str, random : &str) -> Option<OpeningBook>
+{
+    match PolyglotRandom::load(random).and_then(|r| OpeningBook::load_polyglot(book, r))
+    {
+        Ok(b) => Some(b),
+        Err(e) => { println!("{}", e); None },
+    }
+}
 
+/// Build a game from a `GameConfig` file. A random back rank without seed get one from the clock
+fn load_config(path : &str) -> Option<BoardGame>
+{
+    match GameConfig::load(path).and_then(|mut c|
+    {
+        if c.back_rank.use_seed() && c.seed.is_none()
+        {
+            let seed = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+            println!("{} back rank with the seed {}, add `seed = {}` to {} to replay it", c.back_rank.name(), seed, seed, path);
+            c.seed = Some(seed);
+        }
+        c.build()
+    })
+    {
+        Ok(b) => Some(b),
+        Err(e) => { println!("{}", e); None },
+    }
 }
 
 /* 
 cargo run --package=board_console --release
+cargo run --package=board_console --release -- pgn game.pgn
+cargo run --package=board_console --release -- eval default my_weights.toml 3 10
+cargo run --package=board_console --release -- multiplayer 2 6
+cargo run --package=board_console --release -- cpu minimax mcts
+cargo run --package=board_console --release -- cpu minimax mcts board/config/chess_4_players.toml
+cargo run --package=board_console --release -- config board/config/chess_4_players.toml
+cargo run --package=board_console --release -- cpu minimax mcts board/config/dark_chess.toml
+cargo run --package=board_console --release -- config board/config/chess960.toml
+cargo run --package=board_console --release -- cpu minimax mcts board/config/random_army_4_players.toml
+cargo run --package=board_console --release -- tablebase 6 KQvK KRvK
+cargo run --package=board_console --release -- book book.bin random64.txt
+cargo run --package=board_console --release -- perft 4 divide "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"
 */
 
 fn main()
 {
+    let args : Vec<String> = std::env::args().collect();
+    if args.len() == 3 && args[1] == "pgn"
+    {
+        replay_pgn(&args[2]);
+        return;
+    }
+    if (4..=6).contains(&args.len()) && args[1] == "eval"
+    {
+        let depth = args.get(4).and_then(|d| d.parse().ok()).unwrap_or(2);
+        let nb_game = args.get(5).and_then(|n| n.parse().ok()).unwrap_or(2);
+        evaluator_vs_evaluator(&args[2], &args[3], depth, nb_game);
+        return;
+    }
+    if (4..=5).contains(&args.len()) && args[1] == "cpu"
+    {
+        let (Some(white), Some(black)) = (ConsoleEngine::from_name(&args[2]), ConsoleEngine::from_name(&args[3])) else { return; };
+        let Some(b) = args.get(4).map_or_else(|| Some(BoardGame::new_default()), |path| load_config(path)) else { return; };
+        ai_vs_ai(b, &[white, black]);
+        return;
+    }
+    if args.len() == 3 && args[1] == "config"
+    {
+        if let Some(b) = load_config(&args[2]) { player_vs_player(b); }
+        return;
+    }
+    if args.len() >= 4 && args[1] == "tablebase"
+    {
+        generate_tablebase(&args[2], &args[3..]);
+        return;
+    }
+    if args.len() == 4 && args[1] == "book"
+    {
+        if let Some(book) = load_polyglot_book(&args[2], &args[3])
+        {
+            println!("{} positions in the book", book.nb_position());
+            let _ = OpeningBook::set_chess_default(book);
+            ai_vs_ai(BoardGame::new_chess(), &[ConsoleEngine::Minimax]);
+        }
+        return;
+    }
+    if (3..=5).contains(&args.len()) && args[1] == "perft"
+    {
+        let Ok(depth) = args[2].parse() else { println!("invalid depth {}", args[2]); return; };
+        let divide = args.get(3).is_some_and(|a| a == "divide");
+        let fen = args.get(if divide { 4 } else { 3 }).map(|f| f.as_str());
+        perft(depth, divide, fen);
+        return;
+    }
+    if (2..=4).contains(&args.len()) && args[1] == "multiplayer"
+    {
+        let depth = args.get(2).and_then(|d| d.parse().ok()).unwrap_or(2);
+        let nb_game = args.get(3).and_then(|n| n.parse().ok()).unwrap_or(3);
+        multiplayer_vs_multiplayer(depth, nb_game);
+        return;
+    }
+
     for _ in 0..64 { println!(); }
     println!("Hello, world!");
     println!();
@@ -91,6 +404,6 @@ fn main()
     println!("size of a single piece : {} octets", std::mem::size_of_val(&b[at(0, 0)]));
 
     player_vs_player(b);
-    //ai_vs_ai(b);
+    //ai_vs_ai(b, &[ConsoleEngine::Minimax]);
 
 }
\ No newline at end of file
@@ -2,13 +2,216 @@ use std::time::{Duration, Instant};
 
 use super::*;
 
-#[derive(Clone, Debug, Default)]
+/// The search of a cpu engine
+#[derive(Debug)]
+enum GraphicBoardAiSearch
+{
+    Minimax(Box<MinimaxIterativeSearch>),
+    Mcts(MctsSearch),
+    /// A move of the opening book or of the tablebases, without search
+    Known(MinimaxResult),
+    /// Fog of war : a search on each position sampled from what the cpu see, one after the other, then a vote
+    Determinized(Box<GraphicBoardAiDeterminized>),
+}
+impl GraphicBoardAiSearch
+{
+    fn new(state : BoardGameFixedTime, level : CpuDifficulty, budget : SearchBudget, cancel : CancellationToken, seed : u64) -> Self
+    {
+        match level
+        {
+            CpuDifficulty::Mcts => Self::Mcts(MctsSearch::new(state, budget, cancel, MctsConfig::default().with_seed(seed))),
+            _ => Self::Minimax(Box::new(MinimaxIterativeSearch::new(state, budget, cancel))),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn step(&mut self, slice : Option<f64>) -> bool
+    {
+        match self
+        {
+            Self::Minimax(s) => s.step(slice),
+            Self::Mcts(s) => s.step(slice),
+            Self::Known(_) => true,
+            Self::Determinized(s) => s.step(slice),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn result(&self) -> MinimaxResult
+    {
+        match self
+        {
+            Self::Minimax(s) => s.result(),
+            Self::Mcts(s) => s.result(),
+            Self::Known(r) => *r,
+            Self::Determinized(s) => s.result(),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn run(self) -> MinimaxResult
+    {
+        match self
+        {
+            Self::Minimax(s) => Self::run_minimax(*s),
+            Self::Mcts(s) => s.run(),
+            Self::Known(r) => r,
+            Self::Determinized(s) => s.run(),
+        }
+    }
+
+    #[cfg(all(not(target_arch = "wasm32"), not(feature = "parallel")))]
+    fn run_minimax(search : MinimaxIterativeSearch) -> MinimaxResult { search.run() }
+    /// Use all the cores
+    #[cfg(all(not(target_arch = "wasm32"), feature = "parallel"))]
+    fn run_minimax(search : MinimaxIterativeSearch) -> MinimaxResult
+    {
+        let nb_thread = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        search.state.minimax_parallel(search.budget, &search.cancel, nb_thread)
+    }
+}
+
+/// The searches of the positions sampled by `BoardGameFixedTime::determinized_samples()`
+#[derive(Debug)]
+pub struct GraphicBoardAiDeterminized
+{
+    /// The real position, to only vote for the valid actions
+    state : BoardGameFixedTime,
+    samples : Vec<BoardGameFixedTime>,
+    level : CpuDifficulty,
+    /// Budget of each sample
+    budget : SearchBudget,
+    cancel : CancellationToken,
+    seed : u64,
+    /// Created when the previous search is done, because the time of a search start at its creation
+    #[cfg(target_arch = "wasm32")]
+    current : Option<GraphicBoardAiSearch>,
+    results : Vec<MinimaxResult>,
+}
+impl GraphicBoardAiDeterminized
+{
+    fn new(state : BoardGameFixedTime, level : CpuDifficulty, budget : SearchBudget, cancel : CancellationToken, seed : u64) -> Self
+    {
+        let samples = state.determinized_samples(BoardGameFixedTime::DETERMINIZED_NB_SAMPLE, seed);
+        Self
+        {
+            state,
+            samples,
+            level,
+            budget: budget.split(BoardGameFixedTime::DETERMINIZED_NB_SAMPLE),
+            cancel,
+            seed,
+            #[cfg(target_arch = "wasm32")]
+            current: None,
+            results: vec![],
+        }
+    }
+
+    fn next_search(&mut self) -> Option<GraphicBoardAiSearch>
+    {
+        if self.cancel.is_cancelled() { return None; }
+        let sample = self.samples.pop()?;
+        Some(GraphicBoardAiSearch::new(sample, self.level, self.budget, self.cancel.clone(), self.seed))
+    }
+
+    fn result(&self) -> MinimaxResult { self.state.determinized_vote(&self.results) }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn run(mut self) -> MinimaxResult
+    {
+        while let Some(search) = self.next_search() { self.results.push(search.run()); }
+        self.result()
+    }
+
+    /// One sample after the other
+    #[cfg(target_arch = "wasm32")]
+    fn step(&mut self, slice : Option<f64>) -> bool
+    {
+        if self.current.is_none() { self.current = self.next_search(); }
+        let Some(search) = self.current.as_mut() else { return true; };
+        if search.step(slice)
+        {
+            self.results.push(search.result());
+            self.current = None;
+        }
+        false
+    }
+}
+
+/// A cpu search running in the background : in a thread on native, a few milliseconds at each frame on wasm
+#[derive(Debug)]
+pub struct GraphicBoardAiPending
+{
+    /// The search is discarded if the game is not at this position anymore
+    nb_played_actions : usize,
+    zobrist : ZobristHash,
+
+    cancel : CancellationToken,
+    #[cfg(not(target_arch = "wasm32"))]
+    thread : Option<std::thread::JoinHandle<MinimaxResult>>,
+    #[cfg(target_arch = "wasm32")]
+    search : GraphicBoardAiSearch,
+}
+impl Drop for GraphicBoardAiPending { fn drop(&mut self) { self.cancel.cancel(); } }
+impl GraphicBoardAiPending
+{
+    /// Time searched at each frame on wasm
+    #[cfg(target_arch = "wasm32")]
+    const SLICE_SECOND : f64 = 0.008;
+
+    fn new(game : &BoardGame, level : CpuDifficulty) -> Self
+    {
+        let cancel = CancellationToken::new();
+        let (state, budget) = (game.current().clone(), level.search_budget());
+        // a different opening at each game
+        let mut rng = Random::new(game.zobrist ^ (budget.clock)().to_bits());
+        // the opening book and the tablebases need the real position
+        let mut book = || OpeningBook::chess_default().choose(&state, &mut rng).map(|action_id| MinimaxResult { action_id: Some(action_id), from_book: true, ..___() });
+        let tablebase = || Tablebases::default_tables().iter().find_map(|tb| tb.best_action(&state))
+            .map(|(action_id, value)| MinimaxResult { action_id: Some(action_id), score: value.score(), from_tablebase: true, ..___() });
+        let known = if state.fog_of_war { None } else { book().or_else(tablebase) };
+        let search = match known
+        {
+            Some(result) => GraphicBoardAiSearch::Known(result),
+            None if state.fog_of_war => GraphicBoardAiSearch::Determinized(Box::new(GraphicBoardAiDeterminized::new(state, level, budget, cancel.clone(), rng.next_u64()))),
+            // a different seed for each position
+            None => GraphicBoardAiSearch::new(state, level, budget, cancel.clone(), game.zobrist),
+        };
+        Self
+        {
+            nb_played_actions: game.played_actions().len(),
+            zobrist: game.zobrist,
+            cancel,
+            #[cfg(not(target_arch = "wasm32"))]
+            thread: Some(std::thread::spawn(move || search.run())),
+            #[cfg(target_arch = "wasm32")]
+            search,
+        }
+    }
+
+    fn is_for(&self, game : &BoardGame) -> bool { self.nb_played_actions == game.played_actions().len() && self.zobrist == game.zobrist }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn is_done(&mut self) -> bool { self.thread.as_ref().is_none_or(|t| t.is_finished()) }
+    #[cfg(target_arch = "wasm32")]
+    fn is_done(&mut self) -> bool { self.search.step(Some(Self::SLICE_SECOND)) }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn result(mut self) -> Option<MinimaxResult> { self.thread.take()?.join().ok() }
+    #[cfg(target_arch = "wasm32")]
+    fn result(self) -> Option<MinimaxResult> { Some(self.search.result()) }
+}
+
+#[derive(Debug, Default)]
 pub struct GraphicBoardAi
 {
     //pub nb_action_force_brut : usize,
     pub result : MinimaxResult,
+    pub pending : Option<GraphicBoardAiPending>,
 }
 impl PartialEq for GraphicBoardAi { fn eq(&self, other: &Self) -> bool { true }}
+/// The pending search is not cloned
+impl Clone for GraphicBoardAi { fn clone(&self) -> Self { Self { result: self.result, pending: None } }}
 
 impl GraphicBoardGame
 {
@@ -17,4 +220,52 @@ impl GraphicBoardGame
         self.ai.result = self.ai_minimax_default();
         self.ai.result.action_id.unwrap()
     }
+
+    /// Start a search in the background if there is none for the current position.
+    /// Return the action when the search is done and `can_play` is true
+    pub fn ai_poll_best_move(&mut self, level : CpuDifficulty, can_play : bool) -> Option<ActionID>
+    {
+        if self.ai.pending.as_ref().is_some_and(|p| !p.is_for(&self.back_end)) { self.ai_cancel(); }
+
+        let pending = self.ai.pending.get_or_insert_with(|| GraphicBoardAiPending::new(&self.back_end, level));
+        if !pending.is_done() || !can_play { return None; }
+
+        let result = self.ai.pending.take().and_then(|p| p.result())?;
+        self.ai.result = result;
+        result.action_id.filter(|id| self.action_id_is_valid(*id))
+    }
+
+    pub fn ai_is_thinking(&self) -> bool { self.ai.pending.is_some() }
+
+    /// Discard the pending search (Undo, Redo, Home...)
+    pub fn ai_cancel(&mut self) { self.ai.pending = None; }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests
+{
+    use super::*;
+
+    /// The search run in its thread until the end, without the macroquad context
+    #[test]
+    fn ai_pending_search_in_a_thread()
+    {
+        let mut game = BoardGame::new_chess();
+        // out of the opening book
+        for san in ["a4", "h5"] { game.execute(game.san_to_action_id(san).unwrap()); }
+
+        for level in [CpuDifficulty::Easy, CpuDifficulty::Mcts]
+        {
+            let mut pending = GraphicBoardAiPending::new(&game, level);
+            let start = Instant::now();
+            while !pending.is_done()
+            {
+                assert!(start.elapsed() < Duration::from_secs(30), "{:?}", level);
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            let r = pending.result().unwrap();
+            assert!(!r.from_book);
+            assert!(r.action_id.is_some_and(|id| game.action_id_is_valid(id)), "{:?}", level);
+        }
+    }
 }
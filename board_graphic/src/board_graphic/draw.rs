@@ -142,17 +142,41 @@ impl GraphicBoardGame
         //Rect2::new(Vec2::ZERO, self.size().to_vec2() + edge_vec * 2.)
     }
 
+    pub fn color_fog(&self) -> Color { Color::BLACK.with_a(0.6) }
+
     pub fn draw_board_tile(&self, ctx : &mut Context) 
     {
-        let s = self.board_rect_with_edge();
-        ctx.pen.rectangle(s.pos, s.size, Vec2::ZERO, zero(), self.board_edge_color);
+        if self.have_void()
+        {
+            // the edge follow the shape of the board
+            let edge_vec = Vec2::splat(self.edge());
+            for p in self.size().iter_area().filter(|p| self.is_inside(*p))
+            {
+                ctx.pen.rectangle(p.to_vec() - edge_vec, Vec2::ONE + edge_vec * 2., Vec2::ZERO, zero(), self.board_edge_color);
+            }
+        }else
+        {
+            let s = self.board_rect_with_edge();
+            ctx.pen.rectangle(s.pos, s.size, Vec2::ZERO, zero(), self.board_edge_color);
+        }
 
-        for p in self.size().iter_area()
+        // the void tiles are not drawn
+        for p in self.size().iter_area().filter(|p| self.is_inside(*p))
         {
             self.draw_tile(p, self.tile_color_at(p), &mut ctx.pen);
         }
     }
 
+    /// Darken the tiles that the viewer can't see
+    pub fn draw_board_fog(&self, visibility : Option<&Visibility>, ctx : &mut Context)
+    {
+        let Some(visibility) = visibility else { return; };
+        for p in self.size().iter_area().filter(|p| self.is_inside(*p) && !visibility.is_visible(*p))
+        {
+            self.draw_tile(p, self.color_fog(), &mut ctx.pen);
+        }
+    }
+
     pub fn draw_board_piece_color(&self, c : &mut Context) 
     {
         /* 
@@ -184,10 +208,20 @@ impl GraphicBoardGame
         #[cfg(not(feature = "chantal_design"))]
         let piece_display_kind = PieceGraphicRep::Cburmeet;
 
+        if p.is_duck()
+        {
+            let texture = &ctx.globals.assets.img.relics;
+            let r = texture.sheet_rect_from_idx(Relic::DuckButDifferent as usize * 3);
+            ctx.pen.texture(texture, pos + Vec2::HALF, size, Vec2::HALF, DrawTexture::default().with_source(Some(r)).with_angle(angle));
+            return;
+        }
+
         let mut textures_y : [isize; 8] = [0; 8];
         let mut textures_len = 0;
 
-        // Todo : handle different move set
+        // the custom abilities are drawn with the sprites of the standard abilities that move like them
+        let mut p = p;
+        p.flags = self.look_like_standard(p.flags);
         if p.is_also_chess_pawn  () { textures_y[textures_len] = 6; textures_len+=1; }
         if p.is_also_chess_knight() { textures_y[textures_len] = 4; textures_len+=1; }
         // let this one here because it override rook and bishop
@@ -315,13 +349,30 @@ impl GraphicBoardGame
 
     } 
 
-    pub fn draw_board_piece(&self, time : GameTime, c : &mut Context) 
+    pub fn draw_promotion_choice(&self, time : GameTime, c : &mut Context) 
+    {
+        let tiles = self.promotion_choice_tiles();
+        if tiles.is_empty() { return; }
+
+        c.pen.rectangle(Vec2::ZERO, self.size().to_vec2(), Vec2::ZERO, zero(), Color::BLACK.with_a(0.5));
+        for (tile, action_id) in tiles
+        {
+            let mut piece = self[action_id.src()];
+            piece.flags = piece.flags.promote(action_id.promotion().unwrap(), false);
+
+            self.draw_tile(tile, self.tile_color_at(tile), &mut c.pen);
+            self.display_piece(piece, tile.to_vec2(), one(), ___(), time.total(), c);
+        }
+    }
+
+    pub fn draw_board_piece(&self, visibility : Option<&Visibility>, time : GameTime, c : &mut Context) 
     {
         for y in (0..self.size().y).rev()
         {
             for x in 0..self.size().x
             {
                 let draw_pos = at(x,y);
+                if visibility.is_some_and(|v| !v.is_visible(draw_pos)) { continue; }
                 self.display_piece_at(draw_pos, self.draw_piece_position(draw_pos, time, c), time.total(), c);
 
                 /* 
@@ -371,6 +422,37 @@ impl GraphicBoardGame
     }
 
 
+    pub fn draw_thinking(&self, time : GameTime, ctx : &mut DefaultContext<Glob>)
+    {
+        if !self.ai_is_thinking() { return; }
+
+        let nb_dot = (time.total().s() * 3.) as usize % 4;
+        let msg = format!("{} is thinking{}", self.current_team, ".".repeat(nb_dot));
+        let pos = vec2(self.size().x.to_real() / 2., self.size().y.to_real() - 0.5);
+        ctx.pen.text(&msg, pos, 0.5, half(), Color::WHITE.with_a(0.75), ___());
+    }
+
+    pub fn notification_duration() -> Time { 4.s() }
+
+    /// The last notification, fading out
+    pub fn draw_notification(&self, time : GameTime, ctx : &mut DefaultContext<Glob>)
+    {
+        let Some((msg, color, emitted)) = &self.notification else { return; };
+        let elapsed = time.total() - *emitted;
+        if elapsed > Self::notification_duration() { return; }
+
+        let pos = vec2(self.size().x.to_real() / 2., 1.);
+        ctx.pen.text(msg, pos, 0.4, half(), color.with_a(1. - elapsed.s() / Self::notification_duration().s()), ___());
+    }
+
+    /// The seed of a random back rank, to replay it with `seed = N` in the config
+    pub fn draw_layout_seed(&self, ctx : &mut DefaultContext<Glob>)
+    {
+        let Some(seed) = self.layout_seed else { return; };
+        let pos = vec2(self.size().x.to_real() / 2., 0.25);
+        ctx.pen.text(&format!("seed {}", seed), pos, 0.3, half(), Color::WHITE.with_a(0.5), ___());
+    }
+
     pub fn get_piece_captured_particle(&self, src : Option<At>, dest : At, time : GameTime) -> ParticlePieceCaptured
     {
         let v = if let Some(src) = src { dest.to_vec2() - src.to_vec2() } else { vec2((dest.x().to_real()-self.size().x.to_real()/2.).signum() * 3., 2.) };
@@ -485,12 +567,18 @@ impl GraphicBoardGame
         self.cam_board_begin(ctx);
         {
             ctx.pen.push_font(&ctx.globals.assets.img.ui.font.stanberry);
+            let visibility = self.fog_visibility();
             self.draw_board_tile(ctx);
-            self.draw_selector(time.total(), ctx);
+            self.draw_selector(visibility.as_ref(), time.total(), ctx);
             //self.draw_board_piece_color(c);
             self.draw_tile_explosion_particle(time, ctx);
-            self.draw_board_piece(time, ctx);
+            self.draw_board_fog(visibility.as_ref(), ctx);
+            self.draw_board_piece(visibility.as_ref(), time, ctx);
+            self.draw_promotion_choice(time, ctx);
             self.draw_end_message(time, ctx);
+            self.draw_thinking(time, ctx);
+            self.draw_layout_seed(ctx);
+            self.draw_notification(time, ctx);
             self.draw_captured_piece_particle(time, ctx);
 
             ctx.pen.pop_font();
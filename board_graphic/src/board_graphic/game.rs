@@ -7,6 +7,26 @@ pub enum CpuDifficulty
     Easy,
     Normal,
     Hard,
+    /// Monte Carlo Tree Search instead of minimax
+    Mcts,
+}
+impl CpuDifficulty
+{
+    /// Thinking time of the cpu, searched in the background
+    pub fn search_budget(self) -> SearchBudget
+    {
+        let budget = match self
+        {
+            CpuDifficulty::Easy   => SearchBudget::time(0.05).with_max_depth(2),
+            CpuDifficulty::Normal => SearchBudget::time(0.3).with_max_depth(3),
+            CpuDifficulty::Hard   => SearchBudget::time(1.5),
+            CpuDifficulty::Mcts   => SearchBudget::time(1.5),
+        };
+        // `Instant` is not available on wasm. The macroquad clock can only be read on the main thread, so not in the search thread of native
+        #[cfg(target_arch = "wasm32")]
+        let budget = budget.with_clock(macroquad::time::get_time);
+        budget
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Debug, Default)]
@@ -60,6 +80,9 @@ pub struct GraphicBoardGame
     pub particles_tile_explosion    : Vec<ParticleBase>,
 
     pub ai : GraphicBoardAi,
+
+    /// Message shown on the board for a few seconds (where the game was saved...), with its color and the time it was emitted
+    pub notification : Option<(String, Color, Time)>,
 }
 
 impl Deref for GraphicBoardGame { type Target = BoardGame; fn deref(&self) -> &Self::Target { &self.back_end }}
@@ -101,9 +124,59 @@ impl GraphicBoardGame
             },
             particles_captured_pieces: vec![],
             particles_tile_explosion: vec![], 
+            notification: None,
+        }
+    }
+
+    /// The human team that see the board with the fog of war : the current team, or the next human team when a cpu play.
+    /// `None` when every tile is visible (no fog of war, end of the game, or only cpus)
+    pub fn fog_viewer_human(&self) -> Option<Team>
+    {
+        let mut t = self.fog_viewer()?;
+        for _ in 0..Team::LENGHT
+        {
+            if self.players[t as usize].is_human() && self.team_data[t].alive { return Some(t); }
+            t = self.next_playing_team_after(t);
         }
+        None
+    }
+    pub fn fog_visibility(&self) -> Option<Visibility> { self.fog_viewer_human().map(|t| self.visibility(t)) }
+
+    /// With the fog of war, only the actions of the pieces of the viewer are shown
+    pub fn can_show_actions_at(&self, at : At) -> bool { self.fog_viewer_human().is_none_or(|t| self[at].is_also_team(t)) }
+
+    /// Add the kind of the players to the PGN tags
+    pub fn to_pgn(&self) -> Result<String, PgnError>
+    {
+        let white = format!("{:?}", self.players[Team::White as usize]);
+        let black = format!("{:?}", self.players[Team::Black as usize]);
+        self.back_end.to_pgn_with_tags(&[("WhiteKind", &white), ("BlackKind", &black)])
+    }
+
+    /// Save the PGN of the game in the `games` folder. Return the path of the file
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_pgn(&self) -> Result<String, String>
+    {
+        let pgn = self.to_pgn().map_err(|e| format!("can't export the game in PGN : {}", e))?;
+        let dir = "games";
+        std::fs::create_dir_all(dir).map_err(|e| format!("can't create the folder {} : {}", dir, e))?;
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        let path = format!("{}/game_{}.pgn", dir, timestamp);
+        std::fs::write(&path, pgn).map_err(|e| format!("can't save {} : {}", path, e))?;
+        Ok(path)
+    }
+    #[cfg(target_arch = "wasm32")]
+    pub fn save_pgn(&self) -> Result<String, String> { Err("the games can't be saved in the browser".to_owned()) }
+
+    /// Save the PGN and show where, or why it failed, on the board
+    pub fn save_pgn_and_notify(&mut self, time : Time)
+    {
+        self.notification = Some(match self.save_pgn()
+        {
+            Ok(path) => (format!("Game saved in {}", path), Color::WHITE, time),
+            Err(e) => (e, Color::RED, time),
+        });
     }
-        
 }
 
 impl IGame for GraphicBoardGame
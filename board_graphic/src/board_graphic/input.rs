@@ -12,6 +12,8 @@ pub enum GraphicActionID
     Hover (Option<At>),
     HoverTeam(Option<Team>),
     HoverTeamToggle,
+    /// Show the promotion picker of the move (src, dest), or hide it
+    PromotionChoice(Option<(At, At)>),
 }
 
 impl GraphicActionID
@@ -60,9 +62,10 @@ impl GraphicBoardGameInputProvider
         use GraphicActionID::*;
         use LogicActionID::*;
 
-        if c.input.key(KeyCode::U).just_pressed() { return Logic(Undo); }
-        if c.input.key(KeyCode::I).just_pressed() { return Logic(Redo); }
+        if c.input.key(KeyCode::U).just_pressed() { game.ai_cancel(); return Logic(Undo); }
+        if c.input.key(KeyCode::I).just_pressed() { game.ai_cancel(); return Logic(Redo); }
         if c.input.key(KeyCode::T).just_pressed() { return Graphic(HoverTeam(Some(game.current_team))); }
+        if c.input.key(KeyCode::S).just_pressed() { game.save_pgn_and_notify(time.total()); }
 
 
         if !game.is_end_of_the_game() && (c.input.key(KeyCode::Space).just_pressed() || c.input.key(KeyCode::Enter).just_pressed() || c.input.key(KeyCode::P).is_pressed())
@@ -84,23 +87,14 @@ impl GraphicBoardGameInputProvider
                 {
                     if input.have_side_effect() { input = ___(); }
 
-                    if !game.is_end_of_the_game() && time.elapsed_since_last_input().s() >= 0.4
+                    if game.is_end_of_the_game() { return input; }
+
+                    // the search start now, but the action is played after a small delay
+                    match game.ai_poll_best_move(*level, time.elapsed_since_last_input().s() >= 0.4)
                     {
-                        let action_id = game.minimax_custom((match level
-                            {
-                                // [0-1]
-                                CpuDifficulty::Easy => 
-                                {
-                                    (game.turn as usize / 2) % 2
-                                },
-                                // [2-3]
-                                CpuDifficulty::Normal => (game.turn as usize / 2) % 2 + 2,
-                                // [5]
-                                CpuDifficulty::Hard => 5,
-                            } + (game.turn - 96).max(0) as usize / 10).min(5)
-                        ).action_id.unwrap();
-                        Logic(DoAction(action_id))
-                    }else { input }
+                        Some(action_id) => Logic(DoAction(action_id)),
+                        None => input,
+                    }
                 },
             }
         }
@@ -137,27 +131,37 @@ impl GraphicBoardGameInputProvider
                 let dest_point2 = dest_vec.to_point2();
                 let dest =  dest_point2.map(|e| e as AtIntType);
 
+                if game.duck_to_place
+                {
+                    // a simple click is enough to place the duck
+                    let clicked = cursor_pressed || c.input.mouse().press().just_released();
+                    let action_id = ActionID::Duck(dest);
+                    return if clicked && game.action_id_is_valid(action_id) { Logic(DoAction(action_id)) } else { ___() };
+                }
+
+                if game.piece_selector.promotion_choice.is_some()
+                {
+                    if !cursor_pressed { return ___(); }
+                    return match game.promotion_choice_tiles().into_iter().find(|(tile, _)| *tile == dest)
+                    {
+                        Some((_, action_id)) => Logic(DoAction(action_id)),
+                        None => Graphic(PromotionChoice(None)),
+                    };
+                }
+
                 match (cursor_pressed, game.piece_selector.selected_piece)
                 {
                     (true, None) => 
                     {
                         if let Some(src) = game.piece_selector.hovered_piece
                         {
-                            let action_id = Move(src, dest);
-                            if game.action_id_is_valid(action_id)
-                            {
-                                return Logic(DoAction(Move(src, dest)));
-                            }
+                            if let Some(input) = self.move_input(game, src, dest) { return input; }
                         }
                         return Graphic(Select(Some(dest)));
                     },
                     (true, Some(src)) => 
                     {
-                        let action_id = ActionID::Move(src, dest);
-                        if game.action_id_is_valid(action_id)
-                        {
-                            return Logic(DoAction(Move(src, dest)));
-                        }
+                        if let Some(input) = self.move_input(game, src, dest) { return input; }
                         return Graphic(Select(Some(dest)));
                     },
                     (false, None) => 
@@ -229,6 +233,24 @@ impl GraphicBoardGameInputProvider
     }
 }
 
+impl GraphicBoardGameInputProvider
+{
+    /// Play the move, or let the player choose the promotion
+    fn move_input(&mut self, game : &GraphicBoardGame, src : At, dest : At) -> Option<GraphicBoardGameInput>
+    {
+        let action_id = ActionID::Move(src, dest);
+        if game.action_id_is_valid(action_id)
+        {
+            return Some(GraphicBoardGameInput::Logic(LogicActionID::DoAction(action_id)));
+        }
+        if game.actions.iter().any(|a| a.id.src() == src && a.id.dest() == dest && a.id.promotion().is_some())
+        {
+            return Some(GraphicBoardGameInput::Graphic(GraphicActionID::PromotionChoice(Some((src, dest)))));
+        }
+        None
+    }
+}
+
 impl IInputProvider<GraphicBoardGame> for GraphicBoardGameInputProvider
 {
     fn get_input(&mut self, game : &mut GraphicBoardGame, time : GameTime, c : &mut DefaultContext<Glob>) -> GraphicBoardGameInput {
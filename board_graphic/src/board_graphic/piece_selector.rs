@@ -12,6 +12,8 @@ pub struct PieceSelector
     pub selected_piece : Option<At>,
 
     pub avoid_hover_piece_at : Option<At>,
+    /// The move (src, dest) waiting for the player to choose the promotion
+    pub promotion_choice : Option<(At, At)>,
 
     pub action_piece_to_draw : Actions,
     pub action_team_to_draw : Actions,
@@ -47,18 +49,13 @@ impl GraphicBoardGame
                 self.hover_piece(None, time);
 
                 self.piece_selector.action_piece_to_draw.clear();
+                self.piece_selector.promotion_choice = None;
 
                 match l
                 {
                     LogicActionID::DoAction(a) => 
                     {
-                        match a
-                        {
-                            ActionID::Move(src, dest) => 
-                            { 
-                                self.piece_selector.avoid_hover_piece_at = Some(dest);
-                            },
-                        }
+                        self.piece_selector.avoid_hover_piece_at = Some(a.dest());
                     },
                     _ => { self.piece_selector.avoid_hover_piece_at = None; },
                 }
@@ -116,6 +113,11 @@ impl GraphicBoardGame
                     { 
                         self.hover_team(t, time, ctx);
                     },
+                    GraphicActionID::PromotionChoice(choice) => 
+                    {
+                        if choice.is_some() { ctx.audio.play(&ctx.globals.assets.sound.board.event.hover); }
+                        self.piece_selector.promotion_choice = choice;
+                    },
                     GraphicActionID::HoverTeamToggle => 
                     {
                         let next_hovered_team = match self.piece_selector.hovered_team
@@ -139,6 +141,19 @@ impl GraphicBoardGame
         }
     }
 
+    /// The tiles of the promotion picker, from the destination toward the team side
+    pub fn promotion_choice_tiles(&self) -> Vec<(At, ActionID)>
+    {
+        let Some((src, dest)) = self.piece_selector.promotion_choice else { return vec![]; };
+        let dir = self.current_team_direction();
+
+        self.actions.iter().map(|a| a.id).filter(|id| id.src() == src && id.dest() == dest && id.promotion().is_some())
+            .enumerate()
+            .map(|(i, id)| (dest - dir * i as AtIntType, id))
+            .filter(|(tile, _)| self.is_inside(*tile))
+            .collect()
+    }
+
     fn selector_update_piece_action_to_draw(&mut self) 
     {
         self.piece_selector.action_piece_to_draw.clear();
@@ -164,7 +179,7 @@ impl GraphicBoardGame
     {
         if let Some(s) = src
         {
-            if !self.is_inside(s) || self[s].is_empty_ability() { src = None; }
+            if !self.is_inside(s) || self[s].is_empty_ability() || !self.can_show_actions_at(s) { src = None; }
         }
 
         if self.piece_selector.selected_piece.is_some() != src.is_some() && self.piece_selector.hovered_piece != src
@@ -203,7 +218,8 @@ impl GraphicBoardGame
         self.piece_selector.action_team_to_draw.clear();
         match self.piece_selector.hovered_team
         {
-            Some(t) => 
+            // with the fog of war, only the actions of the viewer are shown
+            Some(t) if self.fog_viewer_human().is_none_or(|v| v == t) =>
             {
                 for hover in self.back_end.iter_idx_team(t)
                 {
@@ -211,7 +227,7 @@ impl GraphicBoardGame
                     self.back_end.actions_piece(&mut self.piece_selector.action_team_to_draw, hover, concerned_by_anticipation);
                 }
             },
-            None => {},
+            _ => {},
         }
     }
     
@@ -219,7 +235,7 @@ impl GraphicBoardGame
     {
         if let Some(s) = src
         {
-            if !self.is_inside(s) || self[s].is_empty_ability() { src = None; }
+            if !self.is_inside(s) || self[s].is_empty_ability() || !self.can_show_actions_at(s) { src = None; }
         }
         if src == self.piece_selector.avoid_hover_piece_at
         {
@@ -285,7 +301,7 @@ impl GraphicBoardGame
         }*/
     }
 
-    pub fn draw_selector(&self, time : Time, ctx : &mut Context)
+    pub fn draw_selector(&self, visibility : Option<&Visibility>, time : Time, ctx : &mut Context)
     {
         let (input, pen) = (&mut ctx.input, &mut ctx.pen);
 
@@ -313,12 +329,14 @@ impl GraphicBoardGame
         // Last Moved Piece Tile
         if self.turn > 0
         {
-            for at in self.iter_idx().filter(|e| self.was_moved_last_turn_or_this_turn(*e) && !self[*e].is_empty_ability())
+            // the moves in the fog are hidden
+            let is_visible = |a : At| visibility.is_none_or(|v| v.is_visible(a));
+            for at in self.iter_idx().filter(|e| self.was_moved_last_turn_or_this_turn(*e) && !self[*e].is_empty_ability() && is_visible(*e) && is_visible(self[*e].old_pos))
             {
                 self.draw_tile(self[at].old_pos, self.color_last_moved_src(self[at].old_pos), pen);
             }
 
-            for at in self.iter_idx().filter(|e| self.was_moved_last_turn_or_this_turn(*e) && !self[*e].is_empty_ability())
+            for at in self.iter_idx().filter(|e| self.was_moved_last_turn_or_this_turn(*e) && !self[*e].is_empty_ability() && is_visible(*e))
             {
                 self.draw_tile(at, self.color_last_moved_dest(at), pen);
             }
@@ -376,6 +394,15 @@ impl GraphicBoardGame
             pen.rectangle(at.to_vec() + Vec2::HALF, Vec2::ONE * time_effect, Vec2::HALF,  zero(), color_dest);
         }
 
+        // Duck placement
+        if self.duck_to_place && !self.is_end_of_the_game()
+        {
+            for a in self.actions.iter()
+            {
+                if let ActionID::Duck(dest) = a.id { pen.circle(dest.to_vec2() + Vec2::HALF, 0.15, color_dest.lerp(Color::BLACK, 0.3)); }
+            }
+        }
+
         for (idx, (actions, selector_time)) in [(&self.piece_selector.action_team_to_draw, self.piece_selector.hover_team_time), (&self.piece_selector.action_piece_to_draw, self.piece_selector.hover_piece_time)].into_iter().enumerate()
         {
             let line_tickness = 1. / if idx == 0 { 10. } else { 4. };
@@ -440,7 +467,7 @@ impl GraphicBoardGame
 
                 match &s.id
                 {
-                    ActionID::Move(src, dest) => 
+                    ActionID::Move(src, dest) | ActionID::Promote(src, dest, _) => 
                     {
                         let mut highlight_capture = 0.;
 
@@ -459,6 +486,7 @@ impl GraphicBoardGame
                         //pen.circle(dest_pos, line_tickness / 2. * (1.75 - t + 1.), c);
                         pen.circle(dest_pos, (move_dest_radius + t) * time_effect, c.lerp(Color::BLACK, 0.3).with_a(1.));
                     },
+                    ActionID::Duck(_) => {},
                 }
 
                 /* 
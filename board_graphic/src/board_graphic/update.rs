@@ -84,7 +84,8 @@ impl GraphicBoardGame
                             if piece.is_also_chess_knight() { ctx.audio.play(&ctx.globals.assets.sound.board.knight.moving); }
                         },
                         UnitAction::EnergyAdd(_) => {},
-                        UnitAction::Promote(_) => 
+                        UnitAction::DuckPlace(_, _) => { ctx.audio.play(&ctx.globals.assets.sound.board.event.piece_move); },
+                        UnitAction::Promote(_, _) => 
                         {
                             ctx.audio.play(&ctx.globals.assets.sound.board.promotion);
                         },
@@ -96,6 +97,7 @@ impl GraphicBoardGame
             },
             LogicActionID::Undo => 
             { 
+                self.ai_cancel();
                 ctx.audio.play(&ctx.globals.assets.sound.ui.undo);
                 self.undo();
                 for i in 0..Team::LENGHT - 1
@@ -108,6 +110,7 @@ impl GraphicBoardGame
             },
             LogicActionID::Redo => 
             { 
+                self.ai_cancel();
                 ctx.audio.play(&ctx.globals.assets.sound.ui.redo);
                 self.redo();
                 for i in 0..Team::LENGHT - 1
@@ -169,6 +169,10 @@ pub struct Menu
     ui    : UiManager<Self>,
 
     board : GameRunner<GraphicBoardGame>,
+    /// Game loaded from a PGN file, played when going in game
+    replay : Option<BoardGame>,
+    /// Rules loaded from a `GameConfig` file, used instead of the chess when going in game
+    config : Option<GameConfig>,
 
     //scene : MenuScene,
 }
@@ -184,6 +188,8 @@ impl Menu
         { 
             ui : ___(),
             board : GameRunner::new(GraphicBoardGame::new(BoardGame::new_chess(), players), ctx), 
+            replay : Self::load_pgn_from_args(),
+            config : Self::load_config_from_args(),
             //scene : ___()
         };
         //s.go_home(___(), ctx);
@@ -191,6 +197,29 @@ impl Menu
         s
     }
 
+    /// `board_graphic game.pgn` replay the game in the viewer
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_pgn_from_args() -> Option<BoardGame>
+    {
+        let path = std::env::args().nth(1).filter(|p| p.ends_with(".pgn"))?;
+        let pgn = std::fs::read_to_string(&path).map_err(|e| println!("can't read {} : {}", path, e)).ok()?;
+        BoardGame::from_pgn(&pgn).map_err(|e| println!("can't replay {} : {}", path, e)).ok()
+    }
+    #[cfg(target_arch = "wasm32")]
+    fn load_pgn_from_args() -> Option<BoardGame> { None }
+
+    /// `board_graphic variant.toml` play with the rules of the config
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_config_from_args() -> Option<GameConfig>
+    {
+        let path = std::env::args().nth(1).filter(|p| p.ends_with(".toml"))?;
+        let config = GameConfig::load(&path).map_err(|e| println!("can't load {} : {}", path, e)).ok()?;
+        config.build().map_err(|e| println!("can't build a game from {} : {}", path, e)).ok()?;
+        Some(config)
+    }
+    #[cfg(target_arch = "wasm32")]
+    fn load_config_from_args() -> Option<GameConfig> { None }
+
     fn ui_init_pop_up(&mut self, page_name : MenuUiPageName, time : Time, ctx : &mut Context)
     {
         self.ui.push_page(page_name);
@@ -298,7 +327,15 @@ impl Menu
         self.ui.push_page(MenuUiPageName::InGame);
         self.ui_init_page(ctx);
 
-        self.board = GameRunner::new(GraphicBoardGame::new(BoardGame::new_chess_custom(2, true, self.board.game.team_data[Team::White].relics), self.board.game.players), ctx);
+        let back_end = self.replay.take()
+            .or_else(|| self.config.as_ref().and_then(|c|
+            {
+                // a new random back rank for each game, the seed is shown on the board
+                let seed = c.seed.unwrap_or(macroquad::miniquad::date::now() as u64);
+                c.clone().with_seed(seed).build().ok()
+            }))
+            .unwrap_or_else(|| BoardGame::new_chess_custom(2, true, self.board.game.team_data[Team::White].relics));
+        self.board = GameRunner::new(GraphicBoardGame::new(back_end, self.board.game.players), ctx);
         //self.scene = MenuScene::InGame;
 
         ctx.audio.play(&ctx.globals.assets.sound.board.event.start);
@@ -485,7 +522,7 @@ impl Menu
                         if have_relic
                         {
                             self.ui.add_lambda().in_split_weight(0.5).add_color(Color::from_rgb_hex(0xAF2AA4)).add_color(Color::from_rgb_hex(0xC600AC));
-                            for r in [Relic::Anticipation, Relic::MoveTwiceInATurn, Relic::Explosive, Relic::Absorb]
+                            for r in [Relic::Anticipation, Relic::MoveTwiceInATurn, Relic::Explosive, Relic::Absorb, Relic::DuckButDifferent]
                             {
                                 self.ui.add_named(MenuUiName::Relic(r))
                                     .in_split_square()
@@ -494,7 +531,7 @@ impl Menu
                                         {
                                             Relic::Anticipation => BoardIcon::RelicAnticipation,
                                             Relic::Explosive => BoardIcon::RelicExplosif,
-                                            Relic::DuckButDifferent => BoardIcon::Slash,
+                                            Relic::DuckButDifferent => BoardIcon::RelicDuck,
                                             Relic::Absorb => BoardIcon::RelicAbsorb,
                                             Relic::MoveTwiceInATurn => BoardIcon::RelicMoveTwice,
                                         }
@@ -584,6 +621,7 @@ impl Menu
                     CpuDifficulty::Easy   => (BoardIcon::RobotEasy, Color::from_rgb_hex(0x52A542)),
                     CpuDifficulty::Normal => (BoardIcon::RobotNormal, Color::from_rgb_hex(0x50A3C4)),
                     CpuDifficulty::Hard   => (BoardIcon::RobotHard, Color::from_rgb_hex(0xD3324D)),
+                    CpuDifficulty::Mcts   => (BoardIcon::RobotHard, Color::from_rgb_hex(0x8E44AD)),
                 }
             }
         };
@@ -621,7 +659,8 @@ impl Menu
                         {
                             CpuDifficulty::Easy => PlayerKind::Cpu(CpuDifficulty::Normal),
                             CpuDifficulty::Normal => PlayerKind::Cpu(CpuDifficulty::Hard),
-                            CpuDifficulty::Hard => PlayerKind::Human,
+                            CpuDifficulty::Hard => PlayerKind::Cpu(CpuDifficulty::Mcts),
+                            CpuDifficulty::Mcts => PlayerKind::Human,
                         }
                     },
                 };
@@ -639,6 +678,7 @@ impl Menu
             },
             MenuInput::GoHome => 
             { 
+                self.board.game.ai_cancel();
                 self.ui.clear_pages();
                 self.go_home(time.total(), ctx);
             },